@@ -1,16 +1,18 @@
-/**
+/*
  * Structs and functions to deal largely with writing and reading 
  * block information to and from disk
  */
 
-use ramp::Int;
+use bigint::BigInt;
+use num_traits::Zero;
+use utils::hash256::Hash256;
 use primitives::block::BlockHeader;
 
 
 /*---- STRUCTS + ENUMS ----*/
 
 
-/**
+/*
  * The block chain is a tree shaped structure starting with the genesis block at the root, 
  * with each block potentially having multiple candidates to be the next block. 
  * 
@@ -47,16 +49,17 @@ pub struct Chain {
 
 /// Block index struct
 #[derive(Clone, Debug)]
+#[allow(dead_code)]
 pub struct BlockIndex {
-    pub block_hash: Int,
+    pub block_hash: Hash256,
     previous: Option<Box<BlockIndex>>,
     skip: Option<Box<BlockIndex>>,
-    height: u8,
+    pub height: u64,
 
     pub disk_position: DiskBlockPosition,
 
     undo_position: u8,
-    chain_work: Int,
+    chain_work: BigInt,
     tx: u8,
     chain_tx: u64,
     status: u32,
@@ -64,7 +67,7 @@ pub struct BlockIndex {
     pub time_max: u32,
 
     pub version: u32,
-    pub merkle_root_hash: Int,
+    pub merkle_root_hash: Hash256,
     pub time: u32,
     pub bits: u32,
     pub nonce: u32
@@ -72,6 +75,7 @@ pub struct BlockIndex {
 
 /// Info related to a block file
 #[derive(Clone, Debug)]
+#[allow(dead_code)]
 pub struct BlockFileInfo {
     blocks: u32,
     size: u32,
@@ -92,6 +96,12 @@ pub struct DiskBlockPosition {
 
 /*---- IMPLEMENTATIONS ----*/
 
+impl Default for BlockFileInfo {
+    fn default() -> BlockFileInfo {
+        BlockFileInfo::new()
+    }
+}
+
 impl BlockFileInfo {
 
     /// Generates a blank instance of the BlockFileInfo struct
@@ -136,6 +146,12 @@ impl BlockFileInfo {
 }
 
 
+impl Default for DiskBlockPosition {
+    fn default() -> DiskBlockPosition {
+        DiskBlockPosition::new()
+    }
+}
+
 impl DiskBlockPosition {
     pub fn new() -> DiskBlockPosition {
         DiskBlockPosition {
@@ -150,10 +166,16 @@ impl DiskBlockPosition {
 }
 
 
+impl Default for BlockIndex {
+    fn default() -> BlockIndex {
+        BlockIndex::new()
+    }
+}
+
 impl BlockIndex {
     pub fn new() -> BlockIndex {
         BlockIndex {
-            block_hash: Int::zero(),
+            block_hash: Hash256::zero(),
             previous: None,
             skip: None,
             height: 0,
@@ -161,7 +183,7 @@ impl BlockIndex {
             disk_position: DiskBlockPosition::new(),
 
             undo_position: 0,
-            chain_work: Int::zero(),
+            chain_work: BigInt::zero(),
             tx: 0,
             chain_tx: 0,
             status: 0,
@@ -169,7 +191,7 @@ impl BlockIndex {
             time_max: 0,
             
             version: 0,
-            merkle_root_hash: Int::zero(),
+            merkle_root_hash: Hash256::zero(),
             time: 0,
             bits: 0,
             nonce: 0
@@ -185,7 +207,7 @@ impl BlockIndex {
         let mut block_index = BlockIndex::new();
 
         block_index.version = header.version;
-        block_index.merkle_root_hash = header.merkle_root_hash.clone();
+        block_index.merkle_root_hash = header.merkle_root_hash;
         block_index.time = header.time;
         block_index.bits = header.bits;
         block_index.nonce = header.nonce;
@@ -210,12 +232,12 @@ impl BlockIndex {
         let mut block_header = BlockHeader::new();
 
         block_header.version = self.version;
-        block_header.merkle_root_hash = self.merkle_root_hash.clone();
+        block_header.merkle_root_hash = self.merkle_root_hash;
         block_header.nonce = self.nonce;
         block_header.time = self.time;
         block_header.bits = self.bits;
 
-        if !self.previous.is_none() {
+        if self.previous.is_some() {
             let previous_block = self.previous.clone().unwrap();
             block_header.previous_hash = previous_block.block_hash;
         }
@@ -224,6 +246,12 @@ impl BlockIndex {
     }
 }
 
+impl Default for Chain {
+    fn default() -> Chain {
+        Chain::new()
+    }
+}
+
 impl Chain {
     pub fn new() -> Chain {
         Chain {
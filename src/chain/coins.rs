@@ -1,18 +1,21 @@
-/**
+/*
  * Handling of "coins" for the chain. Important to note 
  * that coins as such do not exist in blockchains, only 
  * the ability to spend the value of a coin
  */
 
-use ramp::Int;
+use serialize::serialize;
+use utils::hash256::Hash256;
+use cryptography::hash::hash_256;
 use primitives::transaction::{ TxOut, OutPoint };
-use cryptography::hash::hash_message;
 use std::collections::HashMap;
 
 
 /*---- CONSTANTS ----*/
 
+#[allow(dead_code)]
 const SALT_1: [u8; 4] = [0,1,2,3];
+#[allow(dead_code)]
 const SALT_2: [u8; 4] = [4,5,6,7];
 
 
@@ -21,6 +24,7 @@ const SALT_2: [u8; 4] = [4,5,6,7];
 /// Abstract view of the open txout dataset
 ///  
 /// Equivalent to Bitcoin's CCoinsView
+#[allow(dead_code)]
 trait CoinSet {
 
     /// Retrieve the Coin (unspent transaction output) for a given outpoint.
@@ -32,13 +36,13 @@ trait CoinSet {
     fn have_coin(outpoint: &OutPoint) -> bool;
 
     /// Retrieve the block hash whose state this CoinSet currently represents
-    fn get_best_block() -> Int;
+    fn get_best_block() -> Hash256;
 
     /// Retrieve the range of blocks that may have been only partially written.
     /// If the database is in a consistent state, the result is the empty vector.
     /// Otherwise, a two-element vector is returned consisting of the new and
     /// the old block hash, in that order.
-    fn get_head_blocks() -> Vec<Int>;
+    fn get_head_blocks() -> Vec<Hash256>;
 
     // BATCH WRITE
     
@@ -60,14 +64,16 @@ pub struct Coin {
 }
 
 /// Equivalent of Bitcoin's CCoinsViewCache
+#[allow(dead_code)]
 pub struct CoinSetCache {
-    block_hash: Int,
-    coins_cache: HashMap<String, Coin>
+    block_hash: Hash256,
+    coins_cache: HashMap<Hash256, Coin>
 }
 
 
 /*---- IMPLEMENTATIONS ----*/
 
+#[allow(dead_code)]
 impl Coin {
     fn new() -> Coin {
         Coin {
@@ -80,8 +86,8 @@ impl Coin {
     fn new_from_data(tx_out: TxOut, is_coinbase: bool, height: u64) -> Coin {
         Coin {
             out: tx_out,
-            is_coinbase: is_coinbase,
-            height: height
+            is_coinbase,
+            height
         }
     }
 
@@ -90,10 +96,16 @@ impl Coin {
     }
 }
 
+impl Default for CoinSetCache {
+    fn default() -> CoinSetCache {
+        CoinSetCache::new()
+    }
+}
+
 impl CoinSetCache {
     pub fn new() -> CoinSetCache {
         CoinSetCache {
-            block_hash: Int::zero(),
+            block_hash: Hash256::zero(),
             coins_cache: HashMap::new()
         }
     }
@@ -114,19 +126,19 @@ impl CoinSetCache {
 
 /*---- FUNCTIONS ----*/
 
-pub fn salt_and_hash_outpoint(first_salt: &[u8], second_salt: &[u8], outpoint: &OutPoint) -> String {
+/// Hashes an outpoint together with two salts, for use as a cache key
+///
+/// ### Arguments
+///
+/// * `first_salt`  - First salt
+/// * `second_salt` - Second salt
+/// * `outpoint`    - Outpoint to hash
+pub fn salt_and_hash_outpoint(first_salt: &[u8], second_salt: &[u8], outpoint: &OutPoint) -> Hash256 {
     let mut message = Vec::<u8>::new();
-    let outpoint_clone = outpoint.clone();
 
     message.extend(first_salt);
     message.extend(second_salt);
-    message.extend(outpoint_clone.hash);
-    message.push(outpoint_clone.n as u8);
-
-    let result = match String::from_utf8(hash_message(&message)) {
-        Ok(r) => r,
-        Err(e) => panic!("Output of hash and salt outpoint is invalid: {}", e)
-    };
+    message.extend(serialize(outpoint));
 
-    result
+    hash_256(&message)
 }
\ No newline at end of file
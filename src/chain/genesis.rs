@@ -0,0 +1,224 @@
+/*
+ * Generation of genesis blocks for brand-new networks. Given a timestamp
+ * phrase, reward, difficulty and output key, this grinds a nonce until the
+ * genesis block satisfies its own proof-of-work, and can then print out a
+ * ChainParams definition to paste into chain/params.rs.
+ */
+
+use std::fmt;
+use std::error::Error;
+use bigint::BigInt;
+use num_traits::Zero;
+use chain::pow::check_proof_of_work;
+use utils::hex::to_hex;
+use utils::compact::set_compact;
+use consensus::params::ConsensusParams;
+use primitives::block::{ Block, create_raw_genesis_block, create_genesis_output_script };
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// Reasons a genesis block can't be turned into a ChainParams definition
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GenesisError {
+    MissingCoinbaseOutput   // Block has no transactions, or its first has no outputs
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl fmt::Display for GenesisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenesisError::MissingCoinbaseOutput => write!(f, "Genesis block has no coinbase output")
+        }
+    }
+}
+
+impl Error for GenesisError {}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Mines a genesis block paying `genesis_reward` to `output_key`. Nonces are
+/// tried in order from zero, and whenever they run out the time is bumped by
+/// a second and the search starts again. Returns None if `bits` does not
+/// describe a valid target, since no nonce could ever satisfy it, or if every
+/// nonce has been tried at every time up to the largest one.
+///
+/// ### Arguments
+///
+/// * `psz_timestamp`   - Timestamp phrase proving time of creation
+/// * `output_key`      - Public key the genesis reward is paid to
+/// * `time`            - Starting block time
+/// * `bits`            - Compact difficulty target
+/// * `version`         - Block version
+/// * `genesis_reward`  - Value of the coinbase output
+pub fn mine_genesis_block(
+    psz_timestamp: &str,
+    output_key: &[u8],
+    time: u32,
+    bits: u32,
+    version: u32,
+    genesis_reward: u64
+) -> Option<Block>
+{
+    let mut negative = false;
+    let mut overflow = false;
+    let target = set_compact(&bits, &mut negative, &mut overflow);
+
+    if negative || overflow || target == BigInt::zero() {
+        return None;
+    }
+
+    // The genesis block defines the network, so its own target is the limit
+    let mut params = ConsensusParams::new();
+    params.pow_limit = target;
+
+    let output_script = create_genesis_output_script(output_key);
+    let mut genesis = create_raw_genesis_block(psz_timestamp, &output_script, &time, &0, &bits, &version, &genesis_reward);
+
+    loop {
+        if check_proof_of_work(&genesis.header.get_hash(), &bits, &params) {
+            return Some(genesis);
+        }
+
+        match genesis.header.nonce.checked_add(1) {
+            Some(nonce) => genesis.header.nonce = nonce,
+            None => {
+                genesis.header.nonce = 0;
+                genesis.header.time = genesis.header.time.checked_add(1)?;
+            }
+        }
+    }
+}
+
+/// Prints a ready-to-paste ChainParams definition for a mined genesis block.
+/// The output is a `get_<network>_params` function in the same shape as the
+/// ones in chain/params.rs, with the genesis hash and merkle root asserted.
+/// Fails if the block has no coinbase output to take the reward and output
+/// script from.
+///
+/// ### Arguments
+///
+/// * `network_id`      - Name of the new network
+/// * `psz_timestamp`   - Timestamp phrase the genesis block was mined with
+/// * `genesis`         - Genesis block, as returned by `mine_genesis_block`
+pub fn get_chain_params_definition(network_id: &str, psz_timestamp: &str, genesis: &Block) -> Result<String, GenesisError> {
+    let header = &genesis.header;
+    let coinbase_out = match genesis.transactions.first().and_then(|tx| tx.outputs().first()) {
+        Some(output) => output,
+        None => return Err(GenesisError::MissingCoinbaseOutput)
+    };
+    let hash = header.get_hash();
+
+    let mut negative = false;
+    let mut overflow = false;
+    let pow_limit = set_compact(&header.bits, &mut negative, &mut overflow);
+
+    let fn_name: String = network_id.to_lowercase().chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    // Take the message start from the genesis hash, so it's unique per network
+    let message_start: Vec<String> = hash.as_bytes()[..4].iter().map(|b| format!("0x{:02x}", b)).collect();
+
+    let mut definition = String::new();
+
+    definition.push_str(&format!("/// {} network params\n", network_id));
+    definition.push_str("///\n");
+    definition.push_str("/// Needs `create_raw_genesis_block` and `utils::hex::from_hex` in scope, and a\n");
+    definition.push_str(&format!("/// \"{}\" => get_{}_params() arm in ChainParams::new\n", network_id, fn_name));
+    definition.push_str(&format!("fn get_{}_params() -> ChainParams {{\n", fn_name));
+    definition.push_str("    let mut consensus = ConsensusParams::new();\n\n");
+    definition.push_str("    // Handle consensus\n");
+    definition.push_str("    consensus.subsidy_halving_interval = 210000;\n");
+    definition.push_str(&format!("    consensus.pow_limit = BigInt::from_str_radix(\"{:0>64}\", 16).unwrap();\n", pow_limit.to_str_radix(16)));
+    definition.push_str("    consensus.pow_target_timespan = 14 * 24 * 60 * 60;  // two weeks\n");
+    definition.push_str("    consensus.pow_target_spacing = 10 * 60;             // 10 minutes\n");
+    definition.push_str("    consensus.rule_change_activation_threshold = 1916;  // 95% of 2016\n");
+    definition.push_str("    consensus.miner_confirmation_window = consensus.difficulty_adjustment_interval(); // should be 2016\n\n");
+    definition.push_str(&format!("    let message = vec![{}];\n\n", message_start.join(", ")));
+    definition.push_str("    // Handle genesis\n");
+    definition.push_str(&format!("    let genesis_output_script = from_hex(\"{}\").unwrap();\n", to_hex(&coinbase_out.script_public_key)));
+    definition.push_str(&format!(
+        "    let genesis = create_raw_genesis_block({:?}, &genesis_output_script, &{}, &{}, &0x{:08x}, &{}, &{});\n",
+        psz_timestamp, header.time, header.nonce, header.bits, header.version, coinbase_out.value.unwrap_or(0)
+    ));
+    definition.push_str("    consensus.genesis_block_hash = genesis.header.get_hash();\n\n");
+    definition.push_str(&format!("    assert_eq!(consensus.genesis_block_hash, Hash256::from_hex(\"{}\").unwrap());\n", hash));
+    definition.push_str(&format!("    assert_eq!(genesis.header.merkle_root_hash, Hash256::from_hex(\"{}\").unwrap());\n\n", header.merkle_root_hash));
+    definition.push_str("    // Checkpoint data\n");
+    definition.push_str("    let mut checkpoint_data = HashMap::new();\n");
+    definition.push_str("    checkpoint_data.insert(0, consensus.genesis_block_hash);\n\n");
+    definition.push_str("    // TX data\n");
+    definition.push_str("    let chain_tx_data = ChainTxData {\n");
+    definition.push_str(&format!("        time: {},\n", header.time));
+    definition.push_str("        tx_count: 1,\n");
+    definition.push_str("        tx_rate: 0.0\n");
+    definition.push_str("    };\n\n");
+    definition.push_str("    ChainParams {\n");
+    definition.push_str("        consensus,\n");
+    definition.push_str(&format!("        network_id: String::from({:?}),\n", network_id));
+    definition.push_str("        default_port: 8333,                 // change this to a free port\n");
+    definition.push_str("        prune_after_height: Some(1000),\n");
+    definition.push_str("        genesis_block: genesis,\n");
+    definition.push_str(&format!("        bech32_hrp: String::from({:?}),\n", fn_name));
+    definition.push_str("        fixed_seeds: Vec::new(),\n");
+    definition.push_str("        default_consistency_checks: false,\n");
+    definition.push_str("        require_standard: true,\n");
+    definition.push_str("        mine_blocks_on_demand: false,\n");
+    definition.push_str("        fallback_fee_enabled: false,\n");
+    definition.push_str("        checkpoint_data,\n");
+    definition.push_str("        chain_tx_data,\n");
+    definition.push_str("        message_start: message,\n");
+    definition.push_str("        seeds: Vec::new(),\n");
+    definition.push_str("        base58_prefixes: Vec::new()\n");
+    definition.push_str("    }\n");
+    definition.push_str("}\n");
+
+    Ok(definition)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::PSZ_TIMESTAMP;
+
+    const EASY_BITS: u32 = 0x207fffff;
+
+    #[test]
+    fn mined_block_passes_its_own_proof_of_work() {
+        let genesis = mine_genesis_block(PSZ_TIMESTAMP, &[0x02; 33], 1296688602, EASY_BITS, 1, 50).unwrap();
+
+        let mut params = ConsensusParams::new();
+        params.pow_limit = set_compact(&EASY_BITS, &mut false, &mut false);
+
+        assert!(check_proof_of_work(&genesis.header.get_hash(), &EASY_BITS, &params));
+        assert!(genesis.header.nonce < 64);
+    }
+
+    #[test]
+    fn invalid_bits_are_rejected() {
+        assert!(mine_genesis_block(PSZ_TIMESTAMP, &[0x02; 33], 0, 0, 1, 50).is_none());
+        assert!(mine_genesis_block(PSZ_TIMESTAMP, &[0x02; 33], 0, 0x04923456, 1, 50).is_none());
+        assert!(mine_genesis_block(PSZ_TIMESTAMP, &[0x02; 33], 0, 0xff123456, 1, 50).is_none());
+    }
+
+    #[test]
+    fn definition_needs_a_coinbase_output() {
+        let genesis = mine_genesis_block(PSZ_TIMESTAMP, &[0x02; 33], 0, EASY_BITS, 1, 50).unwrap();
+        let definition = get_chain_params_definition("Example Net", PSZ_TIMESTAMP, &genesis).unwrap();
+
+        assert!(definition.contains("fn get_example_net_params() -> ChainParams {"));
+        assert!(definition.contains(&genesis.header.get_hash().to_string()));
+
+        let mut empty = genesis.clone();
+        empty.transactions[0].outputs_mut().clear();
+        assert_eq!(get_chain_params_definition("Example Net", PSZ_TIMESTAMP, &empty), Err(GenesisError::MissingCoinbaseOutput));
+
+        empty.transactions.clear();
+        assert_eq!(get_chain_params_definition("Example Net", PSZ_TIMESTAMP, &empty), Err(GenesisError::MissingCoinbaseOutput));
+    }
+}
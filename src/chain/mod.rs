@@ -2,4 +2,5 @@ pub mod params;
 pub mod pow;
 pub mod coins;
 pub mod validation;
-pub mod chain_block_info;
\ No newline at end of file
+pub mod chain_block_info;
+pub mod genesis;
\ No newline at end of file
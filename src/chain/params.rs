@@ -1,4 +1,6 @@
-use ramp::Int;
+use bigint::BigInt;
+use num_traits::{ Num, Zero };
+use utils::hash256::Hash256;
 use utils::amount::COINS;
 use net::address::AddressSet;
 use std::collections::HashMap;
@@ -6,13 +8,14 @@ use consensus::params::ConsensusParams;
 use primitives::block::{ Block, create_genesis_block };
 
 /// Base58 encoding representation
+#[allow(dead_code)]
 enum Base58Type {
-    pubkey_address,
-    script_address,
-    secret_key,
-    ext_public_key,
-    ext_secret_key,
-    max_base58_types
+    PubkeyAddress,
+    ScriptAddress,
+    SecretKey,
+    ExtPublicKey,
+    ExtSecretKey,
+    MaxBase58Types
 }
 
 
@@ -22,6 +25,7 @@ enum Base58Type {
 /// verification progress during chain sync.
 ///
 /// See also: ChainParams::TxData, GuessVerificationProgress. (original Bitcoin)
+#[allow(dead_code)]
 pub struct ChainTxData {
     time: i64,      // UNIX timestamp of last known number of transactions
     tx_count: i64,  // total number of transactions between genesis and that timestamp
@@ -36,6 +40,7 @@ pub struct ChainTxData {
 ///      - the main network on which people trade goods and services
 ///      - the public test network which gets reset from time to time
 ///      - a regression test mode which is intended for private networks only. It has minimal difficulty to ensure that blocks can be found instantly.
+#[allow(dead_code)]
 pub struct ChainParams {
     consensus: ConsensusParams,
     default_port: u32,
@@ -51,19 +56,20 @@ pub struct ChainParams {
     fallback_fee_enabled: bool,
     chain_tx_data: ChainTxData,
     fixed_seeds: Vec<AddressSet>,
-    checkpoint_data: HashMap<u64, Int>,
+    checkpoint_data: HashMap<u64, Hash256>,
     message_start: Vec<u8> // possibly String?
 }
 
 
 /*----- IMPLEMENTATIONS -----*/
 
+#[allow(dead_code)]
 impl ChainParams {
     fn new(network_type: &str) -> ChainParams {
         match network_type {
             "main" => get_main_params(),
-            "test" => get_main_params(),        // get_test_params()
-            "regression" => get_main_params(),  // get_regression_params()
+            "test" => get_test_params(),
+            "regression" => get_regression_params(),
             _ => get_main_params()
         }
     }
@@ -74,18 +80,23 @@ impl ChainParams {
 
 
 /// Main network params
+#[allow(dead_code)]
 fn get_main_params() -> ChainParams {
     let mut consensus = ConsensusParams::new();
 
     // Handle consensus
-    consensus.subsidy_halving_interval = 21000;
-    consensus.bip16_exception = Int::from_str_radix("00000000000002dc756eebf4f49723ed8d30cc28a5f108eb94b1ba88ac4f9c22", 16).unwrap();
+    consensus.subsidy_halving_interval = 210000;
+    consensus.bip16_exception = Hash256::zero();   // Bitcoin's exception block doesn't exist on this chain
     consensus.bip34_height = 227931;
-    consensus.bip34_hash = Int::from_str_radix("000000000000024b89b42a942fe0d9fea3bb44ab7bd1b19115dd6a759c0808b8", 16).unwrap();
+    consensus.bip34_hash = Hash256::zero();        // Fill in once this chain reaches bip34_height
     consensus.bip65_height = 388381;
     consensus.bip66_height = 363725;
+    consensus.csv_height = 419328;
+    consensus.segwit_height = 481824;
+    consensus.taproot_height = 709632;
+    consensus.ctv_height = u64::MAX;
 
-    consensus.pow_limit = Int::from_str_radix("00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff", 16).unwrap();
+    consensus.pow_limit = BigInt::from_str_radix("00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff", 16).unwrap();
     consensus.pow_target_timespan = 14 * 24 * 60 * 60;  // two weeks
     consensus.pow_target_spacing = 10 * 60;             // 10 minutes
     consensus.rule_change_activation_threshold = 1916;  // 95% of 2016
@@ -105,10 +116,10 @@ fn get_main_params() -> ChainParams {
     // consensus.vDeployments[Consensus::DEPLOYMENT_SEGWIT].nTimeout = 1510704000; // November 15th, 2017.
 
     // The best chain should have at least this much work.
-    consensus.minimum_chain_work = Int::from_str_radix("0000000000000000000000000000000000000000028822fef1c230963535a90d", 16).unwrap();
+    consensus.minimum_chain_work = BigInt::zero();  // Raise this as the chain accumulates work
 
     // By default assume that the signatures in ancestors of this block are valid.
-    consensus.default_assume_valid = Int::from_str_radix("0000000000000000002e63058c023a9a1de233554f28c7b21380b6c9003f36a8", 16).unwrap();
+    consensus.default_assume_valid = Hash256::zero();  // None yet, so every signature is checked
 
     // The message start string is designed to be unlikely to occur in normal data.
     // The characters are rarely used upper ASCII, not valid as UTF-8, and produce
@@ -116,16 +127,15 @@ fn get_main_params() -> ChainParams {
     let message = vec![0xf9, 0xbe, 0xb4, 0xd9];
 
     // Handle genesis
-    let genesis = create_genesis_block(1231006505, 2083236893, 0x1d00ffff, 1, 50 * COINS);
-    // let genesis_hash = 
-    // consensus.genesis_block_hash = genesis_hash;
+    let genesis = create_genesis_block(1231006506, 122451214, 0x1d00ffff, 1, 50 * COINS);
+    consensus.genesis_block_hash = genesis.header.get_hash();
+
+    assert_eq!(consensus.genesis_block_hash, Hash256::from_hex("0000000001b5eb1b8050df6048eaa25b234ff08ee6f4e677bc951416e524398a").unwrap());
+    assert_eq!(genesis.header.merkle_root_hash, Hash256::from_hex("fdaf9201e9c4722c9b622b52c380b15ef8855ab140d9e19a4a13b0fea672bc8b").unwrap());
 
     // Checkpoint data
     let mut checkpoint_data = HashMap::new();
-    checkpoint_data.insert(11111, Int::from_str_radix("0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d", 16).unwrap());
-    checkpoint_data.insert(33333, Int::from_str_radix("000000002dd5588a74784eaa7ab0507a18ad16a236e7b1ce69f00d7ddfb5d0a6", 16).unwrap());
-    checkpoint_data.insert(74000, Int::from_str_radix("0000000000573993a3c9e41ce34471c079dcf5f52a0e824a81e7f953b8661a20", 16).unwrap());
-    checkpoint_data.insert(105000, Int::from_str_radix("00000000000291ce28027faea320c8d2b054b2e0fe44a773f3eefb151d6bdc97", 16).unwrap());
+    checkpoint_data.insert(0, consensus.genesis_block_hash);
 
     // TX data
     let chain_tx_data = ChainTxData {
@@ -144,7 +154,7 @@ fn get_main_params() -> ChainParams {
 
 
     ChainParams {
-        consensus: consensus,
+        consensus,
         network_id: String::from("main"),
         default_port: 8333,
         prune_after_height: Some(100000),
@@ -155,10 +165,179 @@ fn get_main_params() -> ChainParams {
         require_standard: true,
         mine_blocks_on_demand: false,
         fallback_fee_enabled: false,
-        checkpoint_data: checkpoint_data,
-        chain_tx_data: chain_tx_data,
+        checkpoint_data,
+        chain_tx_data,
         message_start: message,
         seeds: Vec::new(),                  // change this when real seeds come along
         base58_prefixes: Vec::new()         // change this guy too
     }
-}
\ No newline at end of file
+}
+
+
+/// Test network params
+#[allow(dead_code)]
+fn get_test_params() -> ChainParams {
+    let mut consensus = ConsensusParams::new();
+
+    // Handle consensus
+    consensus.subsidy_halving_interval = 210000;
+    consensus.bip16_exception = Hash256::zero();   // Bitcoin's exception block doesn't exist on this chain
+    consensus.bip34_height = 21111;
+    consensus.bip34_hash = Hash256::zero();        // Fill in once this chain reaches bip34_height
+    consensus.bip65_height = 581885;
+    consensus.bip66_height = 330776;
+    consensus.csv_height = 770112;
+    consensus.segwit_height = 834624;
+    consensus.taproot_height = u64::MAX;   // Not active on this testnet yet, fill in once a height is set
+    consensus.ctv_height = u64::MAX;
+
+    consensus.pow_limit = BigInt::from_str_radix("00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff", 16).unwrap();
+    consensus.pow_target_timespan = 14 * 24 * 60 * 60;  // two weeks
+    consensus.pow_target_spacing = 10 * 60;             // 10 minutes
+    consensus.pow_allow_min_difficulty_blocks = true;
+    consensus.rule_change_activation_threshold = 1512;  // 75% for testchains
+    consensus.miner_confirmation_window = consensus.difficulty_adjustment_interval(); // should be 2016
+
+    // The best chain should have at least this much work.
+    consensus.minimum_chain_work = BigInt::zero();  // Raise this as the chain accumulates work
+
+    // By default assume that the signatures in ancestors of this block are valid.
+    consensus.default_assume_valid = Hash256::zero();  // None yet, so every signature is checked
+
+    let message = vec![0x0b, 0x11, 0x09, 0x07];
+
+    // Handle genesis
+    let genesis = create_genesis_block(1296688602, 994398622, 0x1d00ffff, 1, 50 * COINS);
+    consensus.genesis_block_hash = genesis.header.get_hash();
+
+    assert_eq!(consensus.genesis_block_hash, Hash256::from_hex("000000005dd6281aac0a5b07a080f73b749486c3ffd36cf823d2d1ebc2855306").unwrap());
+    assert_eq!(genesis.header.merkle_root_hash, Hash256::from_hex("fdaf9201e9c4722c9b622b52c380b15ef8855ab140d9e19a4a13b0fea672bc8b").unwrap());
+
+    // Checkpoint data
+    let mut checkpoint_data = HashMap::new();
+    checkpoint_data.insert(0, consensus.genesis_block_hash);
+
+    // TX data
+    let chain_tx_data = ChainTxData {
+        time: 1531929919,
+        tx_count: 19438708,
+        tx_rate: 0.626
+    };
+
+    ChainParams {
+        consensus,
+        network_id: String::from("test"),
+        default_port: 18333,
+        prune_after_height: Some(1000),
+        genesis_block: genesis,
+        bech32_hrp: String::from("tb"),
+        fixed_seeds: Vec::new(),
+        default_consistency_checks: false,
+        require_standard: false,
+        mine_blocks_on_demand: false,
+        fallback_fee_enabled: true,
+        checkpoint_data,
+        chain_tx_data,
+        message_start: message,
+        seeds: Vec::new(),
+        base58_prefixes: Vec::new()
+    }
+}
+
+
+/// Regression test params
+#[allow(dead_code)]
+fn get_regression_params() -> ChainParams {
+    let mut consensus = ConsensusParams::new();
+
+    // Handle consensus
+    consensus.subsidy_halving_interval = 150;
+    consensus.bip34_height = 100000000; // BIP34 has not activated on regtest (far in the future so block v1 are not rejected in tests)
+    consensus.bip65_height = 1351;      // BIP65 activated on regtest (Used in rpc activation tests)
+    consensus.bip66_height = 1251;      // BIP66 activated on regtest (Used in rpc activation tests)
+    consensus.csv_height = 432;         // CSV activated on regtest (Used in rpc activation tests)
+    consensus.segwit_height = 0;        // SEGWIT is always activated on regtest unless overridden
+    consensus.taproot_height = 0;       // Taproot is always activated on regtest
+    consensus.ctv_height = 0;           // CTV is only deployed on regtest, for prototyping covenants
+
+    consensus.pow_limit = BigInt::from_str_radix("7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff", 16).unwrap();
+    consensus.pow_target_timespan = 14 * 24 * 60 * 60;  // two weeks
+    consensus.pow_target_spacing = 10 * 60;             // 10 minutes
+    consensus.pow_allow_min_difficulty_blocks = true;
+    consensus.pow_no_retargeting = true;
+    consensus.rule_change_activation_threshold = 108;   // 75% for testchains
+    consensus.miner_confirmation_window = 144;          // Faster than normal for regtest (144 instead of 2016)
+
+    let message = vec![0xfa, 0xbf, 0xb5, 0xda];
+
+    // Handle genesis
+    let genesis = create_genesis_block(1296688602, 0, 0x207fffff, 1, 50 * COINS);
+    consensus.genesis_block_hash = genesis.header.get_hash();
+
+    assert_eq!(consensus.genesis_block_hash, Hash256::from_hex("0929c2a2b3004f63e0f4546c796c56b2365edc30f7ee44ac77ea3772fae22e92").unwrap());
+    assert_eq!(genesis.header.merkle_root_hash, Hash256::from_hex("fdaf9201e9c4722c9b622b52c380b15ef8855ab140d9e19a4a13b0fea672bc8b").unwrap());
+
+    // Checkpoint data
+    let mut checkpoint_data = HashMap::new();
+    checkpoint_data.insert(0, consensus.genesis_block_hash);
+
+    // TX data
+    let chain_tx_data = ChainTxData {
+        time: 0,
+        tx_count: 0,
+        tx_rate: 0.0
+    };
+
+    ChainParams {
+        consensus,
+        network_id: String::from("regression"),
+        default_port: 18444,
+        prune_after_height: Some(1000),
+        genesis_block: genesis,
+        bech32_hrp: String::from("bcrt"),
+        fixed_seeds: Vec::new(),
+        default_consistency_checks: true,
+        require_standard: false,
+        mine_blocks_on_demand: true,
+        fallback_fee_enabled: true,
+        checkpoint_data,
+        chain_tx_data,
+        message_start: message,
+        seeds: Vec::new(),
+        base58_prefixes: Vec::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain::pow::check_proof_of_work;
+
+    #[test]
+    fn genesis_blocks_pass_proof_of_work() {
+        for network_type in ["main", "test", "regression"].iter() {
+            let params = ChainParams::new(network_type);
+            let header = &params.genesis_block.header;
+
+            assert!(check_proof_of_work(&header.get_hash(), &header.bits, &params.consensus), "{} genesis", network_type);
+        }
+    }
+    #[test]
+    fn no_bitcoin_block_hashes_are_carried_over() {
+        for network_type in ["main", "test", "regression"].iter() {
+            let params = ChainParams::new(network_type);
+
+            assert!(params.consensus.bip16_exception.is_zero(), "{}", network_type);
+            assert!(params.consensus.bip34_hash.is_zero(), "{}", network_type);
+            assert!(params.consensus.default_assume_valid.is_zero(), "{}", network_type);
+            assert_eq!(params.consensus.minimum_chain_work, BigInt::zero(), "{}", network_type);
+
+            // The only block known in advance is the genesis block
+            let mut checkpoints = HashMap::new();
+            checkpoints.insert(0, params.consensus.genesis_block_hash);
+
+            assert_eq!(params.checkpoint_data, checkpoints, "{}", network_type);
+        }
+    }
+}
@@ -1,8 +1,10 @@
-/**
+/*
  * Functions that deal with PoW. Super incomplete
  */
 
-use ramp::Int;
+use bigint::BigInt;
+use num_traits::Zero;
+use utils::hash256::Hash256;
 use utils::compact::set_compact;
 use consensus::params::ConsensusParams;
 
@@ -13,7 +15,7 @@ use consensus::params::ConsensusParams;
 ///
 /// Check whether a block hash satisfies the proof-of-work 
 /// requirement specified by `bits`
-pub fn check_proof_of_work(hash: &Int, bits: &u32, params: &ConsensusParams) -> bool {
+pub fn check_proof_of_work(hash: &Hash256, bits: &u32, params: &ConsensusParams) -> bool {
     let mut negative = false;
     let mut overflow = false;
     let target = set_compact(bits, &mut negative, &mut overflow);
@@ -21,14 +23,14 @@ pub fn check_proof_of_work(hash: &Int, bits: &u32, params: &ConsensusParams) ->
     // Range check
     if 
        negative || overflow ||
-       target == Int::zero() ||
+       target == BigInt::zero() ||
        target > params.pow_limit
     {
         return false;
     }
 
     // Check PoW matches the claimed amount
-    if *hash > target {
+    if hash.to_int() > target {
         return false;
     }
 
@@ -1,7 +1,9 @@
-use ramp::Int;
+use bigint::BigInt;
 use std::sync::{ Arc, Mutex };
 use std::collections::HashMap;
 use chain::chain_block_info::BlockIndex;
+use consensus::params::ConsensusParams;
+use script_lang::flags::ScriptVerifyFlags;
 
 
 /*---- STRUCTS ----*/
@@ -19,6 +21,7 @@ use chain::chain_block_info::BlockIndex;
 /// other class member functions, pure functions in other parts of the consensus
 /// library, callbacks via the validation interface, or read/write-to-disk
 /// functions (eventually this will also be via callbacks).
+#[allow(dead_code)]
 pub struct ChainState {
     block_index_candidates: HashMap<BlockIndex, u8>, // u8?
 
@@ -33,7 +36,7 @@ pub struct ChainState {
     block_sequence_reverse_id: i64,
 
     // chainwork for the last block that preciousblock has been applied to.
-    last_precious_block_chain_work: Int,
+    last_precious_block_chain_work: BigInt,
  
     // In order to efficiently track invalidity of headers, we keep the set of
     // blocks which we tried to connect and found to be invalid here (ie which
@@ -58,4 +61,109 @@ pub struct ChainState {
     // the ChainState CriticalSection
     // A lock that must be held when modifying this ChainState - held in ActivateBestChain()
     chain_state_arc: Mutex<u8>
-}
\ No newline at end of file
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Script verification flags that are consensus rules for the given block,
+/// based on which soft forks are active at its height
+///
+/// ### Arguments
+///
+/// * `index`   - Index of the block being connected
+/// * `params`  - Consensus params of the chain
+pub fn get_block_script_flags(index: &BlockIndex, params: &ConsensusParams) -> ScriptVerifyFlags {
+    let mut flags = ScriptVerifyFlags::NONE;
+
+    // BIP16 didn't become active until Apr 1 2012 (on mainnet, and
+    // retroactively applied to testnet). However, only one historical block
+    // violated the P2SH rules (on both mainnet and testnet), so for
+    // simplicity, always leave P2SH on except for the one violating block.
+    if params.bip16_exception.is_zero() || index.block_hash != params.bip16_exception {
+        flags |= ScriptVerifyFlags::P2SH;
+    }
+
+    // Enforce WITNESS rules whenever P2SH is in effect (and the segwit
+    // deployment is defined)
+    if flags.contains(ScriptVerifyFlags::P2SH) && params.segwit_height != u64::MAX {
+        flags |= ScriptVerifyFlags::WITNESS;
+    }
+
+    // Start enforcing the DERSIG (BIP66) rule
+    if index.height >= params.bip66_height {
+        flags |= ScriptVerifyFlags::DERSIG;
+    }
+
+    // Start enforcing CHECKLOCKTIMEVERIFY (BIP65) rule
+    if index.height >= params.bip65_height {
+        flags |= ScriptVerifyFlags::CHECKLOCKTIMEVERIFY;
+    }
+
+    // Start enforcing BIP112 (CHECKSEQUENCEVERIFY)
+    if index.height >= params.csv_height {
+        flags |= ScriptVerifyFlags::CHECKSEQUENCEVERIFY;
+    }
+
+    // Start enforcing BIP147 NULLDUMMY (activated simultaneously with segwit)
+    if index.height >= params.segwit_height {
+        flags |= ScriptVerifyFlags::NULLDUMMY;
+    }
+
+    // Start enforcing taproot (BIP341 and BIP342) spending rules
+    if index.height >= params.taproot_height {
+        flags |= ScriptVerifyFlags::TAPROOT;
+    }
+
+    // Start enforcing OP_CHECKTEMPLATEVERIFY (BIP119)
+    if index.height >= params.ctv_height {
+        flags |= ScriptVerifyFlags::CHECKTEMPLATEVERIFY;
+    }
+
+    flags
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soft_forks_are_undeployed_by_default() {
+        let params = ConsensusParams::new();
+        let mut index = BlockIndex::new();
+        index.height = 1000000;
+
+        let flags = get_block_script_flags(&index, &params);
+
+        assert!(flags.contains(ScriptVerifyFlags::P2SH));
+        assert!(!flags.contains(ScriptVerifyFlags::WITNESS));
+        assert!(!flags.contains(ScriptVerifyFlags::CHECKSEQUENCEVERIFY));
+        assert!(!flags.contains(ScriptVerifyFlags::NULLDUMMY));
+        assert!(!flags.contains(ScriptVerifyFlags::TAPROOT));
+        assert!(!flags.contains(ScriptVerifyFlags::CHECKTEMPLATEVERIFY));
+    }
+
+    #[test]
+    fn soft_forks_activate_at_their_heights() {
+        let mut params = ConsensusParams::new();
+        params.csv_height = 10;
+        params.segwit_height = 20;
+        params.taproot_height = 30;
+        params.ctv_height = 40;
+
+        let mut index = BlockIndex::new();
+        index.height = 29;
+
+        let flags = get_block_script_flags(&index, &params);
+
+        assert!(flags.contains(ScriptVerifyFlags::WITNESS | ScriptVerifyFlags::CHECKSEQUENCEVERIFY | ScriptVerifyFlags::NULLDUMMY));
+        assert!(!flags.contains(ScriptVerifyFlags::TAPROOT));
+
+        index.height = 40;
+
+        let flags = get_block_script_flags(&index, &params);
+
+        assert!(flags.contains(ScriptVerifyFlags::TAPROOT | ScriptVerifyFlags::CHECKTEMPLATEVERIFY));
+    }
+}
@@ -1,6 +1,7 @@
 use std::fs::File;
 use std::path::Path;
 use std::io::{ BufRead, BufReader };
+use utils::amount::Amount;
 
 
 /*---- STRUCTS ---- */
@@ -24,12 +25,13 @@ pub struct Config {
     rpc_user: Option<String>,
     rpc_password: Option<String>,
     rpc_timeout: u8,
+    #[allow(dead_code)]
     rpc_allowed_ips: Option<Vec<String>>,
     rpc_port: String,
     rpc_ssl: bool,
     rpc_details: Option<RPCDetails>,
     keypool: u8,
-    pay_tx_fee: f32,
+    pay_tx_fee: Amount,
     allow_receive_by_ip: bool
 }
 
@@ -63,7 +65,7 @@ impl Config {
             rpc_ssl: false,
             rpc_details: None,
             keypool: 0,
-            pay_tx_fee: 0.00,
+            pay_tx_fee: Amount::ZERO,
             allow_receive_by_ip: false
         }
     }
@@ -141,7 +143,12 @@ pub fn read_config(path: &str) -> Config {
             "rpcsslcertificatechainfile" => { rpc_details.cert_file = String::from(value); },
             "rpcsslprivatekeyfile" => { rpc_details.key_file = String::from(value); },
             "keypool" => { config.keypool = value.parse::<u8>().unwrap(); },
-            "paytxfee" => { config.pay_tx_fee = value.parse::<f32>().unwrap(); },
+            "paytxfee" => {
+                match value.parse::<Amount>() {
+                    Ok(fee) => { config.pay_tx_fee = fee; },
+                    Err(e) => println!("Ignoring paytxfee {}: {}", value, e)
+                }
+            },
             _ => println!("Property {property} not catered to", property = property)
         }
     }
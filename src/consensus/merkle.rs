@@ -0,0 +1,249 @@
+/*
+ * Merkle tree computation over transaction hashes.
+ *
+ * SATOSHI NAKAMOTO (BITCOIN TEAM):
+ *
+ * WARNING! If you're reading this because you're learning about crypto
+ * and/or designing a new system that will use merkle trees, keep in mind
+ * that the following merkle tree algorithm has a serious flaw related to
+ * duplicate txids, resulting in a vulnerability (CVE-2012-2459).
+ *
+ * The reason is that if the number of hashes in the list at a given level
+ * is odd, the last one is duplicated before computing the next level (which
+ * is unusual in Merkle trees). This results in certain sequences of
+ * transactions leading to the same merkle root. For example, these two
+ * trees:
+ *
+ *                    A               A
+ *                  /  \            /   \
+ *                B     C         B       C
+ *               / \    |        / \     / \
+ *              D   E   F       D   E   F   F
+ *             / \ / \ / \     / \ / \ / \ / \
+ *             1 2 3 4 5 6     1 2 3 4 5 6 5 6
+ *
+ * for transaction lists [1,2,3,4,5,6] and [1,2,3,4,5,6,5,6] (where 5 and
+ * 6 are repeated) result in the same root hash A (because the hash of both
+ * of (F) and (F,F) is C).
+ *
+ * The vulnerability results from being able to send a block with such a
+ * transaction list, with the same merkle root, and the same block hash as
+ * the original without duplication, resulting in failed validation. If the
+ * receiving node proceeds to mark that block as permanently invalid
+ * however, it will fail to accept further unmodified (and thus potentially
+ * valid) versions of the same block. We defend against this by detecting
+ * the case where we would hash two identical hashes at the end of the list
+ * together, and treating that identically to the block having an invalid
+ * merkle root. Assuming no double-SHA256 collisions, this will detect all
+ * known ways of changing the transactions without affecting the merkle
+ * root.
+ */
+
+use utils::hash256::Hash256;
+use cryptography::hash::double_hash;
+
+
+/*---- STRUCTS ----*/
+
+/// A merkle inclusion proof: the sibling hashes on the path from a leaf
+/// up to the root, along with the leaf's position in the tree
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerkleBranch {
+    pub hashes: Vec<Hash256>,
+    pub index: usize
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl MerkleBranch {
+
+    /// Computes the root this branch leads to from the given leaf
+    ///
+    /// ### Arguments
+    ///
+    /// * `leaf`    - Hash of the leaf the branch was built for
+    pub fn get_root(&self, leaf: &Hash256) -> Hash256 {
+        compute_merkle_root_from_branch(leaf, &self.hashes, self.index)
+    }
+
+    /// Checks that the leaf is included in the tree with the given root
+    ///
+    /// ### Arguments
+    ///
+    /// * `leaf`    - Hash of the leaf the branch was built for
+    /// * `root`    - Expected merkle root
+    pub fn verify(&self, leaf: &Hash256, root: &Hash256) -> bool {
+        self.get_root(leaf) == *root
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Hashes two child nodes together into their parent
+///
+/// ### Arguments
+///
+/// * `left`    - Left child
+/// * `right`   - Right child
+pub fn hash_pair(left: &Hash256, right: &Hash256) -> Hash256 {
+    let mut message = Vec::with_capacity(64);
+
+    message.extend_from_slice(left.as_bytes());
+    message.extend_from_slice(right.as_bytes());
+
+    double_hash(&message)
+}
+
+/// Computes the merkle root of a list of leaves. The returned flag is set
+/// if two identical hashes were hashed together anywhere in the tree, which
+/// means the leaf list was mutated as described at the top of this file.
+///
+/// ### Arguments
+///
+/// * `leaves`  - Leaf hashes, in order
+pub fn compute_merkle_root(leaves: &[Hash256]) -> (Hash256, bool) {
+    if leaves.is_empty() {
+        return (Hash256::zero(), false);
+    }
+
+    let mut level = leaves.to_vec();
+    let mut mutated = false;
+
+    while level.len() > 1 {
+        for pair in level.chunks(2) {
+            if pair.len() == 2 && pair[0] == pair[1] {
+                mutated = true;
+            }
+        }
+
+        if level.len() % 2 == 1 {
+            let last = level[level.len() - 1];
+            level.push(last);
+        }
+
+        level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+    }
+
+    (level[0], mutated)
+}
+
+/// Builds the merkle branch proving inclusion of the leaf at `index`
+///
+/// ### Arguments
+///
+/// * `leaves`  - Leaf hashes, in order
+/// * `index`   - Position of the leaf to prove
+pub fn compute_merkle_branch(leaves: &[Hash256], index: usize) -> Option<MerkleBranch> {
+    if index >= leaves.len() {
+        return None;
+    }
+
+    let mut level = leaves.to_vec();
+    let mut position = index;
+    let mut hashes = Vec::new();
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            let last = level[level.len() - 1];
+            level.push(last);
+        }
+
+        hashes.push(level[position ^ 1]);
+
+        level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+        position >>= 1;
+    }
+
+    Some(MerkleBranch {
+        hashes,
+        index
+    })
+}
+
+/// Walks a merkle branch from a leaf up to the root it commits to
+///
+/// ### Arguments
+///
+/// * `leaf`    - Leaf hash
+/// * `branch`  - Sibling hashes from the leaf upwards
+/// * `index`   - Position of the leaf in the tree
+pub fn compute_merkle_root_from_branch(leaf: &Hash256, branch: &[Hash256], index: usize) -> Hash256 {
+    let mut hash = *leaf;
+    let mut position = index;
+
+    for sibling in branch {
+        hash = match position & 1 {
+            1 => hash_pair(sibling, &hash),
+            _ => hash_pair(&hash, sibling)
+        };
+
+        position >>= 1;
+    }
+
+    hash
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u8) -> Vec<Hash256> {
+        (1..=count).map(|n| Hash256::from_bytes([n; 32])).collect()
+    }
+
+    #[test]
+    fn small_trees() {
+        let l = leaves(3);
+
+        assert_eq!(compute_merkle_root(&[]), (Hash256::zero(), false));
+        assert_eq!(compute_merkle_root(&l[..1]), (l[0], false));
+        assert_eq!(compute_merkle_root(&l[..2]), (hash_pair(&l[0], &l[1]), false));
+
+        // An odd leaf is paired with itself, which doesn't count as mutation
+        let expected = hash_pair(&hash_pair(&l[0], &l[1]), &hash_pair(&l[2], &l[2]));
+        assert_eq!(compute_merkle_root(&l), (expected, false));
+    }
+
+    #[test]
+    fn duplicated_leaves_are_flagged_as_mutated() {
+        let original = leaves(6);
+
+        // [1,2,3,4,5,6] and [1,2,3,4,5,6,5,6] share a root (CVE-2012-2459)
+        let mut duplicated = original.clone();
+        duplicated.extend_from_slice(&original[4..]);
+
+        let (root, mutated) = compute_merkle_root(&original);
+        assert!(!mutated);
+        assert_eq!(compute_merkle_root(&duplicated), (root, true));
+
+        // Likewise for an explicitly duplicated last leaf
+        let l = leaves(3);
+        let mut duplicated = l.clone();
+        duplicated.push(l[2]);
+
+        assert_eq!(compute_merkle_root(&duplicated), (compute_merkle_root(&l).0, true));
+    }
+
+    #[test]
+    fn branches_verify_against_the_root() {
+        for count in 1..=9 {
+            let l = leaves(count);
+            let (root, _) = compute_merkle_root(&l);
+
+            for (index, leaf) in l.iter().enumerate() {
+                let branch = compute_merkle_branch(&l, index).unwrap();
+
+                assert_eq!(branch.index, index);
+                assert_eq!(branch.get_root(leaf), root);
+                assert!(branch.verify(leaf, &root), "leaf {} of {}", index, count);
+                assert!(!branch.verify(&Hash256::zero(), &root));
+                assert!(!branch.verify(leaf, &Hash256::zero()));
+            }
+
+            assert_eq!(compute_merkle_branch(&l, l.len()), None);
+        }
+    }
+}
@@ -1 +1,8 @@
-pub mod params;
\ No newline at end of file
+pub mod params;
+pub mod merkle;
+
+
+/*---- CONSTANTS ----*/
+
+/// Weight of a non-witness byte relative to a witness byte (BIP141)
+pub const WITNESS_SCALE_FACTOR: usize = 4;
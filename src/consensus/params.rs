@@ -1,14 +1,22 @@
-use ramp::Int;
+use bigint::BigInt;
+use num_traits::Zero;
+use utils::hash256::Hash256;
 
 /// Parameters that influence chain consensus.
 pub struct ConsensusParams {
-    pub genesis_block_hash: Vec<u8>,
-    pub subsidy_halving_interval: u8,
-    pub bip16_exception: Int,   // Block hash that is excepted from BIP16 enforcement
+    pub genesis_block_hash: Hash256,
+    pub subsidy_halving_interval: u32,
+    pub bip16_exception: Hash256,  // Block hash that is excepted from BIP16 enforcement
     pub bip34_height: u64,      // Block height and hash at which BIP34 becomes active
-    pub bip34_hash: Int,
+    pub bip34_hash: Hash256,
     pub bip65_height: u64,      // Block height at which BIP65 becomes active
     pub bip66_height: u64,      // Block height at which BIP66 becomes active
+    pub csv_height: u64,        // Block height at which CSV (BIP68, BIP112 and BIP113) becomes active
+    pub segwit_height: u64,     // Block height at which segwit (BIP141, BIP143 and BIP147) becomes active.
+                                // Set to u64::MAX to disable segwit entirely.
+    pub taproot_height: u64,    // Block height at which taproot (BIP340, BIP341 and BIP342) becomes active
+    pub ctv_height: u64,        // Block height at which OP_CHECKTEMPLATEVERIFY (BIP119) becomes active.
+                                // Set to u64::MAX to leave it undeployed.
 
     // Minimum blocks including miner confirmation of the total of 2016 blocks in a retargeting period,
     // (nPowTargetTimespan / nPowTargetSpacing) which is also used for BIP9 deployments.
@@ -19,35 +27,45 @@ pub struct ConsensusParams {
 
     // PoW Parameters
     // These params tend to be associated with mining difficulty
-    pub pow_limit: Int,
+    pub pow_limit: BigInt,
     pub pow_allow_min_difficulty_blocks: bool,
     pub pow_no_retargeting: bool,
     pub pow_target_spacing: i64,
     pub pow_target_timespan: i64,
-    pub minimum_chain_work: Int,
-    pub default_assume_valid: Int,
+    pub minimum_chain_work: BigInt,
+    pub default_assume_valid: Hash256,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> ConsensusParams {
+        ConsensusParams::new()
+    }
 }
 
 impl ConsensusParams {
     pub fn new() -> ConsensusParams {
         ConsensusParams {
-            genesis_block_hash: Vec::new(),
+            genesis_block_hash: Hash256::zero(),
             subsidy_halving_interval: 0,
-            bip16_exception: Int::zero(),
+            bip16_exception: Hash256::zero(),
             bip34_height: 0,      
-            bip34_hash: Int::zero(),
+            bip34_hash: Hash256::zero(),
             bip65_height: 0, 
             bip66_height: 0,
+            csv_height: u64::MAX,       // Soft forks stay undeployed until a network sets a height
+            segwit_height: u64::MAX,
+            taproot_height: u64::MAX,
+            ctv_height: u64::MAX,
             rule_change_activation_threshold: 0,
             miner_confirmation_window: 0,
             // BIP9Deployment vDeployments[MAX_VERSION_BITS_DEPLOYMENTS];
-            pow_limit: Int::zero(),
+            pow_limit: BigInt::zero(),
             pow_allow_min_difficulty_blocks: false,
             pow_no_retargeting: false,
             pow_target_spacing: 0,
             pow_target_timespan: 0,
-            minimum_chain_work: Int::zero(),
-            default_assume_valid: Int::zero()
+            minimum_chain_work: BigInt::zero(),
+            default_assume_valid: Hash256::zero()
         }
     }
 
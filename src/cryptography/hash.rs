@@ -1,7 +1,11 @@
 use sha3;
 use blake2;
+use sha1::Sha1;
+use sha2::Sha256;
+use ripemd160::Ripemd160;
 use digest::Digest;
 use tiny_keccak::Keccak;
+use utils::hash256::Hash256;
 use cryptography::HASH_ALGORITHM;
 
 
@@ -26,7 +30,7 @@ pub enum HashAlgorithm {
 /// * `message` - Message to sign
 /// * `protocol` - Hash protocol to use
 pub fn hash_message(message: &[u8]) -> Vec<u8> {
-    let result = match HASH_ALGORITHM {
+    match HASH_ALGORITHM {
         HashAlgorithm::Blake2b => blake2::Blake2b::digest(message).to_vec(),
         HashAlgorithm::Blake2s => blake2::Blake2s::digest(message).to_vec(),
         HashAlgorithm::Sha3_256 => sha3::Sha3_256::digest(message).to_vec(),
@@ -35,21 +39,108 @@ pub fn hash_message(message: &[u8]) -> Vec<u8> {
             let mut keccak = Keccak::new_keccak256();
             let mut res: [u8; 32] = [0; 32];
 
-            keccak.update(&message);
+            keccak.update(message);
             keccak.finalize(&mut res);
 
-            return res.to_vec();
+            res.to_vec()
         },
         HashAlgorithm::Keccak512 => {
             let mut keccak = Keccak::new_keccak512();
             let mut res: [u8; 64] = [0; 64];
 
-            keccak.update(&message);
+            keccak.update(message);
             keccak.finalize(&mut res);
 
-            return res.to_vec();
+            res.to_vec()
         }
-    };
+    }
+}
+
+/// Hash a message down to a fixed 256-bit hash. Algorithms with a wider
+/// output are truncated to their first 32 bytes
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn hash_256(message: &[u8]) -> Hash256 {
+    // Every allowed algorithm outputs at least 32 bytes
+    Hash256::from_slice(&hash_message(message)[..32]).unwrap()
+}
+
+/// Hash a message twice over, as Bitcoin does for txids and block hashes
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn double_hash(message: &[u8]) -> Hash256 {
+    hash_256(&hash_message(message))
+}
+
 
-    result
-}
\ No newline at end of file
+/*---- SCRIPT HASH FUNCTIONS ----*/
+
+// Scripts keep Bitcoin's fixed hash functions, independent of HASH_ALGORITHM,
+// so that hash locks and key hashes mean the same thing as they do elsewhere.
+
+/// SHA-1 of a message (OP_SHA1)
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn sha1(message: &[u8]) -> Vec<u8> {
+    Sha1::digest(message).to_vec()
+}
+
+/// SHA-256 of a message (OP_SHA256)
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn sha256(message: &[u8]) -> Vec<u8> {
+    Sha256::digest(message).to_vec()
+}
+
+/// RIPEMD-160 of a message (OP_RIPEMD160)
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn ripemd160(message: &[u8]) -> Vec<u8> {
+    Ripemd160::digest(message).to_vec()
+}
+
+/// RIPEMD-160 of the SHA-256 of a message (OP_HASH160)
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn hash160(message: &[u8]) -> Vec<u8> {
+    ripemd160(&sha256(message))
+}
+
+/// SHA-256 applied twice (OP_HASH256)
+///
+/// ### Arguments
+///
+/// * `message` - Message to hash
+pub fn sha256d(message: &[u8]) -> Vec<u8> {
+    sha256(&sha256(message))
+}
+
+/// SHA-256 of a message prefixed with the hash of a tag, twice (BIP340).
+/// Hashes made for different purposes can then never collide.
+///
+/// ### Arguments
+///
+/// * `tag`     - Name of the purpose the hash is for, such as "TapLeaf"
+/// * `message` - Message to hash
+pub fn tagged_hash(tag: &str, message: &[u8]) -> Vec<u8> {
+    let tag_hash = sha256(tag.as_bytes());
+    let mut preimage = Vec::with_capacity(64 + message.len());
+
+    preimage.extend_from_slice(&tag_hash);
+    preimage.extend_from_slice(&tag_hash);
+    preimage.extend_from_slice(message);
+
+    sha256(&preimage)
+}
@@ -1,8 +1,59 @@
-/**
+/*
  * Generation and handling of signing and encryption keys, 
  * for use in transactions and blocks.
  * 
  * Bitcoin uses ECDSA seckp256 for its keypair generation. 
  */
 
+use std::convert::TryFrom;
 use sodiumoxide::crypto::sign;
+pub use sodiumoxide::crypto::sign::{ PublicKey, SecretKey };
+
+
+/*---- CONSTANTS ----*/
+
+/// Size of a serialized public key
+pub const PUBLIC_KEY_SIZE: usize = sign::PUBLICKEYBYTES;
+
+/// Size of a signature, without any sighash type byte
+pub const SIGNATURE_SIZE: usize = sign::SIGNATUREBYTES;
+
+
+/*---- FUNCTIONS ----*/
+
+/// Generates a new random keypair
+pub fn generate_keypair() -> (PublicKey, SecretKey) {
+    sign::gen_keypair()
+}
+
+/// Signs a message, returning the raw signature bytes
+///
+/// ### Arguments
+///
+/// * `message`     - Message to sign
+/// * `secret_key`  - Key to sign with
+pub fn sign_message(message: &[u8], secret_key: &SecretKey) -> Vec<u8> {
+    sign::sign_detached(message, secret_key).as_ref().to_vec()
+}
+
+/// Verifies a raw signature over a message against a raw public key.
+/// Malformed signatures or keys simply fail to verify.
+///
+/// ### Arguments
+///
+/// * `signature`   - Raw signature bytes
+/// * `message`     - Message that was signed
+/// * `public_key`  - Raw public key bytes
+pub fn verify_signature(signature: &[u8], message: &[u8], public_key: &[u8]) -> bool {
+    let signature = match sign::Signature::try_from(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false
+    };
+
+    let public_key = match PublicKey::from_slice(public_key) {
+        Some(public_key) => public_key,
+        None => return false
+    };
+
+    sign::verify_detached(&signature, message, &public_key)
+}
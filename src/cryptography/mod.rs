@@ -1,4 +1,6 @@
 pub mod hash;
+pub mod keys;
+pub mod schnorr;
 
 /*---- CONSTANTS ----*/
 
@@ -0,0 +1,146 @@
+/*
+ * BIP340 Schnorr signatures over secp256k1, used by witness version 1
+ * (taproot) spends. Public keys are x-only: just the 32 byte X coordinate,
+ * with the Y coordinate implied to be even.
+ *
+ * Keys can be tweaked by adding a multiple of the generator, which is how a
+ * taproot output key commits to its tree of scripts.
+ */
+
+use secp256k1::{ SECP256K1, Message, Parity, Scalar, XOnlyPublicKey };
+use secp256k1::schnorr::Signature;
+use secp256k1::rand::thread_rng;
+pub use secp256k1::Keypair;
+
+
+/*---- CONSTANTS ----*/
+
+/// Size of an x-only public key
+pub const SCHNORR_PUBLIC_KEY_SIZE: usize = 32;
+
+/// Size of a signature, without any sighash type byte
+pub const SCHNORR_SIGNATURE_SIZE: usize = 64;
+
+
+/*---- FUNCTIONS ----*/
+
+/// Generates a new random keypair
+pub fn generate_keypair() -> Keypair {
+    Keypair::new(SECP256K1, &mut thread_rng())
+}
+
+/// The x-only public key of a keypair
+///
+/// ### Arguments
+///
+/// * `keypair` - Keypair to get the public key of
+pub fn get_public_key(keypair: &Keypair) -> Vec<u8> {
+    keypair.x_only_public_key().0.serialize().to_vec()
+}
+
+/// Signs a 32 byte message hash, returning the raw signature bytes, or
+/// None if the message isn't 32 bytes
+///
+/// ### Arguments
+///
+/// * `message` - Message hash to sign
+/// * `keypair` - Keypair to sign with
+pub fn sign(message: &[u8], keypair: &Keypair) -> Option<Vec<u8>> {
+    let message = Message::from_digest_slice(message).ok()?;
+
+    Some(SECP256K1.sign_schnorr(&message, keypair).as_ref().to_vec())
+}
+
+/// Verifies a raw signature over a 32 byte message hash against an x-only
+/// public key. Malformed signatures or keys simply fail to verify.
+///
+/// ### Arguments
+///
+/// * `signature`   - Raw signature bytes
+/// * `message`     - Message hash that was signed
+/// * `public_key`  - Raw x-only public key
+pub fn verify(signature: &[u8], message: &[u8], public_key: &[u8]) -> bool {
+    let signature = match Signature::from_slice(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false
+    };
+
+    let public_key = match XOnlyPublicKey::from_slice(public_key) {
+        Ok(public_key) => public_key,
+        Err(_) => return false
+    };
+
+    let message = match Message::from_digest_slice(message) {
+        Ok(message) => message,
+        Err(_) => return false
+    };
+
+    SECP256K1.verify_schnorr(&signature, &message, &public_key).is_ok()
+}
+
+/// Adds `tweak` times the generator to a public key, returning the x-only
+/// result and whether its Y coordinate is odd. Fails if the key is invalid,
+/// the tweak is not below the group order, or the result is infinity.
+///
+/// ### Arguments
+///
+/// * `public_key`  - Raw x-only public key to tweak
+/// * `tweak`       - 32 byte big-endian tweak
+pub fn tweak_public_key(public_key: &[u8], tweak: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let public_key = XOnlyPublicKey::from_slice(public_key).ok()?;
+    let (tweaked, parity) = public_key.add_tweak(SECP256K1, &to_scalar(tweak)?).ok()?;
+
+    Some((tweaked.serialize().to_vec(), parity == Parity::Odd))
+}
+
+/// Checks that a tweaked key, with the given Y parity, is a public key
+/// plus `tweak` times the generator. Cheaper than tweaking and comparing.
+///
+/// ### Arguments
+///
+/// * `tweaked_key` - Raw x-only key claimed to be the tweaked key
+/// * `odd`         - Whether the tweaked key's Y coordinate is odd
+/// * `public_key`  - Raw x-only key before tweaking
+/// * `tweak`       - 32 byte big-endian tweak
+pub fn check_tweak(tweaked_key: &[u8], odd: bool, public_key: &[u8], tweak: &[u8]) -> bool {
+    let tweaked_key = match XOnlyPublicKey::from_slice(tweaked_key) {
+        Ok(key) => key,
+        Err(_) => return false
+    };
+
+    let public_key = match XOnlyPublicKey::from_slice(public_key) {
+        Ok(key) => key,
+        Err(_) => return false
+    };
+
+    let tweak = match to_scalar(tweak) {
+        Some(tweak) => tweak,
+        None => return false
+    };
+
+    let parity = if odd { Parity::Odd } else { Parity::Even };
+
+    public_key.tweak_add_check(SECP256K1, &tweaked_key, parity, tweak)
+}
+
+/// Tweaks a keypair so that it signs for the tweaked public key
+///
+/// ### Arguments
+///
+/// * `keypair` - Keypair to tweak
+/// * `tweak`   - 32 byte big-endian tweak, as given to `tweak_public_key`
+pub fn tweak_keypair(keypair: &Keypair, tweak: &[u8]) -> Option<Keypair> {
+    keypair.add_xonly_tweak(SECP256K1, &to_scalar(tweak)?).ok()
+}
+
+/// Reads a 32 byte big-endian tweak, which has to be below the group order
+fn to_scalar(tweak: &[u8]) -> Option<Scalar> {
+    if tweak.len() != 32 {
+        return None;
+    }
+
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(tweak);
+
+    Scalar::from_be_bytes(bytes).ok()
+}
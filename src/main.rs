@@ -1,10 +1,14 @@
 extern crate num_bigint as bigint;
-extern crate ramp;
+extern crate num_traits;
 extern crate sha3;
 extern crate blake2;
 extern crate tiny_keccak;
 extern crate digest;
 extern crate sodiumoxide;
+extern crate sha2;
+extern crate sha1;
+extern crate ripemd160;
+extern crate secp256k1;
 
 pub mod config;
 pub mod net;
@@ -12,19 +16,22 @@ pub mod primitives;
 pub mod utils;
 pub mod chain;
 pub mod consensus;
+pub mod policy;
 pub mod cryptography;
 pub mod script_lang;
+pub mod serialize;
 
-use ramp::int::Int;
+use bigint::BigInt;
+use num_traits::Num;
 
 fn main() {
-    let value = Int::from_str_radix("00000000000002dc756eebf4f49723ed8d30cc28a5f108eb94b1ba88ac4f9c22", 16).unwrap();
-    let bytes_original = String::from("00000000000002dc756eebf4f49723ed8d30cc28a5f108eb94b1ba88ac4f9c22").bytes().len();
+    let value = BigInt::from_str_radix("00000000000002dc756eebf4f49723ed8d30cc28a5f108eb94b1ba88ac4f9c22", 16).unwrap();
+    let bytes_original = String::from("00000000000002dc756eebf4f49723ed8d30cc28a5f108eb94b1ba88ac4f9c22").len();
 
     println!("bytes: {}", bytes_original);
 
-    let hex_value = value.to_str_radix(16, false);
-    let bytes = hex_value.bytes().len();
+    let hex_value = value.to_str_radix(16);
+    let bytes = hex_value.len();
 
     println!("bytes second: {}", bytes);
 
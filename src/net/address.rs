@@ -12,6 +12,7 @@ pub enum ServiceFlag {
 
 /// Equivalent to Bitcon's CService
 #[derive(Clone, Debug)]
+#[allow(dead_code)]
 pub struct AddressSet {
     address: String,
     port: u64
@@ -19,6 +20,7 @@ pub struct AddressSet {
 
 /// Equivalent to Bitcon's CAddress
 #[derive(Clone, Debug)]
+#[allow(dead_code)]
 pub struct AddressAsPeer {
     address: AddressSet,
     time: u64,
@@ -28,21 +30,23 @@ pub struct AddressAsPeer {
 
 /*---- IMPLEMENTATIONS ----*/
 
+#[allow(dead_code)]
 impl AddressSet {
     fn new(address: String, port: u64) -> AddressSet {
         AddressSet {
-            address: address,
-            port: port
+            address,
+            port
         }
     }
 }
 
+#[allow(dead_code)]
 impl AddressAsPeer {
     fn new(address_set: AddressSet, time: u64, services: ServiceFlag) -> AddressAsPeer {
         AddressAsPeer {
             address: address_set,
-            time: time,
-            services: services
+            time,
+            services
         }
     }
 }
\ No newline at end of file
@@ -7,6 +7,7 @@ pub mod node;
 
 /*---- Constants ----*/
 
+#[allow(dead_code)]
 const LISTEN: bool = true;
 
 
@@ -14,7 +15,7 @@ const LISTEN: bool = true;
 
 
 // pub fn advertise_local(peer: &Peer) {
-//     if LISTEN && peer.successfullyConnected {
+//     if LISTEN && peer.successfully_connected {
 //         local_address_set = address::AddressSet("127.0.01", )
 //     }
 // }
\ No newline at end of file
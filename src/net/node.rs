@@ -2,12 +2,13 @@ use net::address::{ AddressAsPeer, ServiceFlag };
 
 /// Equivalent to Bitcon's CNode
 #[derive(Clone, Debug)]
+#[allow(dead_code)]
 pub struct Peer {
     id: u64,
     services: ServiceFlag,
-    startingHeight: u8,
+    starting_height: u8,
     address: AddressAsPeer,
-    successfullyConnected: bool
+    successfully_connected: bool
     // keyedNetGroupIn
     // localHostNonceIn
     // socket
@@ -0,0 +1,50 @@
+/*
+ * Policy rules. These are not consensus, so blocks that break them are still
+ * valid, but transactions that break them are not relayed or mined by
+ * default. Stricter rules here are how soft forks are made safe to deploy.
+ */
+
+use script_lang::flags::ScriptVerifyFlags;
+
+
+/*---- CONSTANTS ----*/
+
+/// Mandatory script verification flags that all new blocks must comply with
+/// for them to be valid (but old blocks may not comply with). Currently just
+/// P2SH, but in the future other flags may be added.
+///
+/// Failing one of these tests may trigger a DoS ban, so take care not to
+/// ban nodes for violating rules they were never expected to follow.
+pub const MANDATORY_SCRIPT_VERIFY_FLAGS: ScriptVerifyFlags = ScriptVerifyFlags::P2SH;
+
+/// Standard script verification flags that standard transactions will comply
+/// with. Transactions failing these are not relayed, but may still be valid
+/// in blocks.
+pub const STANDARD_SCRIPT_VERIFY_FLAGS: ScriptVerifyFlags = ScriptVerifyFlags::from_bits(
+    MANDATORY_SCRIPT_VERIFY_FLAGS.bits() |
+    ScriptVerifyFlags::DERSIG.bits() |
+    ScriptVerifyFlags::STRICTENC.bits() |
+    ScriptVerifyFlags::MINIMALDATA.bits() |
+    ScriptVerifyFlags::NULLDUMMY.bits() |
+    ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS.bits() |
+    ScriptVerifyFlags::CLEANSTACK.bits() |
+    ScriptVerifyFlags::MINIMALIF.bits() |
+    ScriptVerifyFlags::NULLFAIL.bits() |
+    ScriptVerifyFlags::CHECKLOCKTIMEVERIFY.bits() |
+    ScriptVerifyFlags::CHECKSEQUENCEVERIFY.bits() |
+    ScriptVerifyFlags::LOW_S.bits() |
+    ScriptVerifyFlags::WITNESS.bits() |
+    ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM.bits() |
+    ScriptVerifyFlags::WITNESS_PUBKEYTYPE.bits() |
+    ScriptVerifyFlags::CONST_SCRIPTCODE.bits() |
+    ScriptVerifyFlags::TAPROOT.bits() |
+    ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION.bits() |
+    ScriptVerifyFlags::DISCOURAGE_OP_SUCCESS.bits() |
+    ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_PUBKEYTYPE.bits() |
+    ScriptVerifyFlags::CHECKTEMPLATEVERIFY.bits()
+);
+
+/// For convenience, standard but not mandatory verify flags
+pub const STANDARD_NOT_MANDATORY_VERIFY_FLAGS: ScriptVerifyFlags = ScriptVerifyFlags::from_bits(
+    STANDARD_SCRIPT_VERIFY_FLAGS.bits() & !MANDATORY_SCRIPT_VERIFY_FLAGS.bits()
+);
@@ -1,8 +1,14 @@
-use ramp::Int;
-use utils::PSZ_TIMESTAMP;
-use primitives::transaction::Transaction;
-
-/** 
+use utils::hex::from_hex;
+use primitives::SEQUENCE_FINAL;
+use utils::hash256::Hash256;
+use cryptography::hash::double_hash;
+use utils::{ PSZ_TIMESTAMP, GENESIS_OUTPUT_KEY };
+use primitives::transaction::{ Transaction, TxIn, TxOut };
+use script_lang::script::{ Script, OpCodes };
+use consensus::merkle::{ MerkleBranch, compute_merkle_root, compute_merkle_branch };
+use serialize::{ Encodable, Decodable, Reader, SerializeError, serialize };
+
+/* 
  * Nodes collect new transactions into a block, hash them into a hash tree,
  * and scan through nonce values to make the block's hash satisfy proof-of-work
  * requirements. When they solve the proof-of-work, they broadcast the block
@@ -18,8 +24,8 @@ use primitives::transaction::Transaction;
 #[derive(Clone, Debug)]
 pub struct BlockHeader {
     pub version: u32,
-    pub previous_hash: Int,
-    pub merkle_root_hash: Int,
+    pub previous_hash: Hash256,
+    pub merkle_root_hash: Hash256,
     pub time: u32,
     pub bits: u32,
     pub nonce: u32
@@ -36,6 +42,7 @@ pub struct Block {
 /// other node doesn't have the same branch, it can find a recent common trunk.
 /// The further back it is, the further before the fork it may be.
 #[derive(Clone, Debug)]
+#[allow(dead_code)]
 pub struct BlockLocator {
     have: Vec<u8>
 }
@@ -43,12 +50,18 @@ pub struct BlockLocator {
 
 /*---- IMPLEMENTATIONS ----*/
 
+impl Default for BlockHeader {
+    fn default() -> BlockHeader {
+        BlockHeader::new()
+    }
+}
+
 impl BlockHeader {
     pub fn new() -> BlockHeader {
         BlockHeader {
             version: 0,
-            previous_hash: Int::zero(),
-            merkle_root_hash: Int::zero(),
+            previous_hash: Hash256::zero(),
+            merkle_root_hash: Hash256::zero(),
             time: 0,
             bits: 0,
             nonce: 0
@@ -58,6 +71,17 @@ impl BlockHeader {
     pub fn is_null(&self) -> bool {
         self.bits == 0
     }
+
+    /// Block hash: the double hash of the serialized header
+    pub fn get_hash(&self) -> Hash256 {
+        double_hash(&serialize(self))
+    }
+}
+
+impl Default for Block {
+    fn default() -> Block {
+        Block::new()
+    }
 }
 
 impl Block {
@@ -67,18 +91,103 @@ impl Block {
             transactions: Vec::new()
         }
     }
+
+    /// Computes the merkle root over the block's txids. The second value
+    /// is set if the transaction list is mutated (duplicate subtrees), in
+    /// which case the block must be treated as having an invalid root.
+    pub fn compute_merkle_root(&self) -> (Hash256, bool) {
+        compute_merkle_root(&self.get_txids())
+    }
+
+    /// Computes the witness merkle root over the block's wtxids, with the
+    /// coinbase wtxid taken as zero (BIP141)
+    pub fn compute_witness_merkle_root(&self) -> (Hash256, bool) {
+        let mut leaves: Vec<Hash256> = self.transactions.iter().map(|tx| tx.wtxid()).collect();
+
+        if !leaves.is_empty() {
+            leaves[0] = Hash256::zero();
+        }
+
+        compute_merkle_root(&leaves)
+    }
+
+    /// Builds a merkle inclusion proof for the transaction at `index`
+    ///
+    /// ### Arguments
+    ///
+    /// * `index`   - Position of the transaction in the block
+    pub fn get_merkle_branch(&self, index: usize) -> Option<MerkleBranch> {
+        compute_merkle_branch(&self.get_txids(), index)
+    }
+
+    /// Txids of all transactions in the block, in order
+    fn get_txids(&self) -> Vec<Hash256> {
+        self.transactions.iter().map(|tx| tx.txid()).collect()
+    }
 }
 
 
-/*---- FUNCTIONS ----*/
+/*---- SERIALIZATION ----*/
 
-/**
- * Builds the raw shell of a genesis block
- */
+impl Encodable for BlockHeader {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        self.version.encode(stream);
+        self.previous_hash.encode(stream);
+        self.merkle_root_hash.encode(stream);
+        self.time.encode(stream);
+        self.bits.encode(stream);
+        self.nonce.encode(stream);
+    }
+}
+
+impl Decodable for BlockHeader {
+    fn decode(reader: &mut Reader) -> Result<BlockHeader, SerializeError> {
+        Ok(BlockHeader {
+            version: u32::decode(reader)?,
+            previous_hash: Hash256::decode(reader)?,
+            merkle_root_hash: Hash256::decode(reader)?,
+            time: u32::decode(reader)?,
+            bits: u32::decode(reader)?,
+            nonce: u32::decode(reader)?
+        })
+    }
+}
+
+impl Encodable for Block {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        self.header.encode(stream);
+        self.transactions.encode(stream);
+    }
+}
+
+impl Decodable for Block {
+    fn decode(reader: &mut Reader) -> Result<Block, SerializeError> {
+        Ok(Block {
+            header: BlockHeader::decode(reader)?,
+            transactions: Vec::<Transaction>::decode(reader)?
+        })
+    }
+}
 
-fn create_raw_genesis_block(
-    psz_timestamp: &'static str, // this will change
-    // genesis_output_script: ??? sort out script struct
+
+/*---- FUNCTIONS ----*/
+
+/// Builds the raw shell of a genesis block. The coinbase input script
+/// commits to the timestamp phrase the same way the original Bitcoin genesis
+/// block did: `486604799 4 <psz_timestamp>`.
+///
+/// ### Arguments
+///
+/// * `psz_timestamp`           - Timestamp phrase proving time of creation
+/// * `genesis_output_script`   - Script the genesis reward is paid to
+/// * `time`                    - Block time
+/// * `nonce`                   - Block nonce
+/// * `bits`                    - Compact difficulty target
+/// * `version`                 - Block version
+/// * `genesis_reward`          - Value of the coinbase output
+pub fn create_raw_genesis_block(
+    psz_timestamp: &str,
+    genesis_output_script: &[u8],
     time: &u32,
     nonce: &u32,
     bits: &u32,
@@ -86,35 +195,57 @@ fn create_raw_genesis_block(
     genesis_reward: &u64
 ) -> Block 
 {
-    // let psz_timestamp = String::from("The Times 03/Jan/2009 Chancellor on brink of second bailout for banks");
     let mut genesis = Block::new();
-    // let mut gen_transaction = Transaction::new();
-    // let mut tx_in = TxIn::new();
-    // let mut tx_out = TxOut::new();
+    let mut gen_transaction = Transaction::new();
+    let mut tx_in = TxIn::new();
+    let mut tx_out = TxOut::new();
+    let mut script_signature = Script::new();
+
+    // Handle genesis transaction
+    script_signature.push_data(&486604799u32.to_le_bytes())
+                    .push_data(&[4])
+                    .push_data(psz_timestamp.as_bytes());
+
+    tx_in.script_signature = script_signature.to_bytes();
+    tx_in.sequence = SEQUENCE_FINAL;
 
-    // // Handle genesis transaction
-    // tx_in.script_signature = Some( ToHex(psz_timestamp + psz_timestamp.length()) );
-    // tx_out.value = genesis_reward;
-    // tx_out.public_key = Some(genesis_output);
+    tx_out.value = Some(*genesis_reward);
+    tx_out.script_public_key = genesis_output_script.to_vec();
 
-    // gen_transaction.inputs.push(tx_in);
-    // gen_transaction.outputs.push(tx_out);
+    gen_transaction.set_version(1);
+    gen_transaction.inputs_mut().push(tx_in);
+    gen_transaction.outputs_mut().push(tx_out);
 
-    // // Handle block header
-    // genesis.header.version = version;
-    // genesis.header.bits = bits;
-    // genesis.header.nonce = nonce;
-    // genesis.header.time = time;
+    // Add genesis transaction
+    genesis.transactions.push(gen_transaction);
 
-    // // Add genesis transaction
-    // genesis.transactions.push(gen_transaction);
+    // Handle block header
+    genesis.header.version = *version;
+    genesis.header.bits = *bits;
+    genesis.header.nonce = *nonce;
+    genesis.header.time = *time;
+    genesis.header.merkle_root_hash = genesis.compute_merkle_root().0;
 
-    // // Other stuff accepts defaults, so just return the block
+    // Other stuff accepts defaults, so just return the block
     genesis
 }
 
-/// Creates a final genesis block for inclusion in the chain
+/// Creates a final genesis block for inclusion in the chain, paying the
+/// reward to the original Bitcoin genesis output key
 pub fn create_genesis_block(time: u32, nonce: u32, bits: u32, version: u32, genesis_reward: u64) -> Block {
-    // Using straight constant in this case, but will need to incorporate some kind of scripting situation
-    create_raw_genesis_block(&PSZ_TIMESTAMP, &time, &nonce, &bits, &version, &genesis_reward)
-}
\ No newline at end of file
+    let genesis_output_script = create_genesis_output_script(&from_hex(GENESIS_OUTPUT_KEY).unwrap());
+
+    create_raw_genesis_block(PSZ_TIMESTAMP, &genesis_output_script, &time, &nonce, &bits, &version, &genesis_reward)
+}
+
+/// Builds the `<pubkey> OP_CHECKSIG` script a genesis reward is paid to
+///
+/// ### Arguments
+///
+/// * `output_key`  - Public key to pay the reward to
+pub fn create_genesis_output_script(output_key: &[u8]) -> Vec<u8> {
+    let mut script = Script::new();
+    script.push_data(output_key).push_op(OpCodes::OP_CHECKSIG);
+
+    script.to_bytes()
+}
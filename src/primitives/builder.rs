@@ -0,0 +1,265 @@
+/*
+ * Fluent construction of unsigned transactions. Inputs are added by the
+ * outpoint they spend and outputs by script or address; the result comes
+ * out of `build()` with empty input scripts, ready to be signed.
+ */
+
+use std::fmt;
+use std::error::Error;
+use std::collections::HashSet;
+use utils::amount::Amount;
+use script_lang::standard::Address;
+use primitives::transaction::{ Transaction, TxIn, TxOut, OutPoint, TxValidationError };
+
+
+/*---- CONSTANTS ----*/
+
+/// Version given to built transactions unless set otherwise. Version 2
+/// enables BIP68 relative lock-times on input sequence numbers.
+pub const DEFAULT_TX_VERSION: i32 = 2;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// Builder for unsigned transactions
+#[derive(Clone, Debug)]
+pub struct TransactionBuilder {
+    version: i32,
+    lock_time: u32,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>
+}
+
+/// Reasons a builder can fail to produce a transaction
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BuildError {
+    NoInputs,                       // Transaction spends nothing
+    NoOutputs,                      // Transaction pays to nothing
+    DuplicateInput(OutPoint),       // Same outpoint spent twice
+    Value(TxValidationError)        // Output values out of range
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl Default for TransactionBuilder {
+    fn default() -> TransactionBuilder {
+        TransactionBuilder::new()
+    }
+}
+
+impl TransactionBuilder {
+
+    /// Returns a new builder with no inputs or outputs
+    pub fn new() -> TransactionBuilder {
+        TransactionBuilder {
+            version: DEFAULT_TX_VERSION,
+            lock_time: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new()
+        }
+    }
+
+    /// Sets the transaction version
+    ///
+    /// ### Arguments
+    ///
+    /// * `version` - Transaction version
+    pub fn version(mut self, version: i32) -> TransactionBuilder {
+        self.version = version;
+        self
+    }
+
+    /// Sets the transaction lock time. Only takes effect if at least one
+    /// input has a non-final sequence number.
+    ///
+    /// ### Arguments
+    ///
+    /// * `lock_time`   - Block height or UNIX timestamp (see LOCKTIME_THRESHOLD)
+    pub fn lock_time(mut self, lock_time: u32) -> TransactionBuilder {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Adds an input spending the given outpoint
+    ///
+    /// ### Arguments
+    ///
+    /// * `outpoint`    - Previous output to spend
+    /// * `sequence`    - Input sequence number (SEQUENCE_FINAL for none)
+    pub fn add_input(mut self, outpoint: OutPoint, sequence: u32) -> TransactionBuilder {
+        let mut tx_in = TxIn::new();
+
+        tx_in.previous_out = Some(outpoint);
+        tx_in.sequence = sequence;
+
+        self.inputs.push(tx_in);
+        self
+    }
+
+    /// Adds an output paying to a raw script
+    ///
+    /// ### Arguments
+    ///
+    /// * `value`               - Amount to pay
+    /// * `script_public_key`   - Script the output is locked with
+    pub fn add_output(mut self, value: Amount, script_public_key: Vec<u8>) -> TransactionBuilder {
+        let mut tx_out = TxOut::new();
+
+        tx_out.value = Some(value.as_sat());
+        tx_out.script_public_key = script_public_key;
+
+        self.outputs.push(tx_out);
+        self
+    }
+
+    /// Adds an output paying to an address
+    ///
+    /// ### Arguments
+    ///
+    /// * `value`   - Amount to pay
+    /// * `address` - Address to pay to
+    pub fn add_output_to_address(self, value: Amount, address: &Address) -> TransactionBuilder {
+        self.add_output(value, address.get_script_public_key())
+    }
+
+    /// Checks the transaction and returns it, unsigned
+    pub fn build(self) -> Result<Transaction, BuildError> {
+        if self.inputs.is_empty() {
+            return Err(BuildError::NoInputs);
+        }
+
+        if self.outputs.is_empty() {
+            return Err(BuildError::NoOutputs);
+        }
+
+        let mut spent = HashSet::new();
+
+        for tx_in in &self.inputs {
+            // Inputs are only ever added with an outpoint, so we're safe to unwrap
+            let outpoint = tx_in.previous_out.clone().unwrap();
+
+            if !spent.insert(outpoint.clone()) {
+                return Err(BuildError::DuplicateInput(outpoint));
+            }
+        }
+
+        let mut tx = Transaction::new();
+
+        tx.set_version(self.version);
+        tx.set_lock_time(self.lock_time);
+        *tx.inputs_mut() = self.inputs;
+        *tx.outputs_mut() = self.outputs;
+
+        if let Err(e) = tx.get_output_value() {
+            return Err(BuildError::Value(e));
+        }
+
+        Ok(tx)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NoInputs => write!(f, "Transaction has no inputs"),
+            BuildError::NoOutputs => write!(f, "Transaction has no outputs"),
+            BuildError::DuplicateInput(outpoint) => write!(f, "Outpoint {}:{} is spent twice", outpoint.hash, outpoint.n),
+            BuildError::Value(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::hash256::Hash256;
+    use utils::amount::COINS;
+    use primitives::SEQUENCE_FINAL;
+
+    fn outpoint(n: i32) -> OutPoint {
+        OutPoint::new(Hash256::from_bytes([1; 32]), n)
+    }
+
+    fn coins(whole: u64) -> Amount {
+        Amount::from_sat(whole * COINS).unwrap()
+    }
+
+    #[test]
+    fn builds_unsigned_transactions() {
+        let tx = TransactionBuilder::new()
+            .lock_time(500)
+            .add_input(outpoint(0), 0xfffffffe)
+            .add_input(outpoint(1), SEQUENCE_FINAL)
+            .add_output(coins(1), vec![0x51])
+            .build()
+            .unwrap();
+
+        assert_eq!(tx.version(), DEFAULT_TX_VERSION);
+        assert_eq!(tx.lock_time(), 500);
+        assert_eq!(tx.inputs().len(), 2);
+        assert_eq!(tx.inputs()[1].previous_out, Some(outpoint(1)));
+        assert_eq!(tx.inputs()[1].sequence, SEQUENCE_FINAL);
+        assert!(tx.inputs().iter().all(|input| input.script_signature.is_empty() && input.witness.is_empty()));
+        assert_eq!(tx.outputs()[0].value, Some(COINS));
+        assert_eq!(tx.get_output_value(), Ok(coins(1)));
+
+        let tx = TransactionBuilder::new().version(1).add_input(outpoint(0), 0).add_output(coins(1), Vec::new()).build().unwrap();
+        assert_eq!(tx.version(), 1);
+    }
+
+    #[test]
+    fn invalid_transactions_are_refused() {
+        let output_only = TransactionBuilder::new().add_output(coins(1), Vec::new());
+        assert_eq!(output_only.build().unwrap_err(), BuildError::NoInputs);
+
+        let input_only = TransactionBuilder::new().add_input(outpoint(0), 0);
+        assert_eq!(input_only.build().unwrap_err(), BuildError::NoOutputs);
+
+        let duplicate = TransactionBuilder::new()
+            .add_input(outpoint(0), 0)
+            .add_input(outpoint(1), 0)
+            .add_input(outpoint(0), SEQUENCE_FINAL)
+            .add_output(coins(1), Vec::new());
+        assert_eq!(duplicate.build().unwrap_err(), BuildError::DuplicateInput(outpoint(0)));
+
+        // Each output is in range, but together they aren't
+        let overpaying = TransactionBuilder::new()
+            .add_input(outpoint(0), 0)
+            .add_output(Amount::MAX, Vec::new())
+            .add_output(coins(1), Vec::new());
+        assert_eq!(overpaying.build().unwrap_err(), BuildError::Value(TxValidationError::TotalOutputValueOutOfRange));
+    }
+
+    #[test]
+    fn address_output_scripts() {
+        let mut expected = vec![0x76, 0xa9, 0x14];
+        expected.extend_from_slice(&[0x11; 20]);
+        expected.extend_from_slice(&[0x88, 0xac]);
+        assert_eq!(Address::PubKeyHash([0x11; 20]).get_script_public_key(), expected);
+
+        let mut expected = vec![0xa9, 0x14];
+        expected.extend_from_slice(&[0x22; 20]);
+        expected.push(0x87);
+        assert_eq!(Address::ScriptHash([0x22; 20]).get_script_public_key(), expected);
+
+        let mut expected = vec![0x00, 0x14];
+        expected.extend_from_slice(&[0x33; 20]);
+        assert_eq!(Address::WitnessKeyHash([0x33; 20]).get_script_public_key(), expected);
+
+        let mut expected = vec![0x00, 0x20];
+        expected.extend_from_slice(&[0x44; 32]);
+        assert_eq!(Address::WitnessScriptHash([0x44; 32]).get_script_public_key(), expected);
+
+        let mut expected = vec![0x51, 0x20];
+        expected.extend_from_slice(&[0x55; 32]);
+        assert_eq!(Address::Taproot([0x55; 32]).get_script_public_key(), expected);
+
+        let address = Address::WitnessKeyHash([0x33; 20]);
+        let tx = TransactionBuilder::new().add_input(outpoint(0), 0).add_output_to_address(coins(2), &address).build().unwrap();
+        assert_eq!(tx.outputs()[0].script_public_key, address.get_script_public_key());
+    }
+}
@@ -1,5 +1,9 @@
 pub mod block;
 pub mod transaction;
+pub mod builder;
+
+#[cfg(test)]
+pub mod test_utils;
 
 /*---- CONSTANTS ----*/
 
@@ -14,14 +18,14 @@ pub const SEQUENCE_FINAL: u32 = 0xffffffff;
  * relative lock-time. 
  */
 
-pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = (1 << 31);
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
 
 /* If CTxIn::nSequence encodes a relative lock-time and this flag
  * is set, the relative lock-time has units of 512 seconds,
  * otherwise it specifies blocks with a granularity of 1.
  */
 
-pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = (1 << 22);
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
 
 /* If CTxIn::nSequence encodes a relative lock-time, this mask is
  * applied to extract that lock-time from the sequence field. 
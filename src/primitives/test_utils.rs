@@ -0,0 +1,42 @@
+/*
+ * Fixtures shared by the tests of the modules that work on transactions.
+ */
+
+use utils::hash256::Hash256;
+use primitives::transaction::{ Transaction, TxIn, TxOut, OutPoint };
+
+
+/*---- FUNCTIONS ----*/
+
+/// Version 2 transaction with a lock time of 500, spending `inputs` distinct
+/// outpoints and paying 1000 times the output's position plus one to each
+/// of `outputs` outputs. Input `i` has a sequence of 10 + `i`, so lock times
+/// are enforced and relative lock times of up to 10 blocks are met. Every
+/// input is 42 bytes and every output 11 bytes when serialized.
+///
+/// ### Arguments
+///
+/// * `inputs`  - Number of inputs
+/// * `outputs` - Number of outputs
+pub fn sample_transaction(inputs: usize, outputs: usize) -> Transaction {
+    let mut tx = Transaction::new();
+    tx.set_version(2);
+    tx.set_lock_time(500);
+
+    for i in 0..inputs {
+        let mut input = TxIn::new();
+        input.previous_out = Some(OutPoint::new(Hash256::from_bytes([i as u8 + 1; 32]), i as i32));
+        input.script_signature = vec![0x51];
+        input.sequence = 10 + i as u32;
+        tx.inputs_mut().push(input);
+    }
+
+    for i in 0..outputs {
+        let mut output = TxOut::new();
+        output.value = Some(1000 * (i as u64 + 1));
+        output.script_public_key = vec![0x76, 0xa9];
+        tx.outputs_mut().push(output);
+    }
+
+    tx
+}
@@ -1,24 +1,32 @@
-use utils::amount::is_valid_amount;
+use std::fmt;
+use std::cell::Cell;
+use std::error::Error;
+use utils::hash256::Hash256;
+use utils::amount::Amount;
+use cryptography::hash::double_hash;
+use consensus::WITNESS_SCALE_FACTOR;
+use serialize::{ Encodable, Decodable, Reader, SerializeError, serialize, write_compact_size, read_compact_size, write_var_bytes, read_var_bytes };
 
 
 /*---- STRUCTS ----*/
 
 /// An outpoint - a combination of a transaction hash and an index n into its vout.
-/// Hashes are currently Vec<u8> until a better solution comes along
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct OutPoint {
-    pub hash: Vec<u8>,
+    pub hash: Hash256,
     pub n: i32
 }
 
 /// An input of a transaction. It contains the location of the previous
 /// transaction's output that it claims and a signature that matches the
-/// output's public key.
+/// output's public key. Segwit inputs carry their signature data in the
+/// witness stack instead, which is not committed to by the txid.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TxIn {
     pub previous_out: Option<OutPoint>,
     pub sequence: u32,
-    pub script_signature: Option<String>
+    pub script_signature: Vec<u8>,
+    pub witness: Vec<Vec<u8>>
 }
 
 /// An output of a transaction. It contains the public key that the next input
@@ -26,17 +34,30 @@ pub struct TxIn {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TxOut {
     pub value: Option<u64>, // amount in satoshis (original bitcoin)
-    pub script_public_key: Option<String>
+    pub script_public_key: Vec<u8>
 }
 
 /// The basic transaction that is broadcasted on the network and contained in
 /// blocks. A transaction can contain multiple inputs and outputs.
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// The txid and wtxid are computed lazily and cached. The fields are only
+/// reachable through accessors, and every mutable accessor drops the cache,
+/// so the ids can never go stale.
+#[derive(Clone, Debug)]
 pub struct Transaction {
-    pub inputs: Vec<TxIn>,
-    pub outputs: Vec<TxOut>,
-    pub version: i32,
-    pub lock_time: u32
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>,
+    version: i32,
+    lock_time: u32,
+    txid: Cell<Option<Hash256>>,
+    wtxid: Cell<Option<Hash256>>
+}
+
+/// Reasons a transaction can fail validation
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TxValidationError {
+    OutputValueOutOfRange { index: usize, value: u64 },    // A single txout value exceeds MAX_MONEY
+    TotalOutputValueOutOfRange                              // Sum of txout values exceeds MAX_MONEY
 }
 
 
@@ -44,35 +65,65 @@ pub struct Transaction {
 
 impl OutPoint {
     /// Returns a new instance of a outpoint struct
-    fn new(hash: Vec<u8>, n: i32) -> OutPoint {
+    ///
+    /// ### Arguments
+    ///
+    /// * `hash`    - Txid of the transaction being pointed to
+    /// * `n`       - Index into that transaction's outputs
+    pub fn new(hash: Hash256, n: i32) -> OutPoint {
         OutPoint {
-            hash: hash,
-            n: n
+            hash,
+            n
         }
     }
 }
 
+impl From<(Hash256, i32)> for OutPoint {
+    fn from((hash, n): (Hash256, i32)) -> OutPoint {
+        OutPoint::new(hash, n)
+    }
+}
+
+impl Default for TxIn {
+    fn default() -> TxIn {
+        TxIn::new()
+    }
+}
+
 impl TxIn {
     /// Returns a new instance of a txin struct
     pub fn new() -> TxIn {
         TxIn {
             previous_out: None,
             sequence: 0,
-            script_signature: None
+            script_signature: Vec::new(),
+            witness: Vec::new()
         }
     }
 }
 
+impl Default for TxOut {
+    fn default() -> TxOut {
+        TxOut::new()
+    }
+}
+
 impl TxOut {
     /// Returns a new instance of a txout struct
     pub fn new() -> TxOut {
         TxOut {
             value: None,
-            script_public_key: None
+            script_public_key: Vec::new()
         }
     }
 }
 
+impl Default for Transaction {
+    fn default() -> Transaction {
+        Transaction::new()
+    }
+}
+
 impl Transaction {
     /// Returns a new instance of a transaction struct
     pub fn new() -> Transaction {
@@ -80,43 +131,366 @@ impl Transaction {
             inputs: Vec::new(),
             outputs: Vec::new(),
             version: 0,
-            lock_time: 0
+            lock_time: 0,
+            txid: Cell::new(None),
+            wtxid: Cell::new(None)
         }
     }
 
-    /// Return sum of txouts
-    fn get_output_value(&mut self) -> u64 {
-        let mut total_value: u64 = 0;
+    /// Transaction inputs
+    pub fn inputs(&self) -> &[TxIn] {
+        &self.inputs
+    }
 
-        for txout in &mut self.outputs {
-            if txout.value.is_some() {
-                // we're safe to unwrap here
-                let this_value = txout.value.unwrap(); 
+    /// Transaction outputs
+    pub fn outputs(&self) -> &[TxOut] {
+        &self.outputs
+    }
 
-                if !is_valid_amount(&this_value) {
-                    panic!("TxOut value {value} out of range", value = this_value);
-                }
+    /// Transaction version
+    pub fn version(&self) -> i32 {
+        self.version
+    }
 
-                total_value += this_value;
+    /// Transaction lock time
+    pub fn lock_time(&self) -> u32 {
+        self.lock_time
+    }
 
-                if !is_valid_amount(&total_value) {
-                    panic!("Total TxOut value of {value} out of range", value = total_value);
-                }
+    /// Mutable access to the inputs. Drops the cached ids.
+    pub fn inputs_mut(&mut self) -> &mut Vec<TxIn> {
+        self.invalidate_ids();
+        &mut self.inputs
+    }
+
+    /// Mutable access to the outputs. Drops the cached ids.
+    pub fn outputs_mut(&mut self) -> &mut Vec<TxOut> {
+        self.invalidate_ids();
+        &mut self.outputs
+    }
+
+    /// Sets the transaction version
+    ///
+    /// ### Arguments
+    ///
+    /// * `version` - New version
+    pub fn set_version(&mut self, version: i32) {
+        self.invalidate_ids();
+        self.version = version;
+    }
+
+    /// Sets the transaction lock time
+    ///
+    /// ### Arguments
+    ///
+    /// * `lock_time`   - New lock time
+    pub fn set_lock_time(&mut self, lock_time: u32) {
+        self.invalidate_ids();
+        self.lock_time = lock_time;
+    }
+
+    /// Transaction id: the double hash of the transaction serialized
+    /// without witness data
+    pub fn txid(&self) -> Hash256 {
+        match self.txid.get() {
+            Some(txid) => txid,
+            None => {
+                let txid = double_hash(&self.serialize_without_witness());
+                self.txid.set(Some(txid));
+
+                txid
+            }
+        }
+    }
+
+    /// Witness transaction id: the double hash of the transaction serialized
+    /// with witness data. For transactions without witness data this is
+    /// the same as the txid.
+    pub fn wtxid(&self) -> Hash256 {
+        match self.wtxid.get() {
+            Some(wtxid) => wtxid,
+            None => {
+                let wtxid = match self.has_witness() {
+                    true => double_hash(&serialize(self)),
+                    false => self.txid()
+                };
+
+                self.wtxid.set(Some(wtxid));
+
+                wtxid
+            }
+        }
+    }
+
+    /// Drops the cached ids, so they are recomputed on next access
+    fn invalidate_ids(&mut self) {
+        self.txid.set(None);
+        self.wtxid.set(None);
+    }
+
+    /// Returns an outpoint referencing one of this transaction's outputs
+    ///
+    /// ### Arguments
+    ///
+    /// * `n`       - Index of the output
+    pub fn get_outpoint(&self, n: i32) -> OutPoint {
+        OutPoint::new(self.txid(), n)
+    }
+
+    /// Return sum of txouts, or an error if any value or the running
+    /// total falls outside of the valid money range
+    pub fn get_output_value(&self) -> Result<Amount, TxValidationError> {
+        let mut total_value = Amount::ZERO;
+
+        for (index, txout) in self.outputs.iter().enumerate() {
+            if let Some(this_value) = txout.value {
+                let this_amount = match Amount::from_sat(this_value) {
+                    Ok(amount) => amount,
+                    Err(_) => return Err(TxValidationError::OutputValueOutOfRange { index, value: this_value })
+                };
+
+                total_value = match total_value.checked_add(this_amount) {
+                    Some(total) => total,
+                    None => return Err(TxValidationError::TotalOutputValueOutOfRange)
+                };
             }
         }
 
-        total_value
+        Ok(total_value)
+    }
+
+    /// Whether any input carries witness data
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// Serializes the transaction in the original (pre-segwit) format,
+    /// leaving out all witness data. This is what the txid commits to.
+    pub fn serialize_without_witness(&self) -> Vec<u8> {
+        let mut stream = Vec::new();
+
+        self.version.encode(&mut stream);
+        self.inputs.encode(&mut stream);
+        self.outputs.encode(&mut stream);
+        self.lock_time.encode(&mut stream);
+
+        stream
     }
 
     /// Get the total transaction size in bytes, including witness data.
     /// "Total Size" defined in BIP141 and BIP144.
-    fn get_total_size(&self) {
-        // get this from serialization size
+    pub fn get_total_size(&self) -> usize {
+        self.serialized_size()
+    }
+
+    /// Get the transaction size in bytes with all witness data stripped.
+    /// "Base Size" defined in BIP141.
+    pub fn get_stripped_size(&self) -> usize {
+        self.serialize_without_witness().len()
     }
 
-    /// Returns whether current transaction is a coinbase tx
-    fn is_coinbase(&self) -> bool {
-        self.inputs.len() == 1 && self.inputs[0].previous_out != None
+    /// Transaction weight: base size * 3 + total size (BIP141)
+    pub fn get_weight(&self) -> usize {
+        self.get_stripped_size() * (WITNESS_SCALE_FACTOR - 1) + self.get_total_size()
+    }
+
+    /// Virtual size: weight divided by 4, rounded up (BIP141). This is the
+    /// size that fee rates are measured against.
+    pub fn get_virtual_size(&self) -> usize {
+        self.get_weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    /// Returns whether current transaction is a coinbase tx, ie. it has
+    /// a single input that spends no previous output
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_out.is_none()
     }
 }
 
+impl fmt::Display for TxValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TxValidationError::OutputValueOutOfRange { index, value } => {
+                write!(f, "TxOut {} value {} out of range", index, value)
+            },
+            TxValidationError::TotalOutputValueOutOfRange => write!(f, "Total TxOut value out of range")
+        }
+    }
+}
+
+impl Error for TxValidationError {}
+
+/// Equality only looks at the transaction's contents, never the id cache
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Transaction) -> bool {
+        self.version == other.version &&
+        self.inputs == other.inputs &&
+        self.outputs == other.outputs &&
+        self.lock_time == other.lock_time
+    }
+}
+
+impl Eq for Transaction {}
+
+
+/*---- SERIALIZATION ----*/
+
+impl Encodable for OutPoint {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        self.hash.encode(stream);
+        (self.n as u32).encode(stream);
+    }
+}
+
+impl Decodable for OutPoint {
+    fn decode(reader: &mut Reader) -> Result<OutPoint, SerializeError> {
+        let hash = Hash256::decode(reader)?;
+        let n = u32::decode(reader)? as i32;
+
+        Ok(OutPoint::new(hash, n))
+    }
+}
+
+/// A missing previous output is written as the null outpoint
+/// (zero hash, index -1), exactly as a coinbase input carries it
+impl Encodable for TxIn {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        match self.previous_out {
+            Some(ref outpoint) => outpoint.encode(stream),
+            None => OutPoint::new(Hash256::zero(), -1).encode(stream)
+        }
+
+        write_var_bytes(stream, &self.script_signature);
+        self.sequence.encode(stream);
+    }
+}
+
+impl Decodable for TxIn {
+    fn decode(reader: &mut Reader) -> Result<TxIn, SerializeError> {
+        let outpoint = OutPoint::decode(reader)?;
+        let previous_out = match outpoint.n == -1 && outpoint.hash.is_zero() {
+            true => None,
+            false => Some(outpoint)
+        };
+
+        Ok(TxIn {
+            previous_out,
+            script_signature: read_var_bytes(reader)?,
+            sequence: u32::decode(reader)?,
+            witness: Vec::new()
+        })
+    }
+}
+
+/// A missing value is written as -1, which is how Bitcoin serializes
+/// a null txout. Values are signed on the wire, so anything above i64::MAX
+/// is capped there rather than wrapping round to a negative (or null) value.
+/// Such outputs are well over MAX_MONEY and fail get_output_value anyway
+impl Encodable for TxOut {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        match self.value {
+            Some(value) => (value.min(i64::MAX as u64) as i64).encode(stream),
+            None => (-1_i64).encode(stream)
+        }
+
+        write_var_bytes(stream, &self.script_public_key);
+    }
+}
+
+impl Decodable for TxOut {
+    fn decode(reader: &mut Reader) -> Result<TxOut, SerializeError> {
+        let value = match i64::decode(reader)? {
+            -1 => None,
+            v if v < 0 => return Err(SerializeError::InvalidValue("negative txout value")),
+            v => Some(v as u64)
+        };
+
+        Ok(TxOut {
+            value,
+            script_public_key: read_var_bytes(reader)?
+        })
+    }
+}
+
+/// Transactions with witness data use the extended format from BIP144:
+///
+/// - version
+/// - marker (0x00), which old parsers read as an empty input vector
+/// - flag (0x01)
+/// - inputs
+/// - outputs
+/// - one witness stack per input
+/// - lock_time
+///
+/// Transactions without witness data are always written in the original format.
+impl Encodable for Transaction {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        if !self.has_witness() {
+            stream.extend(self.serialize_without_witness());
+            return;
+        }
+
+        self.version.encode(stream);
+        stream.push(0x00);
+        stream.push(0x01);
+        self.inputs.encode(stream);
+        self.outputs.encode(stream);
+
+        for input in &self.inputs {
+            write_compact_size(stream, input.witness.len() as u64);
+
+            for item in &input.witness {
+                write_var_bytes(stream, item);
+            }
+        }
+
+        self.lock_time.encode(stream);
+    }
+}
+
+impl Decodable for Transaction {
+    fn decode(reader: &mut Reader) -> Result<Transaction, SerializeError> {
+        let mut tx = Transaction::new();
+        let mut flags = 0;
+
+        tx.version = i32::decode(reader)?;
+        tx.inputs = Vec::<TxIn>::decode(reader)?;
+
+        // An empty input vector is the segwit marker, so a flag byte follows
+        if tx.inputs.is_empty() {
+            flags = u8::decode(reader)?;
+
+            if flags != 0 {
+                tx.inputs = Vec::<TxIn>::decode(reader)?;
+                tx.outputs = Vec::<TxOut>::decode(reader)?;
+            }
+        } else {
+            tx.outputs = Vec::<TxOut>::decode(reader)?;
+        }
+
+        if flags & 1 != 0 {
+            flags ^= 1;
+
+            for input in &mut tx.inputs {
+                let items = read_compact_size(reader)?;
+
+                for _ in 0..items {
+                    input.witness.push(read_var_bytes(reader)?);
+                }
+            }
+
+            // Serializing would drop the witness section, so this would not round trip
+            if !tx.has_witness() {
+                return Err(SerializeError::InvalidValue("superfluous witness record"));
+            }
+        }
+
+        if flags != 0 {
+            return Err(SerializeError::InvalidValue("unknown transaction optional data"));
+        }
+
+        tx.lock_time = u32::decode(reader)?;
+
+        Ok(tx)
+    }
+}
@@ -0,0 +1,354 @@
+/*
+ * Signature checkers. The interpreter doesn't know which transaction it is
+ * running for, so anything that depends on it (signatures, and later lock
+ * times) goes through a checker instead.
+ */
+
+use utils::amount::Amount;
+use script_lang::num::ScriptNum;
+use script_lang::script::LOCKTIME_THRESHOLD;
+use cryptography::keys::verify_signature;
+use cryptography::schnorr::{ self, SCHNORR_SIGNATURE_SIZE };
+use primitives::transaction::Transaction;
+use primitives::{ SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_TYPE_FLAG, SEQUENCE_LOCKTIME_MASK };
+use script_lang::script::Script;
+use script_lang::error::ScriptError;
+use script_lang::sighash::{
+    SigVersion, PrecomputedTransactionData, ScriptExecutionData, SIGHASH_DEFAULT,
+    signature_hash, taproot_signature_hash, standard_template_hash
+};
+
+
+/*---- TRAITS ----*/
+
+/// Checks made against the transaction a script is running for. Every
+/// check fails by default, for scripts that are run outside of a transaction.
+pub trait SignatureChecker {
+
+    /// Whether a signature, with its sighash type byte appended, is valid
+    /// for the given public key
+    ///
+    /// ### Arguments
+    ///
+    /// * `signature`   - Signature with sighash type byte
+    /// * `public_key`  - Raw public key
+    /// * `script_code` - Script code committed to by the signature
+    /// * `sig_version` - Signature version of the script being run
+    fn check_sig(&self, _signature: &[u8], _public_key: &[u8], _script_code: &Script, _sig_version: SigVersion) -> bool {
+        false
+    }
+
+    /// Checks a BIP340 Schnorr signature, with its optional sighash type byte,
+    /// against an x-only public key. Unlike other signature checks, a taproot
+    /// signature that fails to verify always fails the script.
+    ///
+    /// ### Arguments
+    ///
+    /// * `signature`   - Signature, with a sighash type byte unless it is SIGHASH_DEFAULT
+    /// * `public_key`  - Raw x-only public key
+    /// * `sig_version` - Taproot for key path spends, Tapscript for script path spends
+    /// * `exec_data`   - State of the spend, which the signature commits to
+    fn check_schnorr_sig(
+        &self,
+        _signature: &[u8],
+        _public_key: &[u8],
+        _sig_version: SigVersion,
+        _exec_data: &ScriptExecutionData
+    ) -> Result<(), ScriptError>
+    {
+        Err(ScriptError::SchnorrSig)
+    }
+
+    /// Whether the transaction's lock time satisfies an absolute lock
+    /// time from OP_CHECKLOCKTIMEVERIFY (BIP65)
+    ///
+    /// ### Arguments
+    ///
+    /// * `lock_time`   - Required lock time, as a block height or UNIX timestamp
+    fn check_lock_time(&self, _lock_time: ScriptNum) -> bool {
+        false
+    }
+
+    /// Whether the input's sequence number satisfies a relative lock
+    /// time from OP_CHECKSEQUENCEVERIFY (BIP112)
+    ///
+    /// ### Arguments
+    ///
+    /// * `sequence`    - Required relative lock time, encoded as a sequence number
+    fn check_sequence(&self, _sequence: ScriptNum) -> bool {
+        false
+    }
+
+    /// Whether the transaction matches a standard template hash from
+    /// OP_CHECKTEMPLATEVERIFY (BIP119)
+    ///
+    /// ### Arguments
+    ///
+    /// * `hash`    - Required template hash
+    fn check_template_hash(&self, _hash: &[u8]) -> bool {
+        false
+    }
+}
+
+
+/*---- STRUCTS ----*/
+
+/// Checker for scripts run without a transaction, where every check fails
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BaseSignatureChecker;
+
+/// Checker for an input of a transaction
+#[derive(Clone, Copy, Debug)]
+pub struct TransactionSignatureChecker<'a> {
+    tx: &'a Transaction,
+    input_index: usize,
+    amount: Amount,
+    txdata: Option<&'a PrecomputedTransactionData>
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl SignatureChecker for BaseSignatureChecker {}
+
+impl<'a> TransactionSignatureChecker<'a> {
+
+    /// Returns a checker for the given input of a transaction. Taproot
+    /// signatures commit to every spent output, so they fail with
+    /// TaprootMissingSpentOutputs unless the checker is made with
+    /// `with_precomputed` from data that includes them.
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`          - Transaction being verified
+    /// * `input_index` - Index of the input being verified
+    /// * `amount`      - Value of the output the input spends
+    pub fn new(tx: &'a Transaction, input_index: usize, amount: Amount) -> TransactionSignatureChecker<'a> {
+        TransactionSignatureChecker {
+            tx,
+            input_index,
+            amount,
+            txdata: None
+        }
+    }
+
+    /// Returns a checker that reuses signature hash parts precomputed for
+    /// the transaction, for verifying many inputs of the same transaction
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`          - Transaction being verified
+    /// * `input_index` - Index of the input being verified
+    /// * `amount`      - Value of the output the input spends
+    /// * `txdata`      - Precomputed signature hash parts for `tx`
+    pub fn with_precomputed(
+        tx: &'a Transaction,
+        input_index: usize,
+        amount: Amount,
+        txdata: &'a PrecomputedTransactionData
+    ) -> TransactionSignatureChecker<'a>
+    {
+        TransactionSignatureChecker {
+            tx,
+            input_index,
+            amount,
+            txdata: Some(txdata)
+        }
+    }
+}
+
+impl<'a> SignatureChecker for TransactionSignatureChecker<'a> {
+    fn check_sig(&self, signature: &[u8], public_key: &[u8], script_code: &Script, sig_version: SigVersion) -> bool {
+        // An empty signature is a valid way to fail a check
+        let (hash_type, signature) = match signature.split_last() {
+            Some(split) => split,
+            None => return false
+        };
+
+        let sighash = match signature_hash(script_code, self.tx, self.input_index, *hash_type as u32, self.amount, sig_version, self.txdata) {
+            Some(sighash) => sighash,
+            None => return false
+        };
+
+        verify_signature(signature, sighash.as_bytes(), public_key)
+    }
+
+    fn check_schnorr_sig(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        sig_version: SigVersion,
+        exec_data: &ScriptExecutionData
+    ) -> Result<(), ScriptError>
+    {
+        // A sighash type byte of zero would give a second encoding of
+        // SIGHASH_DEFAULT, so it has to be left out instead
+        let (hash_type, signature) = match signature.len() {
+            SCHNORR_SIGNATURE_SIZE => (SIGHASH_DEFAULT, signature),
+            65 if signature[64] != 0 => (signature[64] as u32, &signature[..64]),
+            65 => return Err(ScriptError::SchnorrSigHashType),
+            _ => return Err(ScriptError::SchnorrSigSize)
+        };
+
+        // Without the spent outputs there is nothing to check against, which
+        // is the caller's mistake rather than a bad signature
+        let txdata = match self.txdata.filter(|txdata| txdata.taproot.is_some()) {
+            Some(txdata) => txdata,
+            None => return Err(ScriptError::TaprootMissingSpentOutputs)
+        };
+
+        let sighash = match taproot_signature_hash(self.tx, self.input_index, hash_type, sig_version, exec_data, txdata) {
+            Some(sighash) => sighash,
+            None => return Err(ScriptError::SchnorrSigHashType)
+        };
+
+        if !schnorr::verify(signature, sighash.as_bytes(), public_key) {
+            return Err(ScriptError::SchnorrSig);
+        }
+
+        Ok(())
+    }
+
+    fn check_lock_time(&self, lock_time: ScriptNum) -> bool {
+        let input = match self.tx.inputs().get(self.input_index) {
+            Some(input) => input,
+            None => return false
+        };
+
+        let tx_lock_time = self.tx.lock_time() as i64;
+        let threshold = LOCKTIME_THRESHOLD as i64;
+
+        // Heights can't be compared with timestamps, so both lock times
+        // have to be of the same type
+        if (tx_lock_time < threshold) != (lock_time.value() < threshold) {
+            return false;
+        }
+
+        if lock_time.value() > tx_lock_time {
+            return false;
+        }
+
+        // A final input would let the transaction's lock time be ignored,
+        // bypassing the check entirely
+        input.sequence != SEQUENCE_FINAL
+    }
+
+    fn check_sequence(&self, sequence: ScriptNum) -> bool {
+        let tx_sequence = match self.tx.inputs().get(self.input_index) {
+            Some(input) => input.sequence as i64,
+            None => return false
+        };
+
+        // Relative lock times only apply from version 2 transactions (BIP68)
+        if (self.tx.version() as u32) < 2 {
+            return false;
+        }
+
+        // Sequence numbers with the disable flag set don't encode a relative
+        // lock time, so they can't satisfy one
+        if tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 != 0 {
+            return false;
+        }
+
+        // Only the type flag and the lock time itself are compared
+        let mask = (SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK) as i64;
+        let type_flag = SEQUENCE_LOCKTIME_TYPE_FLAG as i64;
+        let tx_sequence_masked = tx_sequence & mask;
+        let sequence_masked = sequence.value() & mask;
+
+        // Block-based and time-based relative lock times can't be compared
+        if (tx_sequence_masked < type_flag) != (sequence_masked < type_flag) {
+            return false;
+        }
+
+        sequence_masked <= tx_sequence_masked
+    }
+
+    fn check_template_hash(&self, hash: &[u8]) -> bool {
+        standard_template_hash(self.tx, self.input_index, self.txdata).as_bytes() == hash
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryptography::keys::{ generate_keypair, sign_message };
+    use primitives::test_utils::sample_transaction;
+    use primitives::transaction::TxOut;
+    use script_lang::script::OpCodes;
+    use script_lang::sighash::{ SIGHASH_ALL, witness_v0_signature_hash };
+
+    #[test]
+    fn lock_times_fail_for_missing_inputs() {
+        let tx = sample_transaction(1, 1);
+        let checker = TransactionSignatureChecker::new(&tx, 0, Amount::ZERO);
+        let missing = TransactionSignatureChecker::new(&tx, 1, Amount::ZERO);
+
+        assert!(checker.check_lock_time(ScriptNum::new(400)));
+        assert!(checker.check_sequence(ScriptNum::new(5)));
+        assert!(!missing.check_lock_time(ScriptNum::new(400)));
+        assert!(!missing.check_sequence(ScriptNum::new(5)));
+    }
+
+    #[test]
+    fn witness_signatures_fail_for_missing_inputs() {
+        let tx = sample_transaction(1, 1);
+        let (public_key, secret_key) = generate_keypair();
+
+        let mut script_code = Script::new();
+        script_code.push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIG);
+
+        let sighash = witness_v0_signature_hash(&script_code, &tx, 0, SIGHASH_ALL, Amount::ZERO, None).unwrap();
+        let mut signature = sign_message(sighash.as_bytes(), &secret_key);
+        signature.push(SIGHASH_ALL as u8);
+
+        let checker = TransactionSignatureChecker::new(&tx, 0, Amount::ZERO);
+        let missing = TransactionSignatureChecker::new(&tx, 1, Amount::ZERO);
+
+        assert!(checker.check_sig(&signature, public_key.as_ref(), &script_code, SigVersion::WitnessV0));
+        assert!(!missing.check_sig(&signature, public_key.as_ref(), &script_code, SigVersion::WitnessV0));
+        assert_eq!(witness_v0_signature_hash(&script_code, &tx, 1, SIGHASH_ALL, Amount::ZERO, None), None);
+    }
+
+    #[test]
+    fn schnorr_signatures_need_spent_outputs() {
+        let tx = sample_transaction(1, 1);
+        let keypair = schnorr::generate_keypair();
+        let public_key = schnorr::get_public_key(&keypair);
+        let exec_data = ScriptExecutionData::new();
+
+        let mut spent_output = TxOut::new();
+        spent_output.value = Some(1000);
+
+        // Taproot signatures commit to every spent output, so one per input
+        assert_eq!(PrecomputedTransactionData::with_spent_outputs(&tx, &[]), None);
+        assert_eq!(PrecomputedTransactionData::with_spent_outputs(&tx, &[spent_output.clone(), spent_output.clone()]), None);
+
+        let txdata = PrecomputedTransactionData::with_spent_outputs(&tx, &[spent_output]).unwrap();
+        let sighash = taproot_signature_hash(&tx, 0, SIGHASH_DEFAULT, SigVersion::Taproot, &exec_data, &txdata).unwrap();
+        let signature = schnorr::sign(sighash.as_bytes(), &keypair).unwrap();
+
+        assert_eq!(schnorr::sign(&sighash.as_bytes()[..31], &keypair), None);
+
+        let checker = TransactionSignatureChecker::with_precomputed(&tx, 0, Amount::ZERO, &txdata);
+        assert_eq!(checker.check_schnorr_sig(&signature, &public_key, SigVersion::Taproot, &exec_data), Ok(()));
+
+        let mut invalid = signature.clone();
+        invalid[0] ^= 1;
+        assert_eq!(checker.check_schnorr_sig(&invalid, &public_key, SigVersion::Taproot, &exec_data), Err(ScriptError::SchnorrSig));
+
+        let without_outputs = PrecomputedTransactionData::new(&tx);
+        let checkers = [
+            TransactionSignatureChecker::new(&tx, 0, Amount::ZERO),
+            TransactionSignatureChecker::with_precomputed(&tx, 0, Amount::ZERO, &without_outputs)
+        ];
+
+        for checker in checkers.iter() {
+            assert_eq!(
+                checker.check_schnorr_sig(&signature, &public_key, SigVersion::Taproot, &exec_data),
+                Err(ScriptError::TaprootMissingSpentOutputs)
+            );
+        }
+    }
+}
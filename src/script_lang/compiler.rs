@@ -0,0 +1,1235 @@
+/*
+ * Spending policy compiler. A policy says who may spend an output, for
+ * example `or(thresh(2,pk(A),pk(B),pk(C)),and(pk(D),older(12960)))`, and
+ * is compiled into a tree of script fragments (a small subset of
+ * Miniscript's). The tree gives both the script itself and, from the keys
+ * and preimages at hand, the stack that satisfies it.
+ *
+ * Every fragment leaves a single result on the stack. Where a parent has to
+ * be able to make a child fail without aborting the script (as `or` and
+ * `thresh` do), the child is required to be dissatisfiable, and where the
+ * parent adds up or branches on the result, it has to be exactly 0 or 1.
+ */
+
+use std::fmt;
+use std::collections::HashMap;
+use utils::amount::Amount;
+use utils::hex::{ to_hex, from_hex };
+use cryptography::hash::sha256;
+use cryptography::keys::{ PublicKey, SecretKey, PUBLIC_KEY_SIZE, SIGNATURE_SIZE, sign_message };
+use primitives::SEQUENCE_LOCKTIME_TYPE_FLAG;
+use primitives::transaction::Transaction;
+use script_lang::error::PolicyError;
+use script_lang::num::{ ScriptNum, DEFAULT_MAX_NUM_SIZE };
+use script_lang::checker::{ SignatureChecker, TransactionSignatureChecker };
+use script_lang::sighash::{ SigVersion, SIGHASH_ALL, signature_hash };
+use script_lang::script::{
+    Script, StackEntry, OpCodes,
+    MAX_SCRIPT_SIZE, MAX_OPS_PER_SCRIPT, MAX_PUB_KEYS_PER_MULTISIG, LOCKTIME_THRESHOLD
+};
+
+
+/*---- CONSTANTS ----*/
+
+/// Largest lock time `after` and `older` accept. Anything above would set
+/// the sequence number's disable flag, or need more than 4 bytes.
+const MAX_POLICY_LOCK_TIME: u32 = 0x7fffffff;
+
+/// Deepest nesting of fragments the parser accepts, as in Miniscript
+pub const MAX_POLICY_DEPTH: usize = 402;
+
+/// Size of the preimages `sha256` fragments accept
+const PREIMAGE_SIZE: usize = 32;
+
+/// Witness size of a signature with its sighash type byte, including its length
+const SIGNATURE_COST: f64 = (SIGNATURE_SIZE + 2) as f64;
+
+/// Witness size of a 32 byte preimage, including its length
+const PREIMAGE_COST: f64 = 33.0;
+
+/// Properties a parent can require of a child's compilation, as (dissatisfiable, unit)
+const REQUIREMENTS: [(bool, bool); 4] = [(false, false), (true, false), (false, true), (true, true)];
+
+
+/*---- TRAITS ----*/
+
+/// Source of the signatures, preimages and lock time checks needed to
+/// satisfy a compiled policy. Nothing is available by default.
+pub trait Satisfier {
+
+    /// Signature, with its sighash type byte, for a public key
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key`  - Raw public key to sign for
+    fn lookup_signature(&self, _public_key: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Preimage of a SHA256 hash
+    ///
+    /// ### Arguments
+    ///
+    /// * `hash`    - Hash to find the preimage of
+    fn lookup_preimage(&self, _hash: &[u8; 32]) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Whether the spending transaction meets an absolute lock time
+    ///
+    /// ### Arguments
+    ///
+    /// * `lock_time`   - Required lock time, as a block height or UNIX timestamp
+    fn check_after(&self, _lock_time: u32) -> bool {
+        false
+    }
+
+    /// Whether the spending input meets a relative lock time
+    ///
+    /// ### Arguments
+    ///
+    /// * `sequence`    - Required relative lock time, encoded as a sequence number
+    fn check_older(&self, _sequence: u32) -> bool {
+        false
+    }
+}
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// A spending policy, as parsed from its text form
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Policy {
+    Key(Vec<u8>),                   // pk(KEY)
+    After(u32),                     // after(N)
+    Older(u32),                     // older(N)
+    Sha256([u8; 32]),               // sha256(HASH)
+    And(Vec<Policy>),               // and(X,Y,...)
+    Or(Vec<Policy>),                // or(X,Y,...)
+    Threshold(usize, Vec<Policy>)   // thresh(K,X,Y,...)
+}
+
+/// A compiled script fragment. Each one consumes its inputs from the top
+/// of the stack and leaves a single result in their place.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Fragment {
+    PubKey(Vec<u8>),                        // <key> OP_CHECKSIG
+    After(u32),                             // <n> OP_CHECKLOCKTIMEVERIFY
+    Older(u32),                             // <n> OP_CHECKSEQUENCEVERIFY
+    Sha256([u8; 32]),                       // OP_SIZE <32> OP_EQUALVERIFY OP_SHA256 <hash> OP_EQUAL
+    AndV(Box<Fragment>, Box<Fragment>),     // [X] OP_VERIFY [Y]
+    AndB(Box<Fragment>, Box<Fragment>),     // [X] OP_TOALTSTACK [Y] OP_FROMALTSTACK OP_BOOLAND
+    OrD(Box<Fragment>, Box<Fragment>),      // [X] OP_IFDUP OP_NOTIF [Y] OP_ENDIF
+    OrI(Box<Fragment>, Box<Fragment>),      // OP_IF [X] OP_ELSE [Y] OP_ENDIF
+    Threshold(usize, Vec<Fragment>),        // [X1] OP_TOALTSTACK [X2] OP_FROMALTSTACK OP_ADD ... <k> OP_EQUAL
+    Multi(usize, Vec<Vec<u8>>),             // <k> <key>... <n> OP_CHECKMULTISIG
+    Dissatisfiable(Box<Fragment>)           // OP_DUP OP_IF [X] OP_VERIFY OP_ENDIF
+}
+
+/// Satisfier that signs for an input of a transaction with the keys it is
+/// given. Signatures commit to the whole transaction (SIGHASH_ALL).
+#[derive(Clone, Debug)]
+pub struct TransactionSatisfier<'a> {
+    tx: &'a Transaction,
+    input_index: usize,
+    amount: Amount,
+    script_code: Script,
+    sig_version: SigVersion,
+    keys: HashMap<Vec<u8>, SecretKey>,
+    preimages: HashMap<Vec<u8>, Vec<u8>>
+}
+
+/// Recursive descent parser over policy text
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize
+}
+
+/// Kinds of lock time a policy uses somewhere, and whether a single
+/// satisfaction could need both a height and a time of the same kind. A
+/// transaction has one lock time, and each input one sequence number, so
+/// such a satisfaction could never be valid.
+#[derive(Clone, Copy, Debug, Default)]
+struct TimelockInfo {
+    after_height: bool,
+    after_time: bool,
+    older_height: bool,
+    older_time: bool,
+    contains_combination: bool
+}
+
+/// Best compilation of a policy for each entry of REQUIREMENTS
+type Compilations = [Option<Fragment>; 4];
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl Policy {
+
+    /// Parses a policy from its text form. Keys and hashes are given as
+    /// hex, and lock times as decimal numbers.
+    ///
+    /// ### Arguments
+    ///
+    /// * `text`    - Policy text, such as `and(pk(KEY),after(100))`
+    pub fn parse(text: &str) -> Result<Policy, PolicyError> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+            depth: 0
+        };
+
+        let policy = parser.parse_policy()?;
+
+        match parser.peek() {
+            Some(c) => Err(PolicyError::UnexpectedChar(c, parser.position)),
+            None => Ok(policy)
+        }
+    }
+
+    /// Compiles the policy to the fragment tree with the smallest script
+    /// and expected satisfaction size. Fails if a satisfaction could need
+    /// both a height and a time lock of the same kind, or if no compilation
+    /// fits within the script limits.
+    pub fn compile(&self) -> Result<Fragment, PolicyError> {
+        if self.get_timelock_info().contains_combination {
+            return Err(PolicyError::MixedTimelocks);
+        }
+
+        match compile_policy(self)[0].clone() {
+            Some(fragment) => Ok(fragment),
+            None => Err(PolicyError::ResourceLimit)
+        }
+    }
+
+    /// Lock times used by the policy. An `or` only ever needs one of its
+    /// branches, but `and` needs all of them and a threshold above one
+    /// needs several.
+    fn get_timelock_info(&self) -> TimelockInfo {
+        match self {
+            Policy::Key(_) | Policy::Sha256(_) => TimelockInfo::default(),
+            Policy::After(n) => TimelockInfo {
+                after_height: *n < LOCKTIME_THRESHOLD,
+                after_time: *n >= LOCKTIME_THRESHOLD,
+                ..TimelockInfo::default()
+            },
+            Policy::Older(n) => TimelockInfo {
+                older_height: *n & SEQUENCE_LOCKTIME_TYPE_FLAG == 0,
+                older_time: *n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0,
+                ..TimelockInfo::default()
+            },
+            Policy::And(subs) => TimelockInfo::combine_threshold(subs.len(), subs),
+            Policy::Or(subs) => TimelockInfo::combine_threshold(1, subs),
+            Policy::Threshold(k, subs) => TimelockInfo::combine_threshold(*k, subs)
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Policy::Key(key) => write!(f, "pk({})", to_hex(key)),
+            Policy::After(n) => write!(f, "after({})", n),
+            Policy::Older(n) => write!(f, "older({})", n),
+            Policy::Sha256(hash) => write!(f, "sha256({})", to_hex(hash)),
+            Policy::And(subs) => write!(f, "and({})", join_policies(subs)),
+            Policy::Or(subs) => write!(f, "or({})", join_policies(subs)),
+            Policy::Threshold(k, subs) => write!(f, "thresh({},{})", k, join_policies(subs))
+        }
+    }
+}
+
+impl Fragment {
+
+    /// Builds the script for this fragment
+    pub fn to_script(&self) -> Script {
+        let mut script = Script::new();
+        self.write(&mut script, false);
+
+        script
+    }
+
+    /// Stack that satisfies the fragment, bottom first, or None if the
+    /// satisfier doesn't have what is needed. Where there is a choice,
+    /// the smallest satisfaction is taken.
+    ///
+    /// ### Arguments
+    ///
+    /// * `satisfier`   - Source of signatures, preimages and lock time checks
+    pub fn satisfy(&self, satisfier: &dyn Satisfier) -> Option<Vec<Vec<u8>>> {
+        match self {
+            Fragment::PubKey(key) => satisfier.lookup_signature(key).map(|sig| vec![sig]),
+            Fragment::After(n) => if satisfier.check_after(*n) { Some(Vec::new()) } else { None },
+            Fragment::Older(n) => if satisfier.check_older(*n) { Some(Vec::new()) } else { None },
+            Fragment::Sha256(hash) => satisfier.lookup_preimage(hash).map(|preimage| vec![preimage]),
+            Fragment::AndV(x, y) | Fragment::AndB(x, y) => {
+                let mut stack = y.satisfy(satisfier)?;
+                stack.extend(x.satisfy(satisfier)?);
+                Some(stack)
+            },
+            Fragment::OrD(x, y) => {
+                let second = match (y.satisfy(satisfier), x.dissatisfy()) {
+                    (Some(mut stack), Some(dissat)) => {
+                        stack.extend(dissat);
+                        Some(stack)
+                    },
+                    _ => None
+                };
+
+                smallest(x.satisfy(satisfier), second)
+            },
+            Fragment::OrI(x, y) => {
+                let first = x.satisfy(satisfier).map(|mut stack| { stack.push(vec![1]); stack });
+                let second = y.satisfy(satisfier).map(|mut stack| { stack.push(Vec::new()); stack });
+
+                smallest(first, second)
+            },
+            Fragment::Threshold(k, subs) => {
+                let mut sats: Vec<Option<Vec<Vec<u8>>>> = subs.iter().map(|sub| sub.satisfy(satisfier)).collect();
+                let dissats = subs.iter().map(|sub| sub.dissatisfy()).collect::<Option<Vec<_>>>()?;
+
+                // Satisfy the k children that cost least over dissatisfying them
+                let mut chosen: Vec<usize> = (0..subs.len()).filter(|i| sats[*i].is_some()).collect();
+
+                if chosen.len() < *k {
+                    return None;
+                }
+
+                chosen.sort_by_key(|i| get_witness_size(sats[*i].as_ref().unwrap()) as i64 - get_witness_size(&dissats[*i]) as i64);
+                chosen.truncate(*k);
+
+                // The first child runs first, so its inputs go on top
+                let mut stack = Vec::new();
+
+                for i in (0..subs.len()).rev() {
+                    match chosen.contains(&i) {
+                        true => stack.extend(sats[i].take().unwrap()),
+                        false => stack.extend(dissats[i].clone())
+                    }
+                }
+
+                Some(stack)
+            },
+            Fragment::Multi(k, keys) => {
+                // Signatures have to be in the same order as their keys
+                let sigs: Vec<Vec<u8>> = keys.iter().filter_map(|key| satisfier.lookup_signature(key)).take(*k).collect();
+
+                if sigs.len() < *k {
+                    return None;
+                }
+
+                // Extra element for the CHECKMULTISIG bug
+                let mut stack = vec![Vec::new()];
+                stack.extend(sigs);
+                Some(stack)
+            },
+            Fragment::Dissatisfiable(x) => {
+                let mut stack = x.satisfy(satisfier)?;
+                stack.push(vec![1]);
+                Some(stack)
+            }
+        }
+    }
+
+    /// Stack that makes the fragment leave a false result without
+    /// aborting the script, or None if it can't be dissatisfied
+    pub fn dissatisfy(&self) -> Option<Vec<Vec<u8>>> {
+        match self {
+            Fragment::PubKey(_) => Some(vec![Vec::new()]),
+            Fragment::After(_) | Fragment::Older(_) | Fragment::AndV(_, _) => None,
+            Fragment::Sha256(_) => Some(vec![vec![0; 32]]),
+            Fragment::AndB(x, y) | Fragment::OrD(x, y) => {
+                let mut stack = y.dissatisfy()?;
+                stack.extend(x.dissatisfy()?);
+                Some(stack)
+            },
+            Fragment::OrI(x, y) => {
+                let first = x.dissatisfy().map(|mut stack| { stack.push(vec![1]); stack });
+                let second = y.dissatisfy().map(|mut stack| { stack.push(Vec::new()); stack });
+
+                smallest(first, second)
+            },
+            Fragment::Threshold(_, subs) => {
+                let mut stack = Vec::new();
+
+                for sub in subs.iter().rev() {
+                    stack.extend(sub.dissatisfy()?);
+                }
+
+                Some(stack)
+            },
+            Fragment::Multi(k, _) => Some(vec![Vec::new(); k + 1]),
+            Fragment::Dissatisfiable(_) => Some(vec![Vec::new()])
+        }
+    }
+
+    /// Witness that spends a P2WSH output paying to this fragment's script
+    ///
+    /// ### Arguments
+    ///
+    /// * `satisfier`   - Source of signatures, preimages and lock time checks
+    pub fn get_witness(&self, satisfier: &dyn Satisfier) -> Option<Vec<Vec<u8>>> {
+        let mut witness = self.satisfy(satisfier)?;
+        witness.push(self.to_script().to_bytes());
+
+        Some(witness)
+    }
+
+    /// Signature script that spends a P2SH output paying to this fragment's
+    /// script. The script has to fit in a single push (520 bytes) for this
+    /// to be valid.
+    ///
+    /// ### Arguments
+    ///
+    /// * `satisfier`   - Source of signatures, preimages and lock time checks
+    pub fn get_script_sig(&self, satisfier: &dyn Satisfier) -> Option<Vec<u8>> {
+        let mut script_sig = Script::new();
+
+        for element in self.satisfy(satisfier)? {
+            push_element(&mut script_sig, &element);
+        }
+
+        script_sig.push_data(&self.to_script().to_bytes());
+        Some(script_sig.to_bytes())
+    }
+
+    /// Whether the fragment can be made to leave a false result without
+    /// aborting the script
+    pub fn is_dissatisfiable(&self) -> bool {
+        match self {
+            Fragment::PubKey(_) | Fragment::Sha256(_) | Fragment::Threshold(_, _) |
+            Fragment::Multi(_, _) | Fragment::Dissatisfiable(_) => true,
+            Fragment::After(_) | Fragment::Older(_) | Fragment::AndV(_, _) => false,
+            Fragment::AndB(x, y) => x.is_dissatisfiable() && y.is_dissatisfiable(),
+            Fragment::OrD(_, y) => y.is_dissatisfiable(),
+            Fragment::OrI(x, y) => x.is_dissatisfiable() || y.is_dissatisfiable()
+        }
+    }
+
+    /// Whether a satisfied fragment leaves exactly 1, rather than any true value
+    pub fn is_unit(&self) -> bool {
+        match self {
+            Fragment::PubKey(_) | Fragment::Sha256(_) | Fragment::AndB(_, _) | Fragment::Threshold(_, _) |
+            Fragment::Multi(_, _) | Fragment::Dissatisfiable(_) => true,
+            Fragment::After(_) | Fragment::Older(_) => false,
+            Fragment::AndV(_, y) => y.is_unit(),
+            Fragment::OrD(x, y) | Fragment::OrI(x, y) => x.is_unit() && y.is_unit()
+        }
+    }
+
+    /// Writes the fragment's script
+    ///
+    /// ### Arguments
+    ///
+    /// * `script`  - Script to append to
+    /// * `verify`  - Whether to abort on a false result instead of leaving it
+    fn write(&self, script: &mut Script, verify: bool) {
+        match self {
+            Fragment::PubKey(key) => {
+                script.push_data(key);
+                push_final_op(script, OpCodes::OP_CHECKSIG, OpCodes::OP_CHECKSIGVERIFY, verify);
+            },
+            Fragment::After(n) => {
+                push_number(script, *n as i64);
+                script.push_op(OpCodes::OP_CHECKLOCKTIMEVERIFY);
+                push_verify(script, verify);
+            },
+            Fragment::Older(n) => {
+                push_number(script, *n as i64);
+                script.push_op(OpCodes::OP_CHECKSEQUENCEVERIFY);
+                push_verify(script, verify);
+            },
+            Fragment::Sha256(hash) => {
+                script.push_op(OpCodes::OP_SIZE);
+                push_number(script, 32);
+                script.push_op(OpCodes::OP_EQUALVERIFY)
+                      .push_op(OpCodes::OP_SHA256)
+                      .push_data(hash);
+                push_final_op(script, OpCodes::OP_EQUAL, OpCodes::OP_EQUALVERIFY, verify);
+            },
+            Fragment::AndV(x, y) => {
+                x.write(script, true);
+                y.write(script, verify);
+            },
+            Fragment::AndB(x, y) => {
+                x.write(script, false);
+                script.push_op(OpCodes::OP_TOALTSTACK);
+                y.write(script, false);
+                script.push_op(OpCodes::OP_FROMALTSTACK).push_op(OpCodes::OP_BOOLAND);
+                push_verify(script, verify);
+            },
+            Fragment::OrD(x, y) => {
+                x.write(script, false);
+                script.push_op(OpCodes::OP_IFDUP).push_op(OpCodes::OP_NOTIF);
+                y.write(script, false);
+                script.push_op(OpCodes::OP_ENDIF);
+                push_verify(script, verify);
+            },
+            Fragment::OrI(x, y) => {
+                script.push_op(OpCodes::OP_IF);
+                x.write(script, false);
+                script.push_op(OpCodes::OP_ELSE);
+                y.write(script, false);
+                script.push_op(OpCodes::OP_ENDIF);
+                push_verify(script, verify);
+            },
+            Fragment::Threshold(k, subs) => {
+                subs[0].write(script, false);
+
+                for sub in &subs[1..] {
+                    script.push_op(OpCodes::OP_TOALTSTACK);
+                    sub.write(script, false);
+                    script.push_op(OpCodes::OP_FROMALTSTACK).push_op(OpCodes::OP_ADD);
+                }
+
+                push_number(script, *k as i64);
+                push_final_op(script, OpCodes::OP_EQUAL, OpCodes::OP_EQUALVERIFY, verify);
+            },
+            Fragment::Multi(k, keys) => {
+                push_number(script, *k as i64);
+
+                for key in keys {
+                    script.push_data(key);
+                }
+
+                push_number(script, keys.len() as i64);
+                push_final_op(script, OpCodes::OP_CHECKMULTISIG, OpCodes::OP_CHECKMULTISIGVERIFY, verify);
+            },
+            Fragment::Dissatisfiable(x) => {
+                // No OP_0 for the false branch. Legacy signature checks
+                // delete an empty signature's push, OP_0, from the script
+                // code, which fails under CONST_SCRIPTCODE if it is there.
+                script.push_op(OpCodes::OP_DUP).push_op(OpCodes::OP_IF);
+                x.write(script, true);
+                script.push_op(OpCodes::OP_ENDIF);
+                push_verify(script, verify);
+            }
+        }
+    }
+
+    /// Expected witness size of a satisfaction, taking each branch of an
+    /// `or` and each child of a threshold as equally likely
+    fn get_sat_cost(&self) -> f64 {
+        match self {
+            Fragment::PubKey(_) => SIGNATURE_COST,
+            Fragment::After(_) | Fragment::Older(_) => 0.0,
+            Fragment::Sha256(_) => PREIMAGE_COST,
+            Fragment::AndV(x, y) | Fragment::AndB(x, y) => x.get_sat_cost() + y.get_sat_cost(),
+            Fragment::OrD(x, y) => (x.get_sat_cost() + y.get_sat_cost() + x.get_dissat_cost()) / 2.0,
+            Fragment::OrI(x, y) => (x.get_sat_cost() + 2.0 + y.get_sat_cost() + 1.0) / 2.0,
+            Fragment::Threshold(k, subs) => {
+                let p = *k as f64 / subs.len() as f64;
+                subs.iter().map(|sub| p * sub.get_sat_cost() + (1.0 - p) * sub.get_dissat_cost()).sum()
+            },
+            Fragment::Multi(k, _) => 1.0 + *k as f64 * SIGNATURE_COST,
+            Fragment::Dissatisfiable(x) => x.get_sat_cost() + 2.0
+        }
+    }
+
+    /// Witness size of the smallest dissatisfaction, or infinite if there is none
+    fn get_dissat_cost(&self) -> f64 {
+        match self {
+            Fragment::PubKey(_) | Fragment::Dissatisfiable(_) => 1.0,
+            Fragment::After(_) | Fragment::Older(_) | Fragment::AndV(_, _) => f64::INFINITY,
+            Fragment::Sha256(_) => PREIMAGE_COST,
+            Fragment::AndB(x, y) | Fragment::OrD(x, y) => x.get_dissat_cost() + y.get_dissat_cost(),
+            Fragment::OrI(x, y) => (x.get_dissat_cost() + 2.0).min(y.get_dissat_cost() + 1.0),
+            Fragment::Threshold(_, subs) => subs.iter().map(|sub| sub.get_dissat_cost()).sum(),
+            Fragment::Multi(k, _) => 1.0 + *k as f64
+        }
+    }
+
+    /// Cost the compiler minimizes: script size plus expected satisfaction
+    /// size, plus the dissatisfaction size if the parent may need it
+    fn get_cost(&self, dissatisfiable: bool) -> f64 {
+        let mut cost = self.to_script().to_bytes().len() as f64 + self.get_sat_cost();
+
+        if dissatisfiable {
+            cost += self.get_dissat_cost();
+        }
+
+        cost
+    }
+
+    /// Whether the fragment can be used by a parent that needs these properties
+    fn meets(&self, dissatisfiable: bool, unit: bool) -> bool {
+        (!dissatisfiable || self.is_dissatisfiable()) && (!unit || self.is_unit())
+    }
+}
+
+impl TimelockInfo {
+
+    /// Lock times of a threshold of `k` sub-policies. If more than one of
+    /// them is needed, a height and a time of the same kind in different
+    /// sub-policies could end up in the same satisfaction.
+    ///
+    /// ### Arguments
+    ///
+    /// * `k`       - Number of sub-policies that have to be satisfied
+    /// * `subs`    - Sub-policies
+    fn combine_threshold(k: usize, subs: &[Policy]) -> TimelockInfo {
+        let mut combined = TimelockInfo::default();
+
+        for sub in subs {
+            let info = sub.get_timelock_info();
+
+            if k > 1 {
+                combined.contains_combination |=
+                    (combined.after_height && info.after_time) || (combined.after_time && info.after_height) ||
+                    (combined.older_height && info.older_time) || (combined.older_time && info.older_height);
+            }
+
+            combined.after_height |= info.after_height;
+            combined.after_time |= info.after_time;
+            combined.older_height |= info.older_height;
+            combined.older_time |= info.older_time;
+            combined.contains_combination |= info.contains_combination;
+        }
+
+        combined
+    }
+}
+
+impl<'a> TransactionSatisfier<'a> {
+
+    /// Returns a satisfier for the given input of a transaction, with no
+    /// keys or preimages yet
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`          - Spending transaction
+    /// * `input_index` - Index of the input being satisfied
+    /// * `amount`      - Value of the output the input spends
+    /// * `script_code` - Script being satisfied (the witness or redeem script)
+    /// * `sig_version` - WitnessV0 for P2WSH, Base for P2SH
+    pub fn new(
+        tx: &'a Transaction,
+        input_index: usize,
+        amount: Amount,
+        script_code: &Script,
+        sig_version: SigVersion
+    ) -> TransactionSatisfier<'a>
+    {
+        TransactionSatisfier {
+            tx,
+            input_index,
+            amount,
+            script_code: script_code.clone(),
+            sig_version,
+            keys: HashMap::new(),
+            preimages: HashMap::new()
+        }
+    }
+
+    /// Makes a key available to sign with
+    ///
+    /// ### Arguments
+    ///
+    /// * `public_key`  - Public key, as used in the policy
+    /// * `secret_key`  - Secret key to sign with
+    pub fn add_key(&mut self, public_key: &PublicKey, secret_key: &SecretKey) -> &mut TransactionSatisfier<'a> {
+        self.keys.insert(public_key.as_ref().to_vec(), secret_key.clone());
+        self
+    }
+
+    /// Makes a hash preimage available. Compiled `sha256` fragments check
+    /// that the preimage is 32 bytes, so preimages of any other size can
+    /// never satisfy them and are ignored.
+    ///
+    /// ### Arguments
+    ///
+    /// * `preimage`    - Preimage of a hash in the policy
+    pub fn add_preimage(&mut self, preimage: &[u8]) -> &mut TransactionSatisfier<'a> {
+        if preimage.len() == PREIMAGE_SIZE {
+            self.preimages.insert(sha256(preimage), preimage.to_vec());
+        }
+
+        self
+    }
+
+    /// Checker for the lock times of the input being satisfied
+    fn get_checker(&self) -> TransactionSignatureChecker<'a> {
+        TransactionSignatureChecker::new(self.tx, self.input_index, self.amount)
+    }
+}
+
+impl<'a> Satisfier for TransactionSatisfier<'a> {
+    fn lookup_signature(&self, public_key: &[u8]) -> Option<Vec<u8>> {
+        let secret_key = self.keys.get(public_key)?;
+        let sighash = signature_hash(
+            &self.script_code,
+            self.tx,
+            self.input_index,
+            SIGHASH_ALL,
+            self.amount,
+            self.sig_version,
+            None
+        )?;
+
+        let mut signature = sign_message(sighash.as_bytes(), secret_key);
+        signature.push(SIGHASH_ALL as u8);
+
+        Some(signature)
+    }
+
+    fn lookup_preimage(&self, hash: &[u8; 32]) -> Option<Vec<u8>> {
+        self.preimages.get(&hash[..]).cloned()
+    }
+
+    fn check_after(&self, lock_time: u32) -> bool {
+        self.get_checker().check_lock_time(ScriptNum::new(lock_time as i64))
+    }
+
+    fn check_older(&self, sequence: u32) -> bool {
+        self.get_checker().check_sequence(ScriptNum::new(sequence as i64))
+    }
+}
+
+impl Parser {
+
+    /// Parses a single fragment and its arguments, failing if it is nested
+    /// too deeply
+    fn parse_policy(&mut self) -> Result<Policy, PolicyError> {
+        if self.depth >= MAX_POLICY_DEPTH {
+            return Err(PolicyError::MaxDepthExceeded);
+        }
+
+        self.depth += 1;
+        let policy = self.parse_fragment();
+        self.depth -= 1;
+
+        policy
+    }
+
+    /// Parses a single fragment and its arguments
+    fn parse_fragment(&mut self) -> Result<Policy, PolicyError> {
+        let name = self.read_token();
+
+        if name.is_empty() {
+            return Err(self.unexpected());
+        }
+
+        self.expect('(')?;
+
+        let policy = match name.as_str() {
+            "pk" => Policy::Key(parse_key(&self.read_token())?),
+            "after" => Policy::After(parse_lock_time(&self.read_token())?),
+            "older" => Policy::Older(parse_lock_time(&self.read_token())?),
+            "sha256" => Policy::Sha256(parse_hash(&self.read_token())?),
+            "and" | "or" => {
+                let subs = self.parse_sub_policies()?;
+
+                if subs.len() < 2 {
+                    return Err(PolicyError::WrongArgCount(name));
+                }
+
+                if name == "and" { Policy::And(subs) } else { Policy::Or(subs) }
+            },
+            "thresh" => {
+                let start = self.position;
+                let k = match self.read_token().parse::<usize>() {
+                    Ok(k) => k,
+                    Err(_) => {
+                        self.position = start;
+                        return Err(self.unexpected());
+                    }
+                };
+
+                self.expect(',')?;
+                let subs = self.parse_sub_policies()?;
+
+                if k == 0 || k > subs.len() {
+                    return Err(PolicyError::InvalidThreshold(k, subs.len()));
+                }
+
+                Policy::Threshold(k, subs)
+            },
+            _ => return Err(PolicyError::UnknownFragment(name))
+        };
+
+        self.expect(')')?;
+        Ok(policy)
+    }
+
+    /// Parses a comma separated list of policies
+    fn parse_sub_policies(&mut self) -> Result<Vec<Policy>, PolicyError> {
+        let mut subs = vec![self.parse_policy()?];
+
+        while self.peek() == Some(',') {
+            self.position += 1;
+            subs.push(self.parse_policy()?);
+        }
+
+        Ok(subs)
+    }
+
+    /// Reads a fragment name, number, key or hash
+    fn read_token(&mut self) -> String {
+        self.peek();
+        let start = self.position;
+
+        while self.position < self.chars.len() && self.chars[self.position].is_ascii_alphanumeric() {
+            self.position += 1;
+        }
+
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Skips whitespace and returns the next character, if any
+    fn peek(&mut self) -> Option<char> {
+        while self.position < self.chars.len() && self.chars[self.position].is_whitespace() {
+            self.position += 1;
+        }
+
+        self.chars.get(self.position).cloned()
+    }
+
+    /// Consumes the given character
+    fn expect(&mut self, c: char) -> Result<(), PolicyError> {
+        match self.peek() {
+            Some(next) if next == c => {
+                self.position += 1;
+                Ok(())
+            },
+            _ => Err(self.unexpected())
+        }
+    }
+
+    /// Error for whatever is at the current position
+    fn unexpected(&mut self) -> PolicyError {
+        match self.peek() {
+            Some(c) => PolicyError::UnexpectedChar(c, self.position),
+            None => PolicyError::UnexpectedEnd
+        }
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Compiles a policy for each set of requirements a parent may have
+///
+/// ### Arguments
+///
+/// * `policy`  - Policy to compile
+fn compile_policy(policy: &Policy) -> Compilations {
+    match policy {
+        Policy::Key(key) => get_best(vec![Fragment::PubKey(key.clone())]),
+        Policy::After(n) => get_best(vec![Fragment::After(*n)]),
+        Policy::Older(n) => get_best(vec![Fragment::Older(*n)]),
+        Policy::Sha256(hash) => get_best(vec![Fragment::Sha256(*hash)]),
+        Policy::And(subs) => fold_and(&subs.iter().map(compile_policy).collect::<Vec<_>>()),
+        Policy::Or(subs) => fold_or(&subs.iter().map(compile_policy).collect::<Vec<_>>()),
+        Policy::Threshold(k, subs) => {
+            let children: Vec<Compilations> = subs.iter().map(compile_policy).collect();
+            let mut candidates = Vec::new();
+
+            if *k == subs.len() {
+                candidates.extend(fold_and(&children).iter().flatten().cloned());
+            }
+
+            if *k == 1 {
+                candidates.extend(fold_or(&children).iter().flatten().cloned());
+            }
+
+            // Every child has to be able to fail for the count to work
+            if subs.len() > 1 && children.iter().all(|child| child[3].is_some()) {
+                let fragments = children.iter().map(|child| child[3].clone().unwrap()).collect();
+                candidates.push(Fragment::Threshold(*k, fragments));
+            }
+
+            let keys: Vec<Vec<u8>> = subs.iter().filter_map(|sub| match sub {
+                Policy::Key(key) => Some(key.clone()),
+                _ => None
+            }).collect();
+
+            if keys.len() == subs.len() && keys.len() <= MAX_PUB_KEYS_PER_MULTISIG {
+                candidates.push(Fragment::Multi(*k, keys));
+            }
+
+            get_best(candidates)
+        }
+    }
+}
+
+/// Compiles the `and` of a list of compiled policies, pairing up from the right
+fn fold_and(children: &[Compilations]) -> Compilations {
+    let (last, rest) = children.split_last().unwrap();
+    let mut folded = last.clone();
+
+    for child in rest.iter().rev() {
+        let mut candidates = Vec::new();
+
+        for x in child.iter().flatten() {
+            for y in folded.iter().flatten() {
+                candidates.push(Fragment::AndV(Box::new(x.clone()), Box::new(y.clone())));
+
+                if x.meets(true, true) && y.meets(true, true) {
+                    candidates.push(Fragment::AndB(Box::new(x.clone()), Box::new(y.clone())));
+                }
+            }
+        }
+
+        folded = get_best(candidates);
+    }
+
+    folded
+}
+
+/// Compiles the `or` of a list of compiled policies, pairing up from the right
+fn fold_or(children: &[Compilations]) -> Compilations {
+    let (last, rest) = children.split_last().unwrap();
+    let mut folded = last.clone();
+
+    for child in rest.iter().rev() {
+        let mut candidates = Vec::new();
+
+        for x in child.iter().flatten() {
+            for y in folded.iter().flatten() {
+                // OP_IFDUP OP_NOTIF needs a 0 or 1 from the first branch, both
+                // to fall through to the second and to pass MINIMALIF
+                if x.meets(true, true) {
+                    candidates.push(Fragment::OrD(Box::new(x.clone()), Box::new(y.clone())));
+                }
+
+                if y.meets(true, true) {
+                    candidates.push(Fragment::OrD(Box::new(y.clone()), Box::new(x.clone())));
+                }
+
+                candidates.push(Fragment::OrI(Box::new(x.clone()), Box::new(y.clone())));
+            }
+        }
+
+        folded = get_best(candidates);
+    }
+
+    folded
+}
+
+/// Picks the cheapest candidate for each set of requirements. Candidates
+/// that can't be dissatisfied, or don't leave 0 or 1, are also offered
+/// wrapped so that they do. Candidates whose script breaks the script size
+/// or opcode limit are dropped, since no parent could bring them back
+/// within it.
+///
+/// ### Arguments
+///
+/// * `candidates`  - Fragments that implement the same policy
+fn get_best(mut candidates: Vec<Fragment>) -> Compilations {
+    let wrapped: Vec<Fragment> = candidates.iter()
+        .filter(|candidate| !candidate.meets(true, true))
+        .map(|candidate| Fragment::Dissatisfiable(Box::new(candidate.clone())))
+        .collect();
+
+    candidates.extend(wrapped);
+    candidates.retain(is_within_limits);
+
+    let mut best: Compilations = [None, None, None, None];
+
+    for (i, &(dissatisfiable, unit)) in REQUIREMENTS.iter().enumerate() {
+        best[i] = candidates.iter()
+            .filter(|candidate| candidate.meets(dissatisfiable, unit))
+            .map(|candidate| (candidate.get_cost(dissatisfiable), candidate))
+            .fold(None, |best: Option<(f64, &Fragment)>, (cost, candidate)| match best {
+                Some((best_cost, _)) if best_cost <= cost => best,
+                _ => Some((cost, candidate))
+            })
+            .map(|(_, candidate)| candidate.clone());
+    }
+
+    best
+}
+
+/// Whether a fragment's script fits within the script size and opcode limits
+fn is_within_limits(fragment: &Fragment) -> bool {
+    let script = fragment.to_script();
+
+    script.to_bytes().len() <= MAX_SCRIPT_SIZE && get_op_count(&script) <= MAX_OPS_PER_SCRIPT
+}
+
+/// Opcodes counted towards MAX_OPS_PER_SCRIPT, including the keys of each
+/// OP_CHECKMULTISIG
+fn get_op_count(script: &Script) -> usize {
+    let entries = script.get_entries();
+    let mut count = 0;
+
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.get_op_code() > OpCodes::OP_16 as u8 {
+            count += 1;
+        }
+
+        if *entry == StackEntry::Op(OpCodes::OP_CHECKMULTISIG) || *entry == StackEntry::Op(OpCodes::OP_CHECKMULTISIGVERIFY) {
+            count += match entries[i - 1] {
+                StackEntry::Op(op) => (op as u8).saturating_sub(OpCodes::OP_1 as u8 - 1) as usize,
+                StackEntry::Push(_, ref data) => ScriptNum::from_bytes(data, false, DEFAULT_MAX_NUM_SIZE).map_or(0, |n| n.value() as usize),
+                StackEntry::Unknown(_) => 0
+            };
+        }
+    }
+
+    count
+}
+
+/// Appends a number, as an OP_n where possible
+fn push_number(script: &mut Script, n: i64) {
+    match n {
+        0 => { script.push_op(OpCodes::OP_0); },
+        1..=16 => { script.push_op(OpCodes::from_u8(OpCodes::OP_1 as u8 + n as u8 - 1).unwrap()); },
+        _ => { script.push_data(&ScriptNum::new(n).to_bytes()); }
+    }
+}
+
+/// Appends a stack element with the push MINIMALDATA requires
+fn push_element(script: &mut Script, element: &[u8]) {
+    match element {
+        [n] if *n >= 1 && *n <= 16 => push_number(script, *n as i64),
+        [0x81] => { script.push_op(OpCodes::OP_1NEGATE); },
+        _ => { script.push_data(element); }
+    }
+}
+
+/// Appends a fragment's final opcode, or its VERIFY form
+fn push_final_op(script: &mut Script, op: OpCodes, verify_op: OpCodes, verify: bool) {
+    script.push_op(if verify { verify_op } else { op });
+}
+
+/// Appends OP_VERIFY if the result should be verified
+fn push_verify(script: &mut Script, verify: bool) {
+    if verify {
+        script.push_op(OpCodes::OP_VERIFY);
+    }
+}
+
+/// Serialized size of a witness stack, with a length byte per element
+fn get_witness_size(stack: &[Vec<u8>]) -> usize {
+    stack.iter().map(|element| element.len() + 1).sum()
+}
+
+/// The smaller of two possible stacks
+fn smallest(a: Option<Vec<Vec<u8>>>, b: Option<Vec<Vec<u8>>>) -> Option<Vec<Vec<u8>>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if get_witness_size(&b) < get_witness_size(&a) { b } else { a }),
+        (a, b) => a.or(b)
+    }
+}
+
+/// Policies joined by commas, for display
+fn join_policies(policies: &[Policy]) -> String {
+    let texts: Vec<String> = policies.iter().map(|policy| policy.to_string()).collect();
+
+    texts.join(",")
+}
+
+/// Parses a hex encoded public key
+fn parse_key(token: &str) -> Result<Vec<u8>, PolicyError> {
+    match from_hex(token) {
+        Ok(ref key) if key.len() == PUBLIC_KEY_SIZE => Ok(key.clone()),
+        _ => Err(PolicyError::InvalidKey(token.to_string()))
+    }
+}
+
+/// Parses a hex encoded 32 byte hash
+fn parse_hash(token: &str) -> Result<[u8; 32], PolicyError> {
+    match from_hex(token) {
+        Ok(ref bytes) if bytes.len() == 32 => {
+            let mut hash = [0; 32];
+            hash.copy_from_slice(bytes);
+            Ok(hash)
+        },
+        _ => Err(PolicyError::InvalidHash(token.to_string()))
+    }
+}
+
+/// Parses a lock time for `after` or `older`
+fn parse_lock_time(token: &str) -> Result<u32, PolicyError> {
+    match token.parse::<u32>() {
+        Ok(n) if (1..=MAX_POLICY_LOCK_TIME).contains(&n) => Ok(n),
+        _ => Err(PolicyError::InvalidLockTime(token.to_string()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryptography::hash::hash160;
+    use cryptography::keys::generate_keypair;
+    use policy::STANDARD_SCRIPT_VERIFY_FLAGS;
+    use primitives::test_utils::sample_transaction;
+    use script_lang::interpreter::verify_script;
+
+    fn sample_keys(count: usize) -> Vec<(PublicKey, SecretKey)> {
+        (0..count).map(|_| generate_keypair()).collect()
+    }
+
+    fn nested_ands(depth: usize) -> String {
+        let mut text = "pk(".to_string() + &"11".repeat(PUBLIC_KEY_SIZE) + ")";
+
+        for _ in 1..depth {
+            text = format!("and({},after(1))", text);
+        }
+
+        text
+    }
+
+    /// Spends a P2WSH and a P2SH output paying to the compiled policy with
+    /// the given keys and preimages, through the script interpreter
+    fn verify_spends(policy: &str, keys: &[(PublicKey, SecretKey)], preimages: &[Vec<u8>]) -> bool {
+        let fragment = Policy::parse(policy).unwrap().compile().unwrap();
+        let script = fragment.to_script();
+        let tx = sample_transaction(1, 1);
+        let amount = Amount::from_sat(1000).unwrap();
+        let checker = TransactionSignatureChecker::new(&tx, 0, amount);
+
+        let spend = |sig_version: SigVersion| {
+            let mut satisfier = TransactionSatisfier::new(&tx, 0, amount, &script, sig_version);
+
+            for (public_key, secret_key) in keys {
+                satisfier.add_key(public_key, secret_key);
+            }
+
+            for preimage in preimages {
+                satisfier.add_preimage(preimage);
+            }
+
+            satisfier
+        };
+
+        let mut p2wsh = Script::new();
+        p2wsh.push_op(OpCodes::OP_0).push_data(&sha256(&script.to_bytes()));
+
+        let mut p2sh = Script::new();
+        p2sh.push_op(OpCodes::OP_HASH160).push_data(&hash160(&script.to_bytes())).push_op(OpCodes::OP_EQUAL);
+
+        let witness_ok = match fragment.get_witness(&spend(SigVersion::WitnessV0)) {
+            Some(witness) => verify_script(&[], &p2wsh.to_bytes(), &witness, STANDARD_SCRIPT_VERIFY_FLAGS, &checker).is_ok(),
+            None => false
+        };
+
+        let script_sig_ok = match fragment.get_script_sig(&spend(SigVersion::Base)) {
+            Some(script_sig) => verify_script(&script_sig, &p2sh.to_bytes(), &[], STANDARD_SCRIPT_VERIFY_FLAGS, &checker).is_ok(),
+            None => false
+        };
+
+        assert_eq!(witness_ok, script_sig_ok, "{}", policy);
+        witness_ok
+    }
+
+    #[test]
+    fn policies_round_trip() {
+        let key = "ab".repeat(PUBLIC_KEY_SIZE);
+        let hash = "cd".repeat(32);
+        let text = format!("or(and(pk({}),older(144)),thresh(2,pk({}),sha256({}),after(500000)))", key, key, hash);
+        let policy = Policy::parse(&text).unwrap();
+
+        assert_eq!(policy.to_string(), text);
+        assert_eq!(Policy::parse(&policy.to_string()), Ok(policy));
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        let key = "ab".repeat(PUBLIC_KEY_SIZE);
+
+        assert!(Policy::parse("").is_err());
+        assert!(Policy::parse("pk(00)").is_err());
+        assert!(Policy::parse("sha256(00)").is_err());
+        assert!(Policy::parse("after(0)").is_err());
+        assert!(Policy::parse("after(4294967295)").is_err());
+        assert!(Policy::parse("foo(1)").is_err());
+        assert!(Policy::parse(&format!("and(pk({}))", key)).is_err());
+        assert!(Policy::parse(&format!("thresh(3,pk({}),pk({}))", key, key)).is_err());
+        assert!(Policy::parse(&format!("pk({})x", key)).is_err());
+        assert!(Policy::parse(&format!("or(pk({}),pk({})", key, key)).is_err());
+    }
+
+    #[test]
+    fn nesting_depth_is_limited() {
+        assert!(Policy::parse(&nested_ands(MAX_POLICY_DEPTH)).is_ok());
+        assert_eq!(Policy::parse(&nested_ands(MAX_POLICY_DEPTH + 1)), Err(PolicyError::MaxDepthExceeded));
+
+        // Deep enough to overflow the stack if parsing recursed unchecked
+        assert_eq!(Policy::parse(&"and(".repeat(100000)), Err(PolicyError::MaxDepthExceeded));
+    }
+
+    #[test]
+    fn mixed_timelocks_are_rejected() {
+        let compile = |text: &str| Policy::parse(text).unwrap().compile().map(|_| ());
+        let time_flag = SEQUENCE_LOCKTIME_TYPE_FLAG | 10;
+
+        assert_eq!(compile("and(after(100),after(500000001))"), Err(PolicyError::MixedTimelocks));
+        assert_eq!(compile(&format!("and(older(10),older({}))", time_flag)), Err(PolicyError::MixedTimelocks));
+        assert_eq!(compile("thresh(2,after(100),after(200),after(500000001))"), Err(PolicyError::MixedTimelocks));
+        assert_eq!(compile("or(after(1),and(after(100),or(after(200),after(500000001))))"), Err(PolicyError::MixedTimelocks));
+
+        // Only one branch is ever needed, and the two kinds don't conflict
+        assert_eq!(compile("or(after(100),after(500000001))"), Ok(()));
+        assert_eq!(compile("thresh(1,after(100),after(500000001))"), Ok(()));
+        assert_eq!(compile(&format!("and(after(100),older({}))", time_flag)), Ok(()));
+        assert_eq!(compile("and(after(100),after(200))"), Ok(()));
+    }
+
+    #[test]
+    fn compilations_stay_within_limits() {
+        let keys = sample_keys(55);
+        let subs: Vec<String> = keys.iter().map(|(public_key, _)| format!("pk({})", to_hex(public_key.as_ref()))).collect();
+
+        let fragment = Policy::parse(&format!("thresh(2,{})", subs[..50].join(","))).unwrap().compile().unwrap();
+        let script = fragment.to_script();
+
+        assert!(script.to_bytes().len() <= MAX_SCRIPT_SIZE);
+        assert!(get_op_count(&script) <= MAX_OPS_PER_SCRIPT);
+
+        // Every compilation of these breaks the opcode limit
+        let policy = Policy::parse(&format!("thresh(2,{})", subs.join(","))).unwrap();
+        assert_eq!(policy.compile(), Err(PolicyError::ResourceLimit));
+
+        let afters: Vec<String> = (0..300).map(|_| "after(1)".to_string()).collect();
+        let policy = Policy::parse(&format!("and({})", afters.join(","))).unwrap();
+        assert_eq!(policy.compile(), Err(PolicyError::ResourceLimit));
+    }
+
+    #[test]
+    fn satisfactions_verify() {
+        let keys = sample_keys(3);
+        let hex: Vec<String> = keys.iter().map(|(public_key, _)| to_hex(public_key.as_ref())).collect();
+        let preimages = vec![vec![7; 32]];
+        let hash = to_hex(&sha256(&preimages[0]));
+
+        assert!(verify_spends(&format!("pk({})", hex[0]), &keys[..1], &[]));
+        assert!(!verify_spends(&format!("pk({})", hex[0]), &keys[1..], &[]));
+
+        let multi = format!("thresh(2,pk({}),pk({}),pk({}))", hex[0], hex[1], hex[2]);
+        assert!(verify_spends(&multi, &keys[1..], &[]));
+        assert!(!verify_spends(&multi, &keys[..1], &[]));
+
+        let hash_lock = format!("or(pk({}),and(sha256({}),after(400)))", hex[0], hash);
+        assert!(verify_spends(&hash_lock, &keys[..1], &[]));
+        assert!(verify_spends(&hash_lock, &[], &preimages));
+        assert!(!verify_spends(&hash_lock, &keys[1..], &[]));
+
+        // The transaction's lock time and sequence are too low for these
+        assert!(!verify_spends(&format!("and(sha256({}),after(600))", hash), &[], &preimages));
+        assert!(!verify_spends(&format!("and(pk({}),older(20))", hex[0]), &keys[..1], &[]));
+        assert!(verify_spends(&format!("and(pk({}),older(5))", hex[0]), &keys[..1], &[]));
+
+        let mixed = format!("thresh(2,pk({}),sha256({}),older(10))", hex[0], hash);
+        assert!(verify_spends(&mixed, &keys[..1], &preimages));
+        assert!(verify_spends(&mixed, &[], &preimages));
+        assert!(!verify_spends(&mixed, &[], &[]));
+    }
+
+    #[test]
+    fn preimages_must_be_32_bytes() {
+        let tx = sample_transaction(1, 1);
+        let mut satisfier = TransactionSatisfier::new(&tx, 0, Amount::ZERO, &Script::new(), SigVersion::WitnessV0);
+        let short = vec![7; 31];
+
+        satisfier.add_preimage(&short).add_preimage(&[7; 33]);
+        assert!(satisfier.preimages.is_empty());
+
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&sha256(&short));
+        assert_eq!(Fragment::Sha256(hash).satisfy(&satisfier), None);
+    }
+}
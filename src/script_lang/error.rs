@@ -0,0 +1,225 @@
+/*
+ * Errors raised while parsing or executing scripts. These mirror Bitcoin's
+ * ScriptError_t codes, so a failing spend can be pinned to a precise rule.
+ */
+
+use std::fmt;
+use std::error::Error;
+
+
+/*---- ENUMS ----*/
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ScriptError {
+    EvalFalse,                  // Script ran to completion with a false or empty top stack element
+    OpReturn,                   // OP_RETURN was executed
+
+    // Max sizes
+    ScriptSize,                 // Script is longer than MAX_SCRIPT_SIZE
+    PushSize,                   // Pushed element is larger than MAX_SCRIPT_ELEMENT_SIZE
+    OpCount,                    // More than MAX_OPS_PER_SCRIPT non-push operations
+    StackSize,                  // Stack and altstack together exceed MAX_STACK_SIZE
+    SigCount,                   // Negative signature count, or more signatures than keys
+    PubKeyCount,                // Negative key count, or more than MAX_PUB_KEYS_PER_MULTISIG
+
+    // Failed verify operations
+    Verify,
+    EqualVerify,
+    CheckMultiSigVerify,
+    CheckSigVerify,
+    NumEqualVerify,
+
+    // Logical/Format/Canonical errors
+    BadOpcode,                  // Opcode missing or not understood, including truncated pushes
+    DisabledOpcode,
+    InvalidStackOperation,
+    InvalidAltstackOperation,
+    UnbalancedConditional,
+
+    // CHECKLOCKTIMEVERIFY and CHECKSEQUENCEVERIFY
+    NegativeLocktime,
+    UnsatisfiedLocktime,
+    NumOverflow,                // Numeric operand longer than allowed
+    NumNotMinimal,              // Numeric operand not minimally encoded, under MINIMALDATA
+
+    // CHECKTEMPLATEVERIFY
+    TemplateMismatch,           // Transaction doesn't match the committed template hash
+
+    // BIP62
+    SigHashType,
+    SigDer,
+    MinimalData,
+    SigPushOnly,
+    SigHighS,
+    SigNullDummy,
+    PubKeyType,
+    CleanStack,
+    MinimalIf,
+    SigNullFail,
+
+    // Softfork safeness
+    DiscourageUpgradableNops,
+    DiscourageUpgradableWitnessProgram,
+    DiscourageUpgradableTaprootVersion,
+    DiscourageOpSuccess,
+    DiscourageUpgradablePubKeyType,
+
+    // Segregated witness
+    WitnessProgramWrongLength,
+    WitnessProgramWitnessEmpty,
+    WitnessProgramMismatch,
+    WitnessMalleated,
+    WitnessMalleatedP2SH,
+    WitnessUnexpected,
+    WitnessPubKeyType,
+
+    // Taproot
+    SchnorrSigSize,
+    SchnorrSigHashType,
+    SchnorrSig,
+    TaprootWrongControlSize,
+    TaprootMissingSpentOutputs, // Checker wasn't given the spent outputs that taproot signatures commit to
+    TapscriptValidationWeight,  // Signature checks used up the input's validation weight budget
+    TapscriptCheckMultiSig,
+    TapscriptMinimalIf,
+    TapscriptEmptyPubKey,
+
+    // Constant scriptCode
+    OpCodeSeparator,
+    SigFindAndDelete
+}
+
+
+/// Errors raised while reading a script from its assembly text
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AsmError {
+    UnknownOpcode(String),      // Token is neither an opcode name, a 0x byte nor <hex> data
+    InvalidPushData(String),    // Push data is not valid hex, or too long for its push opcode
+    MissingPushData(String),    // Push opcode is not followed by <hex> data
+    Script(ScriptError)         // Resulting script breaks a size limit
+}
+
+/// Errors raised while parsing or compiling a spending policy
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PolicyError {
+    UnexpectedEnd,                  // Policy text ends in the middle of a fragment
+    UnexpectedChar(char, usize),    // Character, and its position in the policy text
+    UnknownFragment(String),
+    WrongArgCount(String),          // Fragment is given the wrong number of arguments
+    InvalidKey(String),             // Key is not a hex encoded public key
+    InvalidHash(String),            // Hash is not a hex encoded 32 byte hash
+    InvalidLockTime(String),        // Lock time is not a number between 1 and 2^31 - 1
+    InvalidThreshold(usize, usize), // Threshold, and the number of sub-policies
+    MaxDepthExceeded,               // Fragments are nested deeper than MAX_POLICY_DEPTH
+    MixedTimelocks,                 // A satisfaction could need both a height and a time lock of the same kind
+    ResourceLimit                   // Compiled script breaks the script size or opcode limit
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ScriptError::EvalFalse => "Script evaluated without error but finished with a false/empty top stack element",
+            ScriptError::OpReturn => "OP_RETURN was encountered",
+            ScriptError::ScriptSize => "Script is too big",
+            ScriptError::PushSize => "Push value size limit exceeded",
+            ScriptError::OpCount => "Operation limit exceeded",
+            ScriptError::StackSize => "Stack size limit exceeded",
+            ScriptError::SigCount => "Signature count negative or greater than pubkey count",
+            ScriptError::PubKeyCount => "Pubkey count negative or limit exceeded",
+            ScriptError::Verify => "Script failed an OP_VERIFY operation",
+            ScriptError::EqualVerify => "Script failed an OP_EQUALVERIFY operation",
+            ScriptError::CheckMultiSigVerify => "Script failed an OP_CHECKMULTISIGVERIFY operation",
+            ScriptError::CheckSigVerify => "Script failed an OP_CHECKSIGVERIFY operation",
+            ScriptError::NumEqualVerify => "Script failed an OP_NUMEQUALVERIFY operation",
+            ScriptError::BadOpcode => "Opcode missing or not understood",
+            ScriptError::DisabledOpcode => "Attempted to use a disabled opcode",
+            ScriptError::InvalidStackOperation => "Operation not valid with the current stack size",
+            ScriptError::InvalidAltstackOperation => "Operation not valid with the current altstack size",
+            ScriptError::UnbalancedConditional => "Invalid OP_IF construction",
+            ScriptError::NegativeLocktime => "Negative locktime",
+            ScriptError::UnsatisfiedLocktime => "Locktime requirement not satisfied",
+            ScriptError::NumOverflow => "Script number overflow",
+            ScriptError::NumNotMinimal => "Non-minimally encoded script number",
+            ScriptError::TemplateMismatch => "OP_CHECKTEMPLATEVERIFY template hash mismatch",
+            ScriptError::SigHashType => "Signature hash type missing or not understood",
+            ScriptError::SigDer => "Non-canonical signature encoding",
+            ScriptError::MinimalData => "Data push larger than necessary",
+            ScriptError::SigPushOnly => "Only push operators allowed in signatures",
+            ScriptError::SigHighS => "Non-canonical signature: S value is unnecessarily high",
+            ScriptError::SigNullDummy => "Dummy CHECKMULTISIG argument must be zero",
+            ScriptError::PubKeyType => "Public key is not validly encoded",
+            ScriptError::CleanStack => "Stack size must be exactly one after execution",
+            ScriptError::MinimalIf => "OP_IF/NOTIF argument must be minimal",
+            ScriptError::SigNullFail => "Signature must be zero for failed CHECK(MULTI)SIG operation",
+            ScriptError::DiscourageUpgradableNops => "NOPx reserved for soft-fork upgrades",
+            ScriptError::DiscourageUpgradableWitnessProgram => "Witness version reserved for soft-fork upgrades",
+            ScriptError::DiscourageUpgradableTaprootVersion => "Taproot version reserved for soft-fork upgrades",
+            ScriptError::DiscourageOpSuccess => "OP_SUCCESSx reserved for soft-fork upgrades",
+            ScriptError::DiscourageUpgradablePubKeyType => "Public key version reserved for soft-fork upgrades",
+            ScriptError::WitnessProgramWrongLength => "Witness program has incorrect length",
+            ScriptError::WitnessProgramWitnessEmpty => "Witness program was passed an empty witness",
+            ScriptError::WitnessProgramMismatch => "Witness program hash mismatch",
+            ScriptError::WitnessMalleated => "Witness requires empty scriptSig",
+            ScriptError::WitnessMalleatedP2SH => "Witness requires only-redeemscript scriptSig",
+            ScriptError::WitnessUnexpected => "Witness provided for non-witness script",
+            ScriptError::WitnessPubKeyType => "Using non-standard public key encoding in segwit",
+            ScriptError::SchnorrSigSize => "Invalid Schnorr signature size",
+            ScriptError::SchnorrSigHashType => "Invalid Schnorr signature hash type",
+            ScriptError::SchnorrSig => "Invalid Schnorr signature",
+            ScriptError::TaprootWrongControlSize => "Invalid Taproot control block size",
+            ScriptError::TaprootMissingSpentOutputs => "Spent outputs are needed to check Taproot signatures",
+            ScriptError::TapscriptValidationWeight => "Too much signature validation relative to witness weight",
+            ScriptError::TapscriptCheckMultiSig => "OP_CHECKMULTISIG(VERIFY) is not available in tapscript",
+            ScriptError::TapscriptMinimalIf => "OP_IF/NOTIF argument must be minimal in tapscript",
+            ScriptError::TapscriptEmptyPubKey => "Empty public key in tapscript",
+            ScriptError::OpCodeSeparator => "Using OP_CODESEPARATOR in non-witness script",
+            ScriptError::SigFindAndDelete => "Signature is found in scriptCode"
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl Error for ScriptError {}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownOpcode(token) => write!(f, "Unknown opcode: {}", token),
+            AsmError::InvalidPushData(token) => write!(f, "Invalid push data: {}", token),
+            AsmError::MissingPushData(token) => write!(f, "Missing push data after {}", token),
+            AsmError::Script(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyError::UnexpectedEnd => write!(f, "Unexpected end of policy"),
+            PolicyError::UnexpectedChar(c, position) => write!(f, "Unexpected character '{}' at position {}", c, position),
+            PolicyError::UnknownFragment(name) => write!(f, "Unknown policy fragment: {}", name),
+            PolicyError::WrongArgCount(name) => write!(f, "Wrong number of arguments to {}", name),
+            PolicyError::InvalidKey(key) => write!(f, "Invalid public key: {}", key),
+            PolicyError::InvalidHash(hash) => write!(f, "Invalid hash: {}", hash),
+            PolicyError::InvalidLockTime(value) => write!(f, "Invalid lock time: {}", value),
+            PolicyError::InvalidThreshold(k, n) => write!(f, "Invalid threshold {} of {}", k, n),
+            PolicyError::MaxDepthExceeded => write!(f, "Policy is nested too deeply"),
+            PolicyError::MixedTimelocks => write!(f, "Policy combines height and time based lock times"),
+            PolicyError::ResourceLimit => write!(f, "Compiled script exceeds the script size or opcode limit")
+        }
+    }
+}
+
+impl Error for PolicyError {}
+
+impl From<ScriptError> for AsmError {
+    fn from(e: ScriptError) -> AsmError {
+        AsmError::Script(e)
+    }
+}
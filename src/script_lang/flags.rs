@@ -0,0 +1,152 @@
+/*
+ * Script verification flags. Each flag turns on one extra rule in the
+ * interpreter; the bit positions match Bitcoin's SCRIPT_VERIFY_* values.
+ */
+
+use std::ops::{ BitOr, BitOrAssign };
+
+
+/*---- STRUCTS ----*/
+
+/// A set of script verification flags
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ScriptVerifyFlags(u32);
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl ScriptVerifyFlags {
+    pub const NONE: ScriptVerifyFlags = ScriptVerifyFlags(0);
+
+    /// Evaluate P2SH subscripts (BIP16)
+    pub const P2SH: ScriptVerifyFlags = ScriptVerifyFlags(1 << 0);
+
+    /// Passing a public key or signature that isn't validly encoded, or a
+    /// signature with an undefined sighash type, fails the script instead of
+    /// just the check
+    pub const STRICTENC: ScriptVerifyFlags = ScriptVerifyFlags(1 << 1);
+
+    /// Require non-empty signatures to be exactly a signature plus one sighash
+    /// type byte (BIP66)
+    pub const DERSIG: ScriptVerifyFlags = ScriptVerifyFlags(1 << 2);
+
+    /// Require the S value of signatures to be canonical, below the group
+    /// order, so a valid signature can't be altered into another valid one
+    pub const LOW_S: ScriptVerifyFlags = ScriptVerifyFlags(1 << 3);
+
+    /// Require the extra element consumed by CHECKMULTISIG to be empty (BIP147)
+    pub const NULLDUMMY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 4);
+
+    /// Require the signature script to contain only pushes (BIP62 rule 2)
+    pub const SIGPUSHONLY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 5);
+
+    /// Require data pushes and script numbers to use their minimal encoding (BIP62 rules 3 and 4)
+    pub const MINIMALDATA: ScriptVerifyFlags = ScriptVerifyFlags(1 << 6);
+
+    /// Fail on the reserved NOP1 and NOP4-NOP10 opcodes, so they can be safely redefined in
+    /// soft forks. Not used in consensus, only to stop them being relayed.
+    pub const DISCOURAGE_UPGRADABLE_NOPS: ScriptVerifyFlags = ScriptVerifyFlags(1 << 7);
+
+    /// Require exactly one element on the stack after evaluation (BIP62 rule 6).
+    /// Only valid together with P2SH and WITNESS.
+    pub const CLEANSTACK: ScriptVerifyFlags = ScriptVerifyFlags(1 << 8);
+
+    /// Verify OP_CHECKLOCKTIMEVERIFY, instead of treating it as NOP2 (BIP65)
+    pub const CHECKLOCKTIMEVERIFY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 9);
+
+    /// Verify OP_CHECKSEQUENCEVERIFY, instead of treating it as NOP3 (BIP112)
+    pub const CHECKSEQUENCEVERIFY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 10);
+
+    /// Verify witness programs (BIP141)
+    pub const WITNESS: ScriptVerifyFlags = ScriptVerifyFlags(1 << 11);
+
+    /// Fail on witness program versions that aren't defined yet, so they can be
+    /// safely given meaning in soft forks. Not used in consensus.
+    pub const DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM: ScriptVerifyFlags = ScriptVerifyFlags(1 << 12);
+
+    /// Require the argument of OP_IF/NOTIF in witness scripts to be exactly empty or 0x01
+    pub const MINIMALIF: ScriptVerifyFlags = ScriptVerifyFlags(1 << 13);
+
+    /// Require every signature passed to a failing CHECKSIG or CHECKMULTISIG to be empty
+    pub const NULLFAIL: ScriptVerifyFlags = ScriptVerifyFlags(1 << 14);
+
+    /// Require public keys in witness scripts to be validly encoded
+    pub const WITNESS_PUBKEYTYPE: ScriptVerifyFlags = ScriptVerifyFlags(1 << 15);
+
+    /// Fail on OP_CODESEPARATOR, and on signatures found in the script code,
+    /// in non-witness scripts
+    pub const CONST_SCRIPTCODE: ScriptVerifyFlags = ScriptVerifyFlags(1 << 16);
+
+    /// Verify version 1 witness programs: taproot key and script path spends (BIP341 and BIP342)
+    pub const TAPROOT: ScriptVerifyFlags = ScriptVerifyFlags(1 << 17);
+
+    /// Fail on taproot leaf versions that aren't defined yet. Not used in consensus.
+    pub const DISCOURAGE_UPGRADABLE_TAPROOT_VERSION: ScriptVerifyFlags = ScriptVerifyFlags(1 << 18);
+
+    /// Fail on OP_SUCCESSx opcodes in tapscripts, instead of succeeding
+    /// straight away. Not used in consensus.
+    pub const DISCOURAGE_OP_SUCCESS: ScriptVerifyFlags = ScriptVerifyFlags(1 << 19);
+
+    /// Fail on tapscript public keys of an unknown type, instead of treating
+    /// the signature check as passed. Not used in consensus.
+    pub const DISCOURAGE_UPGRADABLE_PUBKEYTYPE: ScriptVerifyFlags = ScriptVerifyFlags(1 << 20);
+
+    /// Verify OP_CHECKTEMPLATEVERIFY against the standard template hash,
+    /// instead of treating it as NOP4 (BIP119)
+    pub const CHECKTEMPLATEVERIFY: ScriptVerifyFlags = ScriptVerifyFlags(1 << 21);
+
+    /// Flag set from raw bits
+    ///
+    /// ### Arguments
+    ///
+    /// * `bits`    - Raw bits of the set
+    pub const fn from_bits(bits: u32) -> ScriptVerifyFlags {
+        ScriptVerifyFlags(bits)
+    }
+
+    /// Raw bits of the set
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Whether every flag in `other` is set
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Flags to check for
+    pub fn contains(&self, other: ScriptVerifyFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Sets the given flags
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Flags to set
+    pub fn insert(&mut self, other: ScriptVerifyFlags) {
+        self.0 |= other.0;
+    }
+
+    /// Clears the given flags
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Flags to clear
+    pub fn remove(&mut self, other: ScriptVerifyFlags) {
+        self.0 &= !other.0;
+    }
+}
+
+impl BitOr for ScriptVerifyFlags {
+    type Output = ScriptVerifyFlags;
+
+    fn bitor(self, other: ScriptVerifyFlags) -> ScriptVerifyFlags {
+        ScriptVerifyFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ScriptVerifyFlags {
+    fn bitor_assign(&mut self, other: ScriptVerifyFlags) {
+        self.0 |= other.0;
+    }
+}
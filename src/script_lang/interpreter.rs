@@ -0,0 +1,1888 @@
+/*
+ * Script interpreter. Scripts run on a simple stack machine: pushes put
+ * data on the stack, and operations pop their operands off it and push their
+ * results back. A spend is valid if running the input's signature script and
+ * then the output's public key script leaves a true value on top of the stack.
+ *
+ * Execution follows Bitcoin's EvalScript closely, including the order in
+ * which limits are checked, since any difference there is a consensus fork.
+ */
+
+use script_lang::error::ScriptError;
+use script_lang::flags::ScriptVerifyFlags;
+use script_lang::checker::SignatureChecker;
+use script_lang::trace::{ ScriptPhase, TraceStep, ScriptTrace };
+use script_lang::sighash::{ SigVersion, ScriptExecutionData, SIGHASH_ALL, SIGHASH_SINGLE, SIGHASH_ANYONECANPAY };
+use script_lang::num::{ ScriptNum, DEFAULT_MAX_NUM_SIZE, LOCKTIME_MAX_NUM_SIZE };
+use script_lang::script::{ Script, StackEntry, OpCodes, Instructions, MAX_SCRIPT_ELEMENT_SIZE, MAX_OPS_PER_SCRIPT, MAX_STACK_SIZE, MAX_PUB_KEYS_PER_MULTISIG, is_op_success };
+use script_lang::taproot::{
+    TAPROOT_LEAF_MASK, TAPROOT_LEAF_TAPSCRIPT, ANNEX_TAG, VALIDATION_WEIGHT_PER_SIGOP_PASSED, VALIDATION_WEIGHT_OFFSET,
+    compute_tapleaf_hash, compute_annex_hash, is_valid_control_size, verify_taproot_commitment
+};
+use primitives::SEQUENCE_LOCKTIME_DISABLE_FLAG;
+use serialize::compact_size_len;
+use cryptography::hash::{ sha1, sha256, ripemd160, hash160, sha256d };
+use cryptography::keys::{ SIGNATURE_SIZE, PUBLIC_KEY_SIZE };
+use cryptography::schnorr::SCHNORR_PUBLIC_KEY_SIZE;
+
+
+/*---- CONSTANTS ----*/
+
+/// Size of a version 0 witness program paying to a public key hash
+const WITNESS_V0_KEYHASH_SIZE: usize = 20;
+
+/// Size of a version 0 witness program paying to a script hash
+const WITNESS_V0_SCRIPTHASH_SIZE: usize = 32;
+
+/// Size of a version 1 witness program, an x-only output key
+const WITNESS_V1_TAPROOT_SIZE: usize = 32;
+
+/// Order of the ed25519 group, little-endian. Canonical signatures have an S
+/// value below this.
+const GROUP_ORDER: [u8; 32] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10
+];
+
+
+/*---- FUNCTIONS ----*/
+
+/// Runs a script against the given stack, leaving its results on the stack
+///
+/// ### Arguments
+///
+/// * `stack`       - Stack to run the script against
+/// * `script`      - Script to run
+/// * `flags`       - Verification flags to run under
+/// * `checker`     - Checker for signatures against the spending transaction
+/// * `sig_version` - Signature version the script runs under
+pub fn eval_script(
+    stack: &mut Vec<Vec<u8>>,
+    script: &Script,
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    sig_version: SigVersion
+) -> Result<(), ScriptError>
+{
+    let mut exec_data = ScriptExecutionData::new();
+
+    run_script(stack, script, flags, checker, sig_version, &mut exec_data, ScriptPhase::ScriptPubKey, None)
+}
+
+/// Runs a script as `eval_script` does, recording a trace step before each
+/// instruction if a trace is given. If an instruction fails, its step is
+/// marked as the failing one.
+#[allow(clippy::too_many_arguments)]
+fn run_script(
+    stack: &mut Vec<Vec<u8>>,
+    script: &Script,
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    sig_version: SigVersion,
+    exec_data: &mut ScriptExecutionData,
+    phase: ScriptPhase,
+    mut trace: Option<&mut ScriptTrace>
+) -> Result<(), ScriptError>
+{
+    let mut in_step = false;
+    let result = run_steps(stack, script, flags, checker, sig_version, exec_data, phase, trace.as_deref_mut(), &mut in_step);
+
+    // Errors from outside an instruction, like an unbalanced conditional
+    // at the end of the script, have no step to blame
+    if let Some(trace) = trace {
+        if result.is_err() && in_step {
+            trace.failed_step = trace.steps.len().checked_sub(1);
+        }
+    }
+
+    result
+}
+
+/// Runs the instructions of a script for `run_script`, keeping `in_step`
+/// set while an instruction is being run
+#[allow(clippy::too_many_arguments)]
+fn run_steps(
+    stack: &mut Vec<Vec<u8>>,
+    script: &Script,
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    sig_version: SigVersion,
+    exec_data: &mut ScriptExecutionData,
+    phase: ScriptPhase,
+    mut trace: Option<&mut ScriptTrace>,
+    in_step: &mut bool
+) -> Result<(), ScriptError>
+{
+    let require_minimal = flags.contains(ScriptVerifyFlags::MINIMALDATA);
+    let is_tapscript = sig_version == SigVersion::Tapscript;
+
+    let mut alt_stack: Vec<Vec<u8>> = Vec::new();
+    let mut exec_stack: Vec<bool> = Vec::new();
+    let mut op_count = 0;
+    let mut code_separator = 0;
+
+    for (pc, entry) in script.get_entries().iter().enumerate() {
+        let executing = !exec_stack.contains(&false);
+        let op_code = entry.get_op_code();
+
+        *in_step = true;
+
+        if let Some(trace) = trace.as_mut() {
+            trace.steps.push(TraceStep {
+                phase,
+                pc,
+                entry: entry.clone(),
+                executing,
+                stack: stack.clone(),
+                alt_stack: alt_stack.clone(),
+                exec_stack: exec_stack.clone()
+            });
+        }
+
+        if let StackEntry::Push(_, data) = entry {
+            if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                return Err(ScriptError::PushSize);
+            }
+        }
+
+        // Note how OP_RESERVED does not count towards the opcode limit.
+        // Tapscripts have no opcode limit, only a validation weight budget.
+        if op_code > OpCodes::OP_16 as u8 && !is_tapscript {
+            op_count += 1;
+
+            if op_count > MAX_OPS_PER_SCRIPT {
+                return Err(ScriptError::OpCount);
+            }
+        }
+
+        // Disabled opcodes fail even in an unexecuted branch
+        if is_disabled(op_code) {
+            return Err(ScriptError::DisabledOpcode);
+        }
+
+        // With CONST_SCRIPTCODE, OP_CODESEPARATOR in non-segwit script is rejected even in an unexecuted branch
+        if op_code == OpCodes::OP_CODESEPARATOR as u8 && sig_version == SigVersion::Base &&
+           flags.contains(ScriptVerifyFlags::CONST_SCRIPTCODE)
+        {
+            return Err(ScriptError::OpCodeSeparator);
+        }
+
+        if executing && op_code <= OpCodes::OP_PUSHDATA4 as u8 {
+            let data = match entry {
+                StackEntry::Push(_, data) => data.clone(),
+                _ => Vec::new()
+            };
+
+            if require_minimal && !is_minimal_push(op_code, &data) {
+                return Err(ScriptError::MinimalData);
+            }
+
+            stack.push(data);
+        } else if executing || (op_code >= OpCodes::OP_IF as u8 && op_code <= OpCodes::OP_ENDIF as u8) {
+            let op = match entry {
+                StackEntry::Op(op) => *op,
+                _ => return Err(ScriptError::BadOpcode)
+            };
+
+            match op {
+
+                // Push value
+                OpCodes::OP_1NEGATE | OpCodes::OP_1 | OpCodes::OP_2 | OpCodes::OP_3 | OpCodes::OP_4 |
+                OpCodes::OP_5 | OpCodes::OP_6 | OpCodes::OP_7 | OpCodes::OP_8 | OpCodes::OP_9 |
+                OpCodes::OP_10 | OpCodes::OP_11 | OpCodes::OP_12 | OpCodes::OP_13 | OpCodes::OP_14 |
+                OpCodes::OP_15 | OpCodes::OP_16 => {
+                    let value = op as i64 - (OpCodes::OP_1 as i64 - 1);
+                    stack.push(ScriptNum::new(value).to_bytes());
+                },
+
+                // Control
+                OpCodes::OP_NOP => {},
+
+                OpCodes::OP_CHECKLOCKTIMEVERIFY if flags.contains(ScriptVerifyFlags::CHECKLOCKTIMEVERIFY) => {
+                    // Lock times are unsigned 32 bit values, so they get an
+                    // extra byte. The operand is left on the stack, as the
+                    // opcode has to keep behaving like the NOP it replaced.
+                    let lock_time = ScriptNum::from_bytes(top(stack, 1)?, require_minimal, LOCKTIME_MAX_NUM_SIZE)?;
+
+                    // A negative lock time could never be satisfied, and
+                    // is most likely a mistake, so fail outright
+                    if lock_time.value() < 0 {
+                        return Err(ScriptError::NegativeLocktime);
+                    }
+
+                    if !checker.check_lock_time(lock_time) {
+                        return Err(ScriptError::UnsatisfiedLocktime);
+                    }
+                },
+
+                OpCodes::OP_CHECKSEQUENCEVERIFY if flags.contains(ScriptVerifyFlags::CHECKSEQUENCEVERIFY) => {
+                    let sequence = ScriptNum::from_bytes(top(stack, 1)?, require_minimal, LOCKTIME_MAX_NUM_SIZE)?;
+
+                    if sequence.value() < 0 {
+                        return Err(ScriptError::NegativeLocktime);
+                    }
+
+                    // With the disable flag set the opcode stays a NOP, so
+                    // those operands are free for future soft forks
+                    if sequence.value() & SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 == 0 && !checker.check_sequence(sequence) {
+                        return Err(ScriptError::UnsatisfiedLocktime);
+                    }
+                },
+
+                OpCodes::OP_CHECKTEMPLATEVERIFY if flags.contains(ScriptVerifyFlags::CHECKTEMPLATEVERIFY) => {
+                    // Only 32 byte arguments are template hashes. Other sizes
+                    // are left for future soft forks, and keep the opcode a NOP.
+                    let hash = top(stack, 1)?;
+
+                    if hash.len() == 32 {
+                        if !checker.check_template_hash(hash) {
+                            return Err(ScriptError::TemplateMismatch);
+                        }
+                    } else if flags.contains(ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS) {
+                        return Err(ScriptError::DiscourageUpgradableNops);
+                    }
+                },
+
+                OpCodes::OP_NOP1 | OpCodes::OP_CHECKLOCKTIMEVERIFY | OpCodes::OP_CHECKSEQUENCEVERIFY |
+                OpCodes::OP_CHECKTEMPLATEVERIFY | OpCodes::OP_NOP5 | OpCodes::OP_NOP6 | OpCodes::OP_NOP7 |
+                OpCodes::OP_NOP8 | OpCodes::OP_NOP9 | OpCodes::OP_NOP10 => {
+                    if flags.contains(ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS) {
+                        return Err(ScriptError::DiscourageUpgradableNops);
+                    }
+                },
+
+                OpCodes::OP_IF | OpCodes::OP_NOTIF => {
+                    let mut value = false;
+
+                    if executing {
+                        if stack.is_empty() {
+                            return Err(ScriptError::UnbalancedConditional);
+                        }
+
+                        let condition = pop(stack)?;
+
+                        // Witness scripts only take exactly empty or 0x01, so the
+                        // branch taken can't be changed by malleating the argument.
+                        // Tapscripts always require this.
+                        let is_minimal = condition.len() < 2 && (condition.is_empty() || condition[0] == 1);
+
+                        if is_tapscript && !is_minimal {
+                            return Err(ScriptError::TapscriptMinimalIf);
+                        }
+
+                        if sig_version == SigVersion::WitnessV0 && flags.contains(ScriptVerifyFlags::MINIMALIF) && !is_minimal {
+                            return Err(ScriptError::MinimalIf);
+                        }
+
+                        value = cast_to_bool(&condition);
+
+                        if op == OpCodes::OP_NOTIF {
+                            value = !value;
+                        }
+                    }
+
+                    exec_stack.push(value);
+                },
+
+                OpCodes::OP_ELSE => {
+                    match exec_stack.last_mut() {
+                        Some(value) => *value = !*value,
+                        None => return Err(ScriptError::UnbalancedConditional)
+                    }
+                },
+
+                OpCodes::OP_ENDIF => {
+                    if exec_stack.pop().is_none() {
+                        return Err(ScriptError::UnbalancedConditional);
+                    }
+                },
+
+                OpCodes::OP_VERIFY => {
+                    if !cast_to_bool(top(stack, 1)?) {
+                        return Err(ScriptError::Verify);
+                    }
+
+                    stack.pop();
+                },
+
+                OpCodes::OP_RETURN => return Err(ScriptError::OpReturn),
+
+                // Stack ops
+                OpCodes::OP_TOALTSTACK => {
+                    let value = pop(stack)?;
+                    alt_stack.push(value);
+                },
+
+                OpCodes::OP_FROMALTSTACK => {
+                    match alt_stack.pop() {
+                        Some(value) => stack.push(value),
+                        None => return Err(ScriptError::InvalidAltstackOperation)
+                    }
+                },
+
+                OpCodes::OP_2DROP => {
+                    require(stack, 2)?;
+                    stack.truncate(stack.len() - 2);
+                },
+
+                OpCodes::OP_2DUP => {
+                    require(stack, 2)?;
+                    let start = stack.len() - 2;
+                    let values = stack[start..].to_vec();
+                    stack.extend(values);
+                },
+
+                OpCodes::OP_3DUP => {
+                    require(stack, 3)?;
+                    let start = stack.len() - 3;
+                    let values = stack[start..].to_vec();
+                    stack.extend(values);
+                },
+
+                OpCodes::OP_2OVER => {
+                    require(stack, 4)?;
+                    let start = stack.len() - 4;
+                    let values = stack[start..start + 2].to_vec();
+                    stack.extend(values);
+                },
+
+                OpCodes::OP_2ROT => {
+                    require(stack, 6)?;
+                    let start = stack.len() - 6;
+                    let values: Vec<Vec<u8>> = stack.drain(start..start + 2).collect();
+                    stack.extend(values);
+                },
+
+                OpCodes::OP_2SWAP => {
+                    require(stack, 4)?;
+                    let len = stack.len();
+                    stack.swap(len - 4, len - 2);
+                    stack.swap(len - 3, len - 1);
+                },
+
+                OpCodes::OP_IFDUP => {
+                    let value = top(stack, 1)?.clone();
+
+                    if cast_to_bool(&value) {
+                        stack.push(value);
+                    }
+                },
+
+                OpCodes::OP_DEPTH => {
+                    let depth = ScriptNum::new(stack.len() as i64).to_bytes();
+                    stack.push(depth);
+                },
+
+                OpCodes::OP_DROP => {
+                    pop(stack)?;
+                },
+
+                OpCodes::OP_DUP => {
+                    let value = top(stack, 1)?.clone();
+                    stack.push(value);
+                },
+
+                OpCodes::OP_NIP => {
+                    require(stack, 2)?;
+                    let len = stack.len();
+                    stack.remove(len - 2);
+                },
+
+                OpCodes::OP_OVER => {
+                    let value = top(stack, 2)?.clone();
+                    stack.push(value);
+                },
+
+                OpCodes::OP_PICK | OpCodes::OP_ROLL => {
+                    let n = ScriptNum::from_bytes(top(stack, 1)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.get_int();
+                    stack.pop();
+
+                    if n < 0 || n as usize >= stack.len() {
+                        return Err(ScriptError::InvalidStackOperation);
+                    }
+
+                    let index = stack.len() - 1 - n as usize;
+                    let value = if op == OpCodes::OP_ROLL { stack.remove(index) } else { stack[index].clone() };
+                    stack.push(value);
+                },
+
+                OpCodes::OP_ROT => {
+                    require(stack, 3)?;
+                    let len = stack.len();
+                    let value = stack.remove(len - 3);
+                    stack.push(value);
+                },
+
+                OpCodes::OP_SWAP => {
+                    require(stack, 2)?;
+                    let len = stack.len();
+                    stack.swap(len - 2, len - 1);
+                },
+
+                OpCodes::OP_TUCK => {
+                    require(stack, 2)?;
+                    let len = stack.len();
+                    let value = stack[len - 1].clone();
+                    stack.insert(len - 2, value);
+                },
+
+                OpCodes::OP_SIZE => {
+                    let size = ScriptNum::new(top(stack, 1)?.len() as i64).to_bytes();
+                    stack.push(size);
+                },
+
+                // Bitwise logic
+                OpCodes::OP_EQUAL | OpCodes::OP_EQUALVERIFY => {
+                    require(stack, 2)?;
+                    let right = pop(stack)?;
+                    let left = pop(stack)?;
+                    let equal = left == right;
+
+                    if op == OpCodes::OP_EQUALVERIFY {
+                        if !equal {
+                            return Err(ScriptError::EqualVerify);
+                        }
+                    } else {
+                        stack.push(encode_bool(equal));
+                    }
+                },
+
+                // Numeric
+                OpCodes::OP_1ADD | OpCodes::OP_1SUB | OpCodes::OP_NEGATE | OpCodes::OP_ABS |
+                OpCodes::OP_NOT | OpCodes::OP_0NOTEQUAL => {
+                    let n = ScriptNum::from_bytes(top(stack, 1)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.value();
+                    stack.pop();
+
+                    let result = match op {
+                        OpCodes::OP_1ADD => n + 1,
+                        OpCodes::OP_1SUB => n - 1,
+                        OpCodes::OP_NEGATE => -n,
+                        OpCodes::OP_ABS => n.abs(),
+                        OpCodes::OP_NOT => (n == 0) as i64,
+                        _ => (n != 0) as i64
+                    };
+
+                    stack.push(ScriptNum::new(result).to_bytes());
+                },
+
+                OpCodes::OP_ADD | OpCodes::OP_SUB | OpCodes::OP_BOOLAND | OpCodes::OP_BOOLOR |
+                OpCodes::OP_NUMEQUAL | OpCodes::OP_NUMEQUALVERIFY | OpCodes::OP_NUMNOTEQUAL |
+                OpCodes::OP_LESSTHAN | OpCodes::OP_GREATERTHAN | OpCodes::OP_LESSTHANOREQUAL |
+                OpCodes::OP_GREATERTHANOREQUAL | OpCodes::OP_MIN | OpCodes::OP_MAX => {
+                    require(stack, 2)?;
+                    let a = ScriptNum::from_bytes(top(stack, 2)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.value();
+                    let b = ScriptNum::from_bytes(top(stack, 1)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.value();
+                    stack.truncate(stack.len() - 2);
+
+                    let result = match op {
+                        OpCodes::OP_ADD => a + b,
+                        OpCodes::OP_SUB => a - b,
+                        OpCodes::OP_BOOLAND => (a != 0 && b != 0) as i64,
+                        OpCodes::OP_BOOLOR => (a != 0 || b != 0) as i64,
+                        OpCodes::OP_NUMEQUAL | OpCodes::OP_NUMEQUALVERIFY => (a == b) as i64,
+                        OpCodes::OP_NUMNOTEQUAL => (a != b) as i64,
+                        OpCodes::OP_LESSTHAN => (a < b) as i64,
+                        OpCodes::OP_GREATERTHAN => (a > b) as i64,
+                        OpCodes::OP_LESSTHANOREQUAL => (a <= b) as i64,
+                        OpCodes::OP_GREATERTHANOREQUAL => (a >= b) as i64,
+                        OpCodes::OP_MIN => a.min(b),
+                        _ => a.max(b)
+                    };
+
+                    if op == OpCodes::OP_NUMEQUALVERIFY {
+                        if result == 0 {
+                            return Err(ScriptError::NumEqualVerify);
+                        }
+                    } else {
+                        stack.push(ScriptNum::new(result).to_bytes());
+                    }
+                },
+
+                OpCodes::OP_WITHIN => {
+                    require(stack, 3)?;
+                    let x = ScriptNum::from_bytes(top(stack, 3)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.value();
+                    let min = ScriptNum::from_bytes(top(stack, 2)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.value();
+                    let max = ScriptNum::from_bytes(top(stack, 1)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.value();
+                    stack.truncate(stack.len() - 3);
+
+                    stack.push(encode_bool(min <= x && x < max));
+                },
+
+                // Crypto
+                OpCodes::OP_RIPEMD160 | OpCodes::OP_SHA1 | OpCodes::OP_SHA256 |
+                OpCodes::OP_HASH160 | OpCodes::OP_HASH256 => {
+                    let value = pop(stack)?;
+
+                    let hash = match op {
+                        OpCodes::OP_RIPEMD160 => ripemd160(&value),
+                        OpCodes::OP_SHA1 => sha1(&value),
+                        OpCodes::OP_SHA256 => sha256(&value),
+                        OpCodes::OP_HASH160 => hash160(&value),
+                        _ => sha256d(&value)
+                    };
+
+                    stack.push(hash);
+                },
+
+                OpCodes::OP_CODESEPARATOR => {
+                    // Signatures only commit to the script after the last executed
+                    // separator, or in tapscripts to the separator's position
+                    code_separator = pc + 1;
+                    exec_data.code_separator_pos = pc as u32;
+                },
+
+                OpCodes::OP_CHECKSIG | OpCodes::OP_CHECKSIGVERIFY if is_tapscript => {
+                    require(stack, 2)?;
+                    let success = check_tapscript_sig(top(stack, 2)?, top(stack, 1)?, flags, checker, exec_data)?;
+                    stack.truncate(stack.len() - 2);
+
+                    if op == OpCodes::OP_CHECKSIGVERIFY {
+                        if !success {
+                            return Err(ScriptError::CheckSigVerify);
+                        }
+                    } else {
+                        stack.push(encode_bool(success));
+                    }
+                },
+
+                OpCodes::OP_CHECKSIG | OpCodes::OP_CHECKSIGVERIFY => {
+                    require(stack, 2)?;
+                    let signature = top(stack, 2)?.clone();
+                    let public_key = top(stack, 1)?.clone();
+
+                    let mut script_code = script.get_subscript(code_separator);
+
+                    // A signature can't sign itself, so drop it from the script
+                    // code. Witness signature hashing never included it.
+                    if sig_version == SigVersion::Base {
+                        let found = script_code.find_and_delete(&signature);
+
+                        if found > 0 && flags.contains(ScriptVerifyFlags::CONST_SCRIPTCODE) {
+                            return Err(ScriptError::SigFindAndDelete);
+                        }
+                    }
+
+                    check_signature_encoding(&signature, flags)?;
+                    check_public_key_encoding(&public_key, flags, sig_version)?;
+
+                    let success = checker.check_sig(&signature, &public_key, &script_code, sig_version);
+
+                    if !success && flags.contains(ScriptVerifyFlags::NULLFAIL) && !signature.is_empty() {
+                        return Err(ScriptError::SigNullFail);
+                    }
+                    stack.truncate(stack.len() - 2);
+
+                    if op == OpCodes::OP_CHECKSIGVERIFY {
+                        if !success {
+                            return Err(ScriptError::CheckSigVerify);
+                        }
+                    } else {
+                        stack.push(encode_bool(success));
+                    }
+                },
+
+                OpCodes::OP_CHECKSIGADD if is_tapscript => {
+                    // Replaces CHECKMULTISIG in tapscripts, as it can't be
+                    // batch verified: each signature is checked against one
+                    // key, with the count of valid signatures kept on the stack
+                    require(stack, 3)?;
+                    let count = ScriptNum::from_bytes(top(stack, 2)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?;
+                    let success = check_tapscript_sig(top(stack, 3)?, top(stack, 1)?, flags, checker, exec_data)?;
+                    stack.truncate(stack.len() - 3);
+
+                    stack.push(ScriptNum::new(count.value() + success as i64).to_bytes());
+                },
+
+                OpCodes::OP_CHECKMULTISIG | OpCodes::OP_CHECKMULTISIGVERIFY if is_tapscript => {
+                    return Err(ScriptError::TapscriptCheckMultiSig);
+                },
+
+                OpCodes::OP_CHECKMULTISIG | OpCodes::OP_CHECKMULTISIGVERIFY => {
+                    // Arguments, from the top: key count, keys, signature count,
+                    // signatures, and one extra element. Positions count down
+                    // from the top of the stack, with the top at 1.
+                    let mut i = 1;
+                    let mut keys_count = ScriptNum::from_bytes(top(stack, i)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.get_int();
+
+                    if keys_count < 0 || keys_count as usize > MAX_PUB_KEYS_PER_MULTISIG {
+                        return Err(ScriptError::PubKeyCount);
+                    }
+
+                    // Every key counts towards the operation limit
+                    let keys_count_total = keys_count as usize;
+                    op_count += keys_count_total;
+
+                    if op_count > MAX_OPS_PER_SCRIPT {
+                        return Err(ScriptError::OpCount);
+                    }
+
+                    i += 1;
+                    let mut key_index = i;
+                    i += keys_count as usize;
+
+                    let mut sigs_count = ScriptNum::from_bytes(top(stack, i)?, require_minimal, DEFAULT_MAX_NUM_SIZE)?.get_int();
+
+                    if sigs_count < 0 || sigs_count > keys_count {
+                        return Err(ScriptError::SigCount);
+                    }
+
+                    i += 1;
+                    let mut sig_index = i;
+                    i += sigs_count as usize;
+                    require(stack, i)?;
+
+                    let mut script_code = script.get_subscript(code_separator);
+
+                    if sig_version == SigVersion::Base {
+                        for k in 0..sigs_count as usize {
+                            let signature = top(stack, sig_index + k)?.clone();
+                            let found = script_code.find_and_delete(&signature);
+
+                            if found > 0 && flags.contains(ScriptVerifyFlags::CONST_SCRIPTCODE) {
+                                return Err(ScriptError::SigFindAndDelete);
+                            }
+                        }
+                    }
+
+                    // Signatures must be in the same order as their keys, so
+                    // each key is tried once against the next unmatched signature
+                    let mut success = true;
+
+                    while success && sigs_count > 0 {
+                        let signature = top(stack, sig_index)?;
+                        let public_key = top(stack, key_index)?;
+
+                        // Note how this makes the exact order of key/signature evaluation
+                        // distinguishable by CHECKMULTISIG NOT if the STRICTENC flag is set
+                        check_signature_encoding(signature, flags)?;
+                        check_public_key_encoding(public_key, flags, sig_version)?;
+
+                        if checker.check_sig(signature, public_key, &script_code, sig_version) {
+                            sig_index += 1;
+                            sigs_count -= 1;
+                        }
+
+                        key_index += 1;
+                        keys_count -= 1;
+
+                        // Too few keys left for the remaining signatures
+                        if sigs_count > keys_count {
+                            success = false;
+                        }
+                    }
+
+                    // If the operation failed, every signature must be empty under
+                    // NULLFAIL. Signatures sit below the keys and the two counts.
+                    if !success && flags.contains(ScriptVerifyFlags::NULLFAIL) {
+                        let first_signature = stack.len() - (i - 1);
+                        let last_signature = stack.len() - (keys_count_total + 2);
+
+                        if stack[first_signature..last_signature].iter().any(|signature| !signature.is_empty()) {
+                            return Err(ScriptError::SigNullFail);
+                        }
+                    }
+
+                    stack.truncate(stack.len() - (i - 1));
+
+                    // A bug in the original implementation consumes one extra
+                    // element, which now has to stay for compatibility
+                    let dummy = pop(stack)?;
+
+                    if flags.contains(ScriptVerifyFlags::NULLDUMMY) && !dummy.is_empty() {
+                        return Err(ScriptError::SigNullDummy);
+                    }
+
+                    if op == OpCodes::OP_CHECKMULTISIGVERIFY {
+                        if !success {
+                            return Err(ScriptError::CheckMultiSigVerify);
+                        }
+                    } else {
+                        stack.push(encode_bool(success));
+                    }
+                },
+
+                _ => return Err(ScriptError::BadOpcode)
+            }
+        }
+
+        // Size limits
+        if stack.len() + alt_stack.len() > MAX_STACK_SIZE {
+            return Err(ScriptError::StackSize);
+        }
+
+        *in_step = false;
+    }
+
+    if !exec_stack.is_empty() {
+        return Err(ScriptError::UnbalancedConditional);
+    }
+
+    Ok(())
+}
+
+/// Verifies a spend by running the input's signature script followed by the
+/// public key script of the output it spends. The spend is valid if this
+/// leaves a true value on top of the stack. If the output is a witness
+/// program, the input's witness must then satisfy it too.
+///
+/// ### Arguments
+///
+/// * `script_sig`      - Raw signature script from the spending input
+/// * `script_pubkey`   - Raw public key script from the output being spent
+/// * `witness`         - Witness stack from the spending input
+/// * `flags`           - Verification flags to run under
+/// * `checker`         - Checker for signatures against the spending transaction
+pub fn verify_script(
+    script_sig: &[u8],
+    script_pubkey: &[u8],
+    witness: &[Vec<u8>],
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker
+) -> Result<(), ScriptError>
+{
+    verify_spend(script_sig, script_pubkey, witness, flags, checker, None)
+}
+
+/// Verifies a spend as `verify_script` does, recording every step the
+/// interpreter takes, so a failing spend can be stepped through afterwards
+///
+/// ### Arguments
+///
+/// * `script_sig`      - Raw signature script from the spending input
+/// * `script_pubkey`   - Raw public key script from the output being spent
+/// * `witness`         - Witness stack from the spending input
+/// * `flags`           - Verification flags to run under
+/// * `checker`         - Checker for signatures against the spending transaction
+pub fn trace_verify_script(
+    script_sig: &[u8],
+    script_pubkey: &[u8],
+    witness: &[Vec<u8>],
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker
+) -> ScriptTrace
+{
+    let mut trace = ScriptTrace::new();
+    trace.result = verify_spend(script_sig, script_pubkey, witness, flags, checker, Some(&mut trace));
+
+    trace
+}
+
+/// Verifies a spend, recording trace steps if a trace is given
+fn verify_spend(
+    script_sig: &[u8],
+    script_pubkey: &[u8],
+    witness: &[Vec<u8>],
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    mut trace: Option<&mut ScriptTrace>
+) -> Result<(), ScriptError>
+{
+    let script_sig = Script::from_bytes(script_sig)?;
+    let script_pubkey = Script::from_bytes(script_pubkey)?;
+    let mut had_witness = false;
+
+    if flags.contains(ScriptVerifyFlags::SIGPUSHONLY) && !script_sig.is_push_only() {
+        return Err(ScriptError::SigPushOnly);
+    }
+
+    let mut stack = Vec::new();
+    let mut exec_data = ScriptExecutionData::new();
+    run_script(&mut stack, &script_sig, flags, checker, SigVersion::Base, &mut exec_data, ScriptPhase::ScriptSig, trace.as_deref_mut())?;
+
+    // Kept for P2SH, which runs the redeem script against the same inputs
+    let stack_copy = match flags.contains(ScriptVerifyFlags::P2SH) {
+        true => stack.clone(),
+        false => Vec::new()
+    };
+
+    run_script(&mut stack, &script_pubkey, flags, checker, SigVersion::Base, &mut exec_data, ScriptPhase::ScriptPubKey, trace.as_deref_mut())?;
+
+    match stack.last() {
+        Some(value) if cast_to_bool(value) => {},
+        _ => return Err(ScriptError::EvalFalse)
+    }
+
+    // Bare witness programs
+    if flags.contains(ScriptVerifyFlags::WITNESS) {
+        if let Some((version, program)) = script_pubkey.get_witness_program() {
+            had_witness = true;
+
+            // The scriptSig must be _exactly_ empty, otherwise we reintroduce malleability
+            if !script_sig.get_entries().is_empty() {
+                return Err(ScriptError::WitnessMalleated);
+            }
+
+            verify_witness_program(witness, version, program, flags, checker, false, trace.as_deref_mut())?;
+
+            // The witness program ran on its own stack, so the clean stack
+            // check below doesn't apply
+            stack.truncate(1);
+        }
+    }
+
+    // Additional validation for spend-to-script-hash transactions (BIP16)
+    if flags.contains(ScriptVerifyFlags::P2SH) && script_pubkey.is_p2sh() {
+        // The scriptSig must be literals-only, or validation fails
+        if !script_sig.is_push_only() {
+            return Err(ScriptError::SigPushOnly);
+        }
+
+        stack = stack_copy;
+
+        // The stack can't be empty here, or the hash check in the
+        // scriptPubKey would already have failed above
+        let redeem_bytes = pop(&mut stack)?;
+        let redeem_script = Script::from_bytes(&redeem_bytes)?;
+
+        run_script(&mut stack, &redeem_script, flags, checker, SigVersion::Base, &mut exec_data, ScriptPhase::RedeemScript, trace.as_deref_mut())?;
+
+        match stack.last() {
+            Some(value) if cast_to_bool(value) => {},
+            _ => return Err(ScriptError::EvalFalse)
+        }
+
+        // P2SH witness program
+        if flags.contains(ScriptVerifyFlags::WITNESS) {
+            if let Some((version, program)) = redeem_script.get_witness_program() {
+                had_witness = true;
+
+                // The scriptSig must be _exactly_ a single push of the redeem
+                // script, otherwise we reintroduce malleability
+                let mut expected_sig = Script::new();
+                expected_sig.push_data(&redeem_bytes);
+
+                if script_sig != expected_sig {
+                    return Err(ScriptError::WitnessMalleatedP2SH);
+                }
+
+                verify_witness_program(witness, version, program, flags, checker, true, trace)?;
+                stack.truncate(1);
+            }
+        }
+    }
+
+    // Nothing but the result may be left over, since anything else could be
+    // changed by a third party without invalidating the spend
+    if flags.contains(ScriptVerifyFlags::CLEANSTACK) && stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+
+    // A witness given to an input that doesn't spend a witness program could
+    // be stripped or changed by anyone, so it is not allowed at all
+    if flags.contains(ScriptVerifyFlags::WITNESS) && !had_witness && !witness.is_empty() {
+        return Err(ScriptError::WitnessUnexpected);
+    }
+
+    Ok(())
+}
+
+/// Verifies a witness program against the witness that spends it. Version 0
+/// programs are either a 20 byte key hash, spent with a signature and public
+/// key, or a 32 byte script hash, spent with the script's inputs followed by
+/// the script itself. Version 1 programs are taproot output keys, spent with
+/// a signature for the key or with one of the scripts it commits to. Unknown
+/// versions are left for future soft forks and always pass, unless discouraged.
+///
+/// ### Arguments
+///
+/// * `witness` - Witness stack from the spending input
+/// * `version` - Witness version
+/// * `program` - Witness program
+/// * `flags`   - Verification flags to run under
+/// * `checker` - Checker for signatures against the spending transaction
+/// * `is_p2sh` - Whether the program is wrapped in P2SH
+/// * `trace`   - Trace to record steps in, if tracing
+fn verify_witness_program(
+    witness: &[Vec<u8>],
+    version: u8,
+    program: &[u8],
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    is_p2sh: bool,
+    trace: Option<&mut ScriptTrace>
+) -> Result<(), ScriptError>
+{
+    let mut exec_data = ScriptExecutionData::new();
+
+    if version == 1 && program.len() == WITNESS_V1_TAPROOT_SIZE && !is_p2sh {
+        if !flags.contains(ScriptVerifyFlags::TAPROOT) {
+            return Ok(());
+        }
+
+        return verify_taproot_program(witness, program, flags, checker, &mut exec_data, trace);
+    }
+
+    if version != 0 {
+        if flags.contains(ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM) {
+            return Err(ScriptError::DiscourageUpgradableWitnessProgram);
+        }
+
+        return Ok(());
+    }
+
+    let (stack, script) = match program.len() {
+        WITNESS_V0_SCRIPTHASH_SIZE => {
+            let (witness_script, inputs) = match witness.split_last() {
+                Some(split) => split,
+                None => return Err(ScriptError::WitnessProgramWitnessEmpty)
+            };
+
+            if sha256(witness_script) != program {
+                return Err(ScriptError::WitnessProgramMismatch);
+            }
+
+            (inputs.to_vec(), Script::from_bytes(witness_script)?)
+        },
+        WITNESS_V0_KEYHASH_SIZE => {
+            if witness.len() != 2 {
+                return Err(ScriptError::WitnessProgramMismatch);
+            }
+
+            let mut script = Script::new();
+            script.push_op(OpCodes::OP_DUP)
+                  .push_op(OpCodes::OP_HASH160)
+                  .push_data(program)
+                  .push_op(OpCodes::OP_EQUALVERIFY)
+                  .push_op(OpCodes::OP_CHECKSIG);
+
+            (witness.to_vec(), script)
+        },
+        _ => return Err(ScriptError::WitnessProgramWrongLength)
+    };
+
+    execute_witness_script(stack, &script, flags, checker, SigVersion::WitnessV0, &mut exec_data, trace)
+}
+
+/// Verifies a version 1 witness program (BIP341). A witness with a single
+/// element is a key path spend, a signature for the output key. Otherwise
+/// the last two elements are a script and a control block proving the
+/// output key commits to it, and the rest are the script's inputs. Either
+/// way, a last element starting with ANNEX_TAG is the annex, which is
+/// ignored apart from being signed.
+///
+/// ### Arguments
+///
+/// * `witness`     - Witness stack from the spending input
+/// * `program`     - Witness program, the x-only output key
+/// * `flags`       - Verification flags to run under
+/// * `checker`     - Checker for signatures against the spending transaction
+/// * `exec_data`   - Execution data for the spend
+/// * `trace`       - Trace to record steps in, if tracing
+fn verify_taproot_program(
+    witness: &[Vec<u8>],
+    program: &[u8],
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    exec_data: &mut ScriptExecutionData,
+    trace: Option<&mut ScriptTrace>
+) -> Result<(), ScriptError>
+{
+    let mut stack = witness.to_vec();
+
+    if stack.is_empty() {
+        return Err(ScriptError::WitnessProgramWitnessEmpty);
+    }
+
+    if stack.len() >= 2 && stack[stack.len() - 1].first() == Some(&ANNEX_TAG) {
+        let annex = pop(&mut stack)?;
+        exec_data.annex_hash = Some(compute_annex_hash(&annex));
+    }
+
+    // Key path spend
+    if stack.len() == 1 {
+        return checker.check_schnorr_sig(&stack[0], program, SigVersion::Taproot, exec_data);
+    }
+
+    // Script path spend
+    let control = pop(&mut stack)?;
+    let script_bytes = pop(&mut stack)?;
+
+    if !is_valid_control_size(&control) {
+        return Err(ScriptError::TaprootWrongControlSize);
+    }
+
+    let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+    let tapleaf_hash = compute_tapleaf_hash(leaf_version, &script_bytes);
+
+    if !verify_taproot_commitment(&control, program, &tapleaf_hash) {
+        return Err(ScriptError::WitnessProgramMismatch);
+    }
+
+    exec_data.tapleaf_hash = Some(tapleaf_hash);
+
+    // Unknown leaf versions are left for future soft forks
+    if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+        if flags.contains(ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION) {
+            return Err(ScriptError::DiscourageUpgradableTaprootVersion);
+        }
+
+        return Ok(());
+    }
+
+    // Any OP_SUCCESSx makes the script succeed outright, even if it comes
+    // after something that would fail, as long as the script parses up to it
+    for instruction in Instructions::new(&script_bytes) {
+        let (op_code, _) = instruction?;
+
+        if is_op_success(op_code) {
+            if flags.contains(ScriptVerifyFlags::DISCOURAGE_OP_SUCCESS) {
+                return Err(ScriptError::DiscourageOpSuccess);
+            }
+
+            return Ok(());
+        }
+    }
+
+    // Signature checks are paid for by the size of the witness, so a
+    // tapscript can only check as many signatures as its witness is big
+    let witness_size = compact_size_len(witness.len() as u64) +
+        witness.iter().map(|element| compact_size_len(element.len() as u64) + element.len()).sum::<usize>();
+    exec_data.validation_weight_left = witness_size as i64 + VALIDATION_WEIGHT_OFFSET;
+
+    let script = Script::from_tapscript_bytes(&script_bytes)?;
+
+    execute_witness_script(stack, &script, flags, checker, SigVersion::Tapscript, exec_data, trace)
+}
+
+/// Runs a witness script against its inputs. Witness scripts must leave
+/// exactly one true element on the stack.
+///
+/// ### Arguments
+///
+/// * `stack`       - Inputs to the script, from the witness
+/// * `script`      - Script to run
+/// * `flags`       - Verification flags to run under
+/// * `checker`     - Checker for signatures against the spending transaction
+/// * `sig_version` - WitnessV0 or Tapscript
+/// * `exec_data`   - Execution data for the spend
+/// * `trace`       - Trace to record steps in, if tracing
+fn execute_witness_script(
+    mut stack: Vec<Vec<u8>>,
+    script: &Script,
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    sig_version: SigVersion,
+    exec_data: &mut ScriptExecutionData,
+    trace: Option<&mut ScriptTrace>
+) -> Result<(), ScriptError>
+{
+    // Tapscripts are held to the stack size limit from the start
+    if sig_version == SigVersion::Tapscript && stack.len() > MAX_STACK_SIZE {
+        return Err(ScriptError::StackSize);
+    }
+
+    // Witness stack elements are held to the same limit as pushes
+    if stack.iter().any(|element| element.len() > MAX_SCRIPT_ELEMENT_SIZE) {
+        return Err(ScriptError::PushSize);
+    }
+
+    run_script(&mut stack, script, flags, checker, sig_version, exec_data, ScriptPhase::WitnessScript, trace)?;
+
+    // Scripts inside witness implicitly require cleanstack behaviour
+    if stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+
+    if !cast_to_bool(&stack[0]) {
+        return Err(ScriptError::EvalFalse);
+    }
+
+    Ok(())
+}
+
+/// Interprets a stack element as a boolean. Any non-zero byte makes it true,
+/// except for a sign bit on the last byte on its own (negative zero).
+///
+/// ### Arguments
+///
+/// * `value`   - Stack element to interpret
+pub fn cast_to_bool(value: &[u8]) -> bool {
+    for (i, byte) in value.iter().enumerate() {
+        if *byte != 0 {
+            return !(i == value.len() - 1 && *byte == 0x80);
+        }
+    }
+
+    false
+}
+
+/// Checks the encoding of a signature under the given flags. Empty signatures
+/// always pass, as they are the way to deliberately fail a check.
+///
+/// ### Arguments
+///
+/// * `signature`   - Signature with sighash type byte
+/// * `flags`       - Verification flags to run under
+fn check_signature_encoding(signature: &[u8], flags: ScriptVerifyFlags) -> Result<(), ScriptError> {
+    let (hash_type, signature) = match signature.split_last() {
+        Some(split) => split,
+        None => return Ok(())
+    };
+
+    let strict = ScriptVerifyFlags::DERSIG | ScriptVerifyFlags::LOW_S | ScriptVerifyFlags::STRICTENC;
+
+    if flags.bits() & strict.bits() != 0 && signature.len() != SIGNATURE_SIZE {
+        return Err(ScriptError::SigDer);
+    }
+
+    if flags.contains(ScriptVerifyFlags::LOW_S) && !is_low_s(signature) {
+        return Err(ScriptError::SigHighS);
+    }
+
+    if flags.contains(ScriptVerifyFlags::STRICTENC) {
+        let base_type = (*hash_type as u32) & !SIGHASH_ANYONECANPAY;
+
+        if !(SIGHASH_ALL..=SIGHASH_SINGLE).contains(&base_type) {
+            return Err(ScriptError::SigHashType);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a signature in a tapscript (BIP342), returning whether it passed.
+/// An empty signature fails the check, but any other signature that fails
+/// fails the whole script. Every signature checked uses up part of the
+/// validation weight budget.
+///
+/// ### Arguments
+///
+/// * `signature`   - Signature, with a sighash type byte unless it is SIGHASH_DEFAULT
+/// * `public_key`  - Raw public key
+/// * `flags`       - Verification flags to run under
+/// * `checker`     - Checker for signatures against the spending transaction
+/// * `exec_data`   - Execution data for the spend
+fn check_tapscript_sig(
+    signature: &[u8],
+    public_key: &[u8],
+    flags: ScriptVerifyFlags,
+    checker: &dyn SignatureChecker,
+    exec_data: &mut ScriptExecutionData
+) -> Result<bool, ScriptError>
+{
+    let success = !signature.is_empty();
+
+    if success {
+        exec_data.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+
+        if exec_data.validation_weight_left < 0 {
+            return Err(ScriptError::TapscriptValidationWeight);
+        }
+    }
+
+    match public_key.len() {
+        0 => return Err(ScriptError::TapscriptEmptyPubKey),
+        SCHNORR_PUBLIC_KEY_SIZE => {
+            if success {
+                checker.check_schnorr_sig(signature, public_key, SigVersion::Tapscript, exec_data)?;
+            }
+        },
+        // Other key types are left for future soft forks, and any
+        // signature for them passes
+        _ => {
+            if flags.contains(ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_PUBKEYTYPE) {
+                return Err(ScriptError::DiscourageUpgradablePubKeyType);
+            }
+        }
+    }
+
+    Ok(success)
+}
+
+/// Checks the encoding of a public key under the given flags
+///
+/// ### Arguments
+///
+/// * `public_key`  - Raw public key
+/// * `flags`       - Verification flags to run under
+/// * `sig_version` - Signature version of the script being run
+fn check_public_key_encoding(public_key: &[u8], flags: ScriptVerifyFlags, sig_version: SigVersion) -> Result<(), ScriptError> {
+    if flags.contains(ScriptVerifyFlags::STRICTENC) && public_key.len() != PUBLIC_KEY_SIZE {
+        return Err(ScriptError::PubKeyType);
+    }
+
+    // Only the one key encoding is allowed in witness scripts
+    if flags.contains(ScriptVerifyFlags::WITNESS_PUBKEYTYPE) && sig_version == SigVersion::WitnessV0 &&
+       public_key.len() != PUBLIC_KEY_SIZE
+    {
+        return Err(ScriptError::WitnessPubKeyType);
+    }
+
+    Ok(())
+}
+
+/// Whether the S half of a signature is below the group order. Adding the
+/// order to S gives another signature that most implementations accept, so
+/// only the reduced form is allowed.
+///
+/// ### Arguments
+///
+/// * `signature`   - Signature, without sighash type byte
+fn is_low_s(signature: &[u8]) -> bool {
+    let s = &signature[SIGNATURE_SIZE / 2..];
+
+    for i in (0..GROUP_ORDER.len()).rev() {
+        if s[i] != GROUP_ORDER[i] {
+            return s[i] < GROUP_ORDER[i];
+        }
+    }
+
+    false
+}
+
+/// Whether an opcode is disabled. These fail wherever they appear, even in
+/// branches that are not executed.
+///
+/// ### Arguments
+///
+/// * `op_code` - Opcode byte to check
+fn is_disabled(op_code: u8) -> bool {
+    let disabled = [
+        OpCodes::OP_CAT, OpCodes::OP_SUBSTR, OpCodes::OP_LEFT, OpCodes::OP_RIGHT,
+        OpCodes::OP_INVERT, OpCodes::OP_AND, OpCodes::OP_OR, OpCodes::OP_XOR,
+        OpCodes::OP_2MUL, OpCodes::OP_2DIV, OpCodes::OP_MUL, OpCodes::OP_DIV,
+        OpCodes::OP_MOD, OpCodes::OP_LSHIFT, OpCodes::OP_RSHIFT
+    ];
+
+    disabled.iter().any(|op| *op as u8 == op_code)
+}
+
+/// Whether data was pushed with the smallest possible push opcode
+///
+/// ### Arguments
+///
+/// * `op_code` - Opcode the data was pushed with
+/// * `data`    - Pushed data
+fn is_minimal_push(op_code: u8, data: &[u8]) -> bool {
+    if data.is_empty() {
+        // Should have used OP_0
+        return op_code == OpCodes::OP_0 as u8;
+    }
+
+    if data.len() == 1 && data[0] >= 1 && data[0] <= 16 {
+        // Should have used OP_1 .. OP_16
+        return false;
+    }
+
+    if data.len() == 1 && data[0] == 0x81 {
+        // Should have used OP_1NEGATE
+        return false;
+    }
+
+    match data.len() {
+        1..=75 => op_code as usize == data.len(),
+        76..=255 => op_code == OpCodes::OP_PUSHDATA1 as u8,
+        256..=65535 => op_code == OpCodes::OP_PUSHDATA2 as u8,
+        _ => true
+    }
+}
+
+/// Element `depth` places from the top of the stack, with 1 being the top
+///
+/// ### Arguments
+///
+/// * `stack`   - Stack to read from
+/// * `depth`   - Position from the top
+fn top(stack: &[Vec<u8>], depth: usize) -> Result<&Vec<u8>, ScriptError> {
+    require(stack, depth)?;
+    Ok(&stack[stack.len() - depth])
+}
+
+/// Pops the top element off the stack
+///
+/// ### Arguments
+///
+/// * `stack`   - Stack to pop from
+fn pop(stack: &mut Vec<Vec<u8>>) -> Result<Vec<u8>, ScriptError> {
+    stack.pop().ok_or(ScriptError::InvalidStackOperation)
+}
+
+/// Fails unless the stack holds at least `count` elements
+///
+/// ### Arguments
+///
+/// * `stack`   - Stack to check
+/// * `count`   - Number of elements needed
+fn require(stack: &[Vec<u8>], count: usize) -> Result<(), ScriptError> {
+    if stack.len() < count {
+        return Err(ScriptError::InvalidStackOperation);
+    }
+
+    Ok(())
+}
+
+/// Encodes a boolean the way comparison opcodes push it
+///
+/// ### Arguments
+///
+/// * `value`   - Boolean to encode
+fn encode_bool(value: bool) -> Vec<u8> {
+    if value { vec![1] } else { Vec::new() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryptography::keys::{ SecretKey, generate_keypair, sign_message };
+    use policy::STANDARD_SCRIPT_VERIFY_FLAGS;
+    use primitives::test_utils::sample_transaction;
+    use primitives::transaction::Transaction;
+    use script_lang::checker::{ BaseSignatureChecker, TransactionSignatureChecker };
+    use script_lang::sighash::{ SIGHASH_NONE, legacy_signature_hash, standard_template_hash };
+    use utils::amount::Amount;
+    use utils::hex::to_hex;
+
+    fn asm(text: &str) -> Script {
+        Script::from_asm(text).unwrap()
+    }
+
+    fn verify(script_sig: &str, script_pubkey: &str, flags: ScriptVerifyFlags) -> Result<(), ScriptError> {
+        verify_script(&asm(script_sig).to_bytes(), &asm(script_pubkey).to_bytes(), &[], flags, &BaseSignatureChecker)
+    }
+
+    fn eval(stack: &mut Vec<Vec<u8>>, script: &str, flags: ScriptVerifyFlags, sig_version: SigVersion) -> Result<(), ScriptError> {
+        eval_script(stack, &asm(script), flags, &BaseSignatureChecker, sig_version)
+    }
+
+    /// Signs the legacy signature hash of `script_code` for input 0
+    fn sign_legacy(tx: &Transaction, script_code: &Script, hash_type: u32, secret_key: &SecretKey) -> Vec<u8> {
+        let sighash = legacy_signature_hash(script_code, tx, 0, hash_type);
+        let mut signature = sign_message(sighash.as_bytes(), secret_key);
+        signature.push(hash_type as u8);
+
+        signature
+    }
+
+    fn verify_spend_of(tx: &Transaction, script_sig: &Script, script_pubkey: &Script, flags: ScriptVerifyFlags) -> Result<(), ScriptError> {
+        let checker = TransactionSignatureChecker::new(tx, 0, Amount::ZERO);
+
+        verify_script(&script_sig.to_bytes(), &script_pubkey.to_bytes(), &[], flags, &checker)
+    }
+
+    #[test]
+    fn known_vectors() {
+        let none = ScriptVerifyFlags::NONE;
+        let vectors = vec![
+            ("OP_1 OP_2", "OP_ADD OP_3 OP_EQUAL", Ok(())),
+            ("OP_1NEGATE", "OP_ABS OP_1 OP_NUMEQUAL", Ok(())),
+            ("OP_2 OP_3 OP_4", "OP_WITHIN OP_NOT", Ok(())),
+            ("<ffffff7f>", "OP_1ADD OP_SIZE OP_5 OP_EQUAL OP_NIP", Ok(())),
+            ("<aa> <bb>", "OP_SWAP OP_DROP <bb> OP_EQUAL", Ok(())),
+            ("OP_1 OP_2 OP_3", "OP_ROT OP_1 OP_EQUALVERIFY OP_DEPTH OP_2 OP_EQUAL", Ok(())),
+            ("OP_1", "OP_TOALTSTACK OP_0 OP_FROMALTSTACK", Ok(())),
+            ("<>", "OP_SHA256 <e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855> OP_EQUAL", Ok(())),
+            ("OP_0", "", Err(ScriptError::EvalFalse)),
+            ("<80>", "", Err(ScriptError::EvalFalse)),
+            ("", "", Err(ScriptError::EvalFalse)),
+            ("", "OP_DROP OP_1", Err(ScriptError::InvalidStackOperation)),
+            ("", "OP_FROMALTSTACK", Err(ScriptError::InvalidAltstackOperation)),
+            ("OP_0", "OP_VERIFY OP_1", Err(ScriptError::Verify)),
+            ("OP_1 OP_2", "OP_EQUALVERIFY OP_1", Err(ScriptError::EqualVerify)),
+            ("OP_1 OP_2", "OP_NUMEQUALVERIFY OP_1", Err(ScriptError::NumEqualVerify)),
+            ("<0000008000>", "OP_1ADD", Err(ScriptError::NumOverflow)),
+            ("OP_1", "OP_RETURN", Err(ScriptError::OpReturn)),
+            ("OP_0", "OP_IF OP_RETURN OP_ENDIF OP_1", Ok(())),
+            ("OP_1", "OP_RESERVED", Err(ScriptError::BadOpcode)),
+            ("OP_0", "OP_IF OP_RESERVED OP_ENDIF OP_1", Ok(()))
+        ];
+
+        for (script_sig, script_pubkey, expected) in vectors {
+            assert_eq!(verify(script_sig, script_pubkey, none), expected, "{} / {}", script_sig, script_pubkey);
+        }
+    }
+
+    #[test]
+    fn conditionals() {
+        let none = ScriptVerifyFlags::NONE;
+        let vectors = vec![
+            ("OP_1", "OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF", Ok(())),
+            ("OP_0", "OP_IF OP_0 OP_ELSE OP_1 OP_ENDIF", Ok(())),
+            ("OP_0", "OP_NOTIF OP_1 OP_ELSE OP_0 OP_ENDIF", Ok(())),
+            ("OP_1", "OP_NOTIF OP_1 OP_ELSE OP_0 OP_ENDIF", Err(ScriptError::EvalFalse)),
+            ("OP_1 OP_0", "OP_IF OP_0 OP_ELSE OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF OP_ENDIF", Ok(())),
+            ("OP_0 OP_1", "OP_IF OP_IF OP_0 OP_ELSE OP_0 OP_ENDIF OP_ELSE OP_1 OP_ENDIF", Err(ScriptError::EvalFalse)),
+
+            // Every ELSE inverts the branch again
+            ("OP_0", "OP_IF OP_0 OP_ELSE OP_1 OP_ELSE OP_0 OP_ELSE OP_1 OP_ENDIF", Ok(())),
+
+            // An unexecuted IF doesn't pop its condition
+            ("OP_1 OP_0", "OP_IF OP_IF OP_0 OP_ENDIF OP_ENDIF", Ok(())),
+            ("", "OP_IF OP_1 OP_ENDIF", Err(ScriptError::UnbalancedConditional)),
+            ("OP_1", "OP_IF OP_1", Err(ScriptError::UnbalancedConditional)),
+            ("OP_1", "OP_ENDIF OP_1", Err(ScriptError::UnbalancedConditional)),
+            ("OP_1", "OP_ELSE OP_1 OP_ENDIF", Err(ScriptError::UnbalancedConditional)),
+
+            // Conditionals can't span the scriptSig and scriptPubKey
+            ("OP_1 OP_IF", "OP_1 OP_ENDIF", Err(ScriptError::UnbalancedConditional))
+        ];
+
+        for (script_sig, script_pubkey, expected) in vectors {
+            assert_eq!(verify(script_sig, script_pubkey, none), expected, "{} / {}", script_sig, script_pubkey);
+        }
+    }
+
+    #[test]
+    fn disabled_opcodes_fail_in_unexecuted_branches() {
+        let disabled = [
+            "OP_CAT", "OP_SUBSTR", "OP_LEFT", "OP_RIGHT", "OP_INVERT", "OP_AND", "OP_OR", "OP_XOR",
+            "OP_2MUL", "OP_2DIV", "OP_MUL", "OP_DIV", "OP_MOD", "OP_LSHIFT", "OP_RSHIFT"
+        ];
+
+        for op in disabled.iter() {
+            let script_pubkey = format!("OP_IF {} OP_ENDIF OP_1", op);
+
+            assert_eq!(verify("OP_0", &script_pubkey, ScriptVerifyFlags::NONE), Err(ScriptError::DisabledOpcode), "{}", op);
+        }
+    }
+
+    #[test]
+    fn stack_and_op_count_limits() {
+        let none = ScriptVerifyFlags::NONE;
+        let pushes = vec!["OP_1"; MAX_STACK_SIZE].join(" ");
+        assert_eq!(verify("", &pushes, none), Ok(()));
+        assert_eq!(verify("OP_1", &pushes, none), Err(ScriptError::StackSize));
+
+        // The alt stack counts towards the same limit
+        assert_eq!(verify("", &format!("{} OP_TOALTSTACK", pushes), none), Ok(()));
+        assert_eq!(verify("", &format!("{} OP_TOALTSTACK OP_1", pushes), none), Err(ScriptError::StackSize));
+
+        // Pushes don't count towards the opcode limit, but unexecuted opcodes do
+        let nops = vec!["OP_NOP"; MAX_OPS_PER_SCRIPT].join(" ");
+        assert_eq!(verify("OP_1", &nops, none), Ok(()));
+        assert_eq!(verify("OP_1", &format!("{} OP_NOP", nops), none), Err(ScriptError::OpCount));
+        assert_eq!(verify("OP_1", &format!("OP_0 OP_IF {} OP_ENDIF", nops), none), Err(ScriptError::OpCount));
+        assert_eq!(verify("OP_1", &format!("{} OP_16", nops), none), Ok(()));
+    }
+
+    #[test]
+    fn minimal_data() {
+        let minimal = ScriptVerifyFlags::MINIMALDATA;
+        let vectors = vec![
+            ("<01>", "OP_DROP OP_1"),
+            ("<81>", "OP_DROP OP_1"),
+            ("OP_PUSHDATA1 <aa>", "OP_DROP OP_1"),
+            ("OP_PUSHDATA2 <aa>", "OP_DROP OP_1"),
+            ("OP_PUSHDATA4 <aa>", "OP_DROP OP_1")
+        ];
+
+        for (script_sig, script_pubkey) in vectors {
+            assert_eq!(verify(script_sig, script_pubkey, ScriptVerifyFlags::NONE), Ok(()), "{}", script_sig);
+            assert_eq!(verify(script_sig, script_pubkey, minimal), Err(ScriptError::MinimalData), "{}", script_sig);
+        }
+
+        // Numeric operands must be minimally encoded too
+        assert_eq!(verify("<0100>", "OP_1ADD OP_2 OP_EQUAL", ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(verify("<0100>", "OP_1ADD OP_2 OP_EQUAL", minimal), Err(ScriptError::NumNotMinimal));
+        assert_eq!(verify("<00>", "OP_NOT", minimal), Err(ScriptError::NumNotMinimal));
+
+        // Pushes in unexecuted branches aren't checked
+        assert_eq!(verify("OP_0", "OP_IF <01> OP_ENDIF OP_1", minimal), Ok(()));
+    }
+
+    #[test]
+    fn minimal_if_only_applies_to_witness_scripts() {
+        let script = "OP_IF OP_1 OP_ELSE OP_0 OP_ENDIF";
+        let minimal_if = ScriptVerifyFlags::MINIMALIF;
+
+        for condition in [vec![2], vec![1, 0], vec![0]] {
+            let mut stack = vec![condition.clone()];
+            assert_eq!(eval(&mut stack, script, minimal_if, SigVersion::WitnessV0), Err(ScriptError::MinimalIf), "{:?}", condition);
+
+            let mut stack = vec![condition.clone()];
+            assert_eq!(eval(&mut stack, script, ScriptVerifyFlags::NONE, SigVersion::WitnessV0), Ok(()));
+
+            let mut stack = vec![condition.clone()];
+            assert_eq!(eval(&mut stack, script, minimal_if, SigVersion::Base), Ok(()));
+        }
+
+        for condition in [vec![], vec![1]] {
+            let mut stack = vec![condition.clone()];
+            assert_eq!(eval(&mut stack, script, minimal_if, SigVersion::WitnessV0), Ok(()));
+            assert_eq!(stack, vec![encode_bool(!condition.is_empty())]);
+        }
+    }
+
+    #[test]
+    fn eval_script_runs_on_the_given_stack() {
+        let mut stack = vec![vec![1], vec![2], vec![3]];
+        assert_eq!(eval(&mut stack, "OP_ROT OP_2DUP", ScriptVerifyFlags::NONE, SigVersion::Base), Ok(()));
+        assert_eq!(stack, vec![vec![2], vec![3], vec![1], vec![3], vec![1]]);
+
+        let mut stack = vec![vec![5]];
+        assert_eq!(eval(&mut stack, "OP_1SUB OP_DUP OP_ADD", ScriptVerifyFlags::NONE, SigVersion::Base), Ok(()));
+        assert_eq!(stack, vec![vec![8]]);
+
+        // Nothing is required of the final stack
+        let mut stack = Vec::new();
+        assert_eq!(eval(&mut stack, "OP_0", ScriptVerifyFlags::NONE, SigVersion::Base), Ok(()));
+        assert_eq!(stack, vec![Vec::<u8>::new()]);
+    }
+
+    #[test]
+    fn upgradable_nops_and_clean_stack() {
+        let discourage = ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS;
+
+        assert_eq!(verify("OP_1", "OP_NOP1 OP_NOP10", ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(verify("OP_1", "OP_NOP1", discourage), Err(ScriptError::DiscourageUpgradableNops));
+        assert_eq!(verify("OP_1", "OP_NOP10", discourage), Err(ScriptError::DiscourageUpgradableNops));
+        assert_eq!(verify("OP_0", "OP_IF OP_NOP1 OP_ENDIF OP_1", discourage), Ok(()));
+
+        let clean_stack = ScriptVerifyFlags::P2SH | ScriptVerifyFlags::CLEANSTACK;
+        assert_eq!(verify("OP_1 OP_1", "", ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(verify("OP_1 OP_1", "", clean_stack), Err(ScriptError::CleanStack));
+        assert_eq!(verify("OP_1", "", clean_stack), Ok(()));
+    }
+
+    #[test]
+    fn casting_to_bool() {
+        assert!(!cast_to_bool(&[]));
+        assert!(!cast_to_bool(&[0]));
+        assert!(!cast_to_bool(&[0, 0]));
+        assert!(!cast_to_bool(&[0x80]));
+        assert!(!cast_to_bool(&[0, 0, 0x80]));
+        assert!(cast_to_bool(&[1]));
+        assert!(cast_to_bool(&[0x80, 0]));
+        assert!(cast_to_bool(&[0, 0x81]));
+    }
+
+    #[test]
+    fn checksig_against_a_transaction() {
+        let tx = sample_transaction(1, 1);
+        let (public_key, secret_key) = generate_keypair();
+        let strict = ScriptVerifyFlags::STRICTENC | ScriptVerifyFlags::NULLFAIL;
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIG);
+
+        for hash_type in [SIGHASH_ALL, SIGHASH_NONE, SIGHASH_SINGLE, SIGHASH_ALL | SIGHASH_ANYONECANPAY].iter() {
+            let mut script_sig = Script::new();
+            script_sig.push_data(&sign_legacy(&tx, &script_pubkey, *hash_type, &secret_key));
+
+            assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, strict), Ok(()), "hash type {:#x}", hash_type);
+        }
+
+        // A signature with a different hash type byte than it was made with fails
+        let mut signature = sign_legacy(&tx, &script_pubkey, SIGHASH_ALL, &secret_key);
+        *signature.last_mut().unwrap() = SIGHASH_NONE as u8;
+
+        let mut script_sig = Script::new();
+        script_sig.push_data(&signature);
+        assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE), Err(ScriptError::EvalFalse));
+        assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, strict), Err(ScriptError::SigNullFail));
+
+        // Only an empty signature may fail under NULLFAIL
+        let mut script_pubkey_not = script_pubkey.clone();
+        script_pubkey_not.push_op(OpCodes::OP_NOT);
+        assert_eq!(verify_spend_of(&tx, &asm("OP_0"), &script_pubkey_not, strict), Ok(()));
+
+        // Undefined hash types are only rejected under STRICTENC
+        let signature = sign_legacy(&tx, &script_pubkey, 0x04, &secret_key);
+        let mut script_sig = Script::new();
+        script_sig.push_data(&signature);
+        assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, strict), Err(ScriptError::SigHashType));
+    }
+
+    #[test]
+    fn checksigverify_against_a_transaction() {
+        let tx = sample_transaction(1, 1);
+        let (public_key, secret_key) = generate_keypair();
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIGVERIFY).push_op(OpCodes::OP_1);
+
+        let mut script_sig = Script::new();
+        script_sig.push_data(&sign_legacy(&tx, &script_pubkey, SIGHASH_ALL, &secret_key));
+        assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+
+        // Signed for another transaction
+        let mut other = tx.clone();
+        other.set_lock_time(1);
+        assert_eq!(verify_spend_of(&other, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE), Err(ScriptError::CheckSigVerify));
+        assert_eq!(verify_spend_of(&tx, &asm("OP_0"), &script_pubkey, ScriptVerifyFlags::NONE), Err(ScriptError::CheckSigVerify));
+    }
+
+    #[test]
+    fn signatures_are_deleted_from_the_script_code() {
+        let tx = sample_transaction(1, 1);
+        let (public_key, secret_key) = generate_keypair();
+
+        // The script code signed is what's left once the signature is deleted
+        let mut signed_code = Script::new();
+        signed_code.push_op(OpCodes::OP_DROP).push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIG);
+        let signature = sign_legacy(&tx, &signed_code, SIGHASH_ALL, &secret_key);
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_data(&signature).push_op(OpCodes::OP_DROP)
+            .push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIG);
+
+        let mut script_sig = Script::new();
+        script_sig.push_data(&signature);
+
+        assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(
+            verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::CONST_SCRIPTCODE),
+            Err(ScriptError::SigFindAndDelete)
+        );
+    }
+
+    #[test]
+    fn signatures_commit_to_the_script_after_the_last_code_separator() {
+        let tx = sample_transaction(1, 1);
+        let (public_key, secret_key) = generate_keypair();
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_NOP).push_op(OpCodes::OP_CODESEPARATOR)
+            .push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIG);
+
+        let after_separator = script_pubkey.get_subscript(2);
+
+        for (script_code, expected) in [(&after_separator, Ok(())), (&script_pubkey, Err(ScriptError::EvalFalse))] {
+            let mut script_sig = Script::new();
+            script_sig.push_data(&sign_legacy(&tx, script_code, SIGHASH_ALL, &secret_key));
+
+            assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE), expected);
+        }
+
+        // Separators in non-segwit scripts are rejected outright under CONST_SCRIPTCODE
+        assert_eq!(
+            verify_spend_of(&tx, &asm("OP_0"), &asm("OP_IF OP_CODESEPARATOR OP_ENDIF OP_1"), ScriptVerifyFlags::CONST_SCRIPTCODE),
+            Err(ScriptError::OpCodeSeparator)
+        );
+    }
+
+    /// Bare `m`-of-n multisig script for the given keys
+    fn multisig_script(required: i64, public_keys: &[&[u8]], op: OpCodes) -> Script {
+        let mut script = Script::new();
+        script.push_data(&ScriptNum::new(required).to_bytes());
+
+        for public_key in public_keys {
+            script.push_data(public_key);
+        }
+
+        script.push_data(&ScriptNum::new(public_keys.len() as i64).to_bytes()).push_op(op);
+        script
+    }
+
+    #[test]
+    fn checkmultisig_matches_keys_in_order() {
+        let tx = sample_transaction(1, 1);
+        let keys: Vec<_> = (0..3).map(|_| generate_keypair()).collect();
+        let public_keys: Vec<&[u8]> = keys.iter().map(|(public_key, _)| public_key.as_ref()).collect();
+        let script_pubkey = multisig_script(2, &public_keys, OpCodes::OP_CHECKMULTISIG);
+        let signatures: Vec<Vec<u8>> = keys.iter().map(|(_, secret_key)| sign_legacy(&tx, &script_pubkey, SIGHASH_ALL, secret_key)).collect();
+
+        let spend = |order: &[usize]| {
+            let mut script_sig = Script::new();
+            script_sig.push_op(OpCodes::OP_0);
+
+            for i in order {
+                script_sig.push_data(&signatures[*i]);
+            }
+
+            verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NONE)
+        };
+
+        assert_eq!(spend(&[0, 1]), Ok(()));
+        assert_eq!(spend(&[0, 2]), Ok(()));
+        assert_eq!(spend(&[1, 2]), Ok(()));
+
+        // Each key is only tried once, so signatures out of key order fail
+        assert_eq!(spend(&[1, 0]), Err(ScriptError::EvalFalse));
+        assert_eq!(spend(&[2, 1]), Err(ScriptError::EvalFalse));
+        assert_eq!(spend(&[0, 0]), Err(ScriptError::EvalFalse));
+
+        let verify_script_pubkey = multisig_script(2, &public_keys, OpCodes::OP_CHECKMULTISIGVERIFY);
+        let mut script_sig = Script::new();
+        script_sig.push_op(OpCodes::OP_0).push_data(&signatures[1]).push_data(&signatures[0]);
+
+        assert_eq!(
+            verify_spend_of(&tx, &script_sig, &verify_script_pubkey, ScriptVerifyFlags::NONE),
+            Err(ScriptError::CheckMultiSigVerify)
+        );
+        assert_eq!(
+            verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::NULLFAIL),
+            Err(ScriptError::SigNullFail)
+        );
+    }
+
+    #[test]
+    fn checkmultisig_dummy_element() {
+        let none = ScriptVerifyFlags::NONE;
+        let null_dummy = ScriptVerifyFlags::NULLDUMMY;
+
+        assert_eq!(verify("OP_0", "OP_0 <11> OP_1 OP_CHECKMULTISIG", null_dummy), Ok(()));
+
+        // Any dummy passes, unless NULLDUMMY requires it to be empty
+        assert_eq!(verify("OP_1", "OP_0 <11> OP_1 OP_CHECKMULTISIG", none), Ok(()));
+        assert_eq!(verify("OP_1", "OP_0 <11> OP_1 OP_CHECKMULTISIG", null_dummy), Err(ScriptError::SigNullDummy));
+
+        // Without a dummy there's one element too few
+        assert_eq!(verify("", "OP_0 <11> OP_1 OP_CHECKMULTISIG", none), Err(ScriptError::InvalidStackOperation));
+    }
+
+    #[test]
+    fn checkmultisig_count_bounds() {
+        let none = ScriptVerifyFlags::NONE;
+        let keys = |count: usize| vec!["<11>"; count].join(" ");
+
+        let max_keys = format!("OP_0 {} <14> OP_CHECKMULTISIG", keys(MAX_PUB_KEYS_PER_MULTISIG));
+        assert_eq!(verify("OP_0", &max_keys, none), Ok(()));
+
+        let too_many_keys = format!("OP_0 {} <15> OP_CHECKMULTISIG", keys(MAX_PUB_KEYS_PER_MULTISIG + 1));
+        assert_eq!(verify("OP_0", &too_many_keys, none), Err(ScriptError::PubKeyCount));
+        assert_eq!(verify("OP_0", "OP_0 OP_1NEGATE OP_CHECKMULTISIG", none), Err(ScriptError::PubKeyCount));
+
+        assert_eq!(verify("OP_0", "OP_0 OP_0 OP_CHECKMULTISIG", none), Ok(()));
+        assert_eq!(verify("OP_0 OP_0 OP_0", "OP_2 <11> OP_1 OP_CHECKMULTISIG", none), Err(ScriptError::SigCount));
+        assert_eq!(verify("OP_0", "OP_1NEGATE <11> OP_1 OP_CHECKMULTISIG", none), Err(ScriptError::SigCount));
+
+        // Not enough elements below the key count for the keys it claims
+        assert_eq!(verify("", "OP_0 <11> OP_2 OP_CHECKMULTISIG", none), Err(ScriptError::InvalidStackOperation));
+    }
+
+    #[test]
+    fn checkmultisig_keys_count_towards_the_op_limit() {
+        let none = ScriptVerifyFlags::NONE;
+        let multisig = format!("OP_0 {} <14> OP_CHECKMULTISIG", vec!["<11>"; MAX_PUB_KEYS_PER_MULTISIG].join(" "));
+
+        // The opcode and each of its keys count as one operation
+        let fits = MAX_OPS_PER_SCRIPT - 1 - MAX_PUB_KEYS_PER_MULTISIG;
+        let nops = |count: usize| vec!["OP_NOP"; count].join(" ");
+
+        assert_eq!(verify("OP_0", &format!("{} {}", nops(fits), multisig), none), Ok(()));
+        assert_eq!(verify("OP_0", &format!("{} {}", nops(fits + 1), multisig), none), Err(ScriptError::OpCount));
+
+        // Keys are only counted when the opcode runs
+        let unexecuted = format!("OP_0 OP_IF {} OP_ENDIF OP_1", multisig);
+        assert_eq!(verify("", &format!("{} {}", nops(MAX_OPS_PER_SCRIPT - 3), unexecuted), none), Ok(()));
+    }
+
+    fn p2sh_script(redeem_script: &Script) -> Script {
+        let mut script = Script::new();
+        script.push_op(OpCodes::OP_HASH160).push_data(&hash160(&redeem_script.to_bytes())).push_op(OpCodes::OP_EQUAL);
+
+        script
+    }
+
+    #[test]
+    fn p2sh_runs_the_redeem_script() {
+        let p2sh = ScriptVerifyFlags::P2SH;
+        let redeem_script = asm("OP_2 OP_EQUAL");
+        let script_pubkey = p2sh_script(&redeem_script).to_asm();
+        let spend = |inputs: &str| format!("{} <{}>", inputs, to_hex(&redeem_script.to_bytes()));
+
+        assert_eq!(verify(&spend("OP_2"), &script_pubkey, p2sh), Ok(()));
+        assert_eq!(verify(&spend("OP_3"), &script_pubkey, p2sh), Err(ScriptError::EvalFalse));
+        assert_eq!(verify(&spend(""), &script_pubkey, p2sh), Err(ScriptError::InvalidStackOperation));
+
+        // Before BIP16 only the hash is checked
+        assert_eq!(verify(&spend("OP_3"), &script_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+
+        // A redeem script that doesn't match the hash fails as any other script would
+        assert_eq!(verify("OP_2 <52>", &script_pubkey, p2sh), Err(ScriptError::EvalFalse));
+
+        let failing = asm("OP_RETURN");
+        let failing_pubkey = p2sh_script(&failing).to_asm();
+        let failing_spend = format!("<{}>", to_hex(&failing.to_bytes()));
+        assert_eq!(verify(&failing_spend, &failing_pubkey, p2sh), Err(ScriptError::OpReturn));
+        assert_eq!(verify(&failing_spend, &failing_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+    }
+
+    #[test]
+    fn p2sh_requires_push_only_signature_scripts() {
+        let p2sh = ScriptVerifyFlags::P2SH;
+        let redeem_script = asm("OP_2 OP_EQUAL");
+        let script_pubkey = p2sh_script(&redeem_script).to_asm();
+        let redeem_push = format!("<{}>", to_hex(&redeem_script.to_bytes()));
+
+        let not_push_only = format!("OP_1 OP_1ADD {}", redeem_push);
+        assert_eq!(verify(&not_push_only, &script_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(verify(&not_push_only, &script_pubkey, p2sh), Err(ScriptError::SigPushOnly));
+
+        // SIGPUSHONLY extends the rule to every spend
+        assert_eq!(verify("OP_1 OP_NOP", "", ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(verify("OP_1 OP_NOP", "", ScriptVerifyFlags::SIGPUSHONLY), Err(ScriptError::SigPushOnly));
+        assert_eq!(verify(&format!("OP_2 {}", redeem_push), &script_pubkey, p2sh | ScriptVerifyFlags::SIGPUSHONLY), Ok(()));
+    }
+
+    #[test]
+    fn p2sh_needs_the_exact_template() {
+        // The same hash pushed with OP_PUSHDATA1 isn't P2SH, so the redeem
+        // script is never run
+        let redeem_script = asm("OP_2 OP_EQUAL");
+        let hash = to_hex(&hash160(&redeem_script.to_bytes()));
+        let script_pubkey = format!("OP_HASH160 OP_PUSHDATA1 <{}> OP_EQUAL", hash);
+        let script_sig = format!("OP_3 <{}>", to_hex(&redeem_script.to_bytes()));
+
+        assert_eq!(verify(&script_sig, &script_pubkey, ScriptVerifyFlags::P2SH), Ok(()));
+        assert_eq!(verify(&script_sig, &p2sh_script(&redeem_script).to_asm(), ScriptVerifyFlags::P2SH), Err(ScriptError::EvalFalse));
+    }
+
+    #[test]
+    fn p2sh_signatures_commit_to_the_redeem_script() {
+        let tx = sample_transaction(1, 1);
+        let (public_key, secret_key) = generate_keypair();
+
+        let mut redeem_script = Script::new();
+        redeem_script.push_data(public_key.as_ref()).push_op(OpCodes::OP_CHECKSIG);
+        let script_pubkey = p2sh_script(&redeem_script);
+
+        for (script_code, expected) in [(&redeem_script, Ok(())), (&script_pubkey, Err(ScriptError::EvalFalse))] {
+            let mut script_sig = Script::new();
+            script_sig.push_data(&sign_legacy(&tx, script_code, SIGHASH_ALL, &secret_key)).push_data(&redeem_script.to_bytes());
+
+            assert_eq!(verify_spend_of(&tx, &script_sig, &script_pubkey, ScriptVerifyFlags::P2SH), expected);
+        }
+    }
+
+    #[test]
+    fn checktemplateverify_matches_the_spending_transaction() {
+        let ctv = ScriptVerifyFlags::CHECKTEMPLATEVERIFY;
+        let tx = sample_transaction(1, 2);
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_data(standard_template_hash(&tx, 0, None).as_bytes()).push_op(OpCodes::OP_CHECKTEMPLATEVERIFY);
+
+        assert_eq!(verify_spend_of(&tx, &Script::new(), &script_pubkey, ctv), Ok(()));
+        assert_eq!(verify_spend_of(&tx, &Script::new(), &script_pubkey, STANDARD_SCRIPT_VERIFY_FLAGS), Ok(()));
+
+        let mut other = tx.clone();
+        other.outputs_mut()[1].value = Some(1);
+        assert_eq!(verify_spend_of(&other, &Script::new(), &script_pubkey, ctv), Err(ScriptError::TemplateMismatch));
+        assert_eq!(verify_spend_of(&other, &Script::new(), &script_pubkey, STANDARD_SCRIPT_VERIFY_FLAGS), Err(ScriptError::TemplateMismatch));
+
+        // Before activation the opcode is still OP_NOP4
+        assert_eq!(verify_spend_of(&other, &Script::new(), &script_pubkey, ScriptVerifyFlags::NONE), Ok(()));
+        assert_eq!(
+            verify_spend_of(&other, &Script::new(), &script_pubkey, ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS),
+            Err(ScriptError::DiscourageUpgradableNops)
+        );
+
+        assert_eq!(verify_spend_of(&tx, &Script::new(), &asm("OP_CHECKTEMPLATEVERIFY"), ctv), Err(ScriptError::InvalidStackOperation));
+    }
+
+    #[test]
+    fn checktemplateverify_other_sizes_are_nops() {
+        let ctv = ScriptVerifyFlags::CHECKTEMPLATEVERIFY;
+        let tx = sample_transaction(1, 1);
+
+        for size in [0, 1, 31, 33] {
+            let mut script_pubkey = Script::new();
+            script_pubkey.push_data(&vec![0x11; size]).push_op(OpCodes::OP_CHECKTEMPLATEVERIFY).push_op(OpCodes::OP_DROP).push_op(OpCodes::OP_1);
+
+            assert_eq!(verify_spend_of(&tx, &Script::new(), &script_pubkey, ctv), Ok(()), "{} bytes", size);
+            assert_eq!(
+                verify_spend_of(&tx, &Script::new(), &script_pubkey, ctv | ScriptVerifyFlags::DISCOURAGE_UPGRADABLE_NOPS),
+                Err(ScriptError::DiscourageUpgradableNops),
+                "{} bytes", size
+            );
+            assert_eq!(
+                verify_spend_of(&tx, &Script::new(), &script_pubkey, STANDARD_SCRIPT_VERIFY_FLAGS),
+                Err(ScriptError::DiscourageUpgradableNops),
+                "{} bytes", size
+            );
+        }
+    }
+}
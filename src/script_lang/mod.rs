@@ -1 +1,11 @@
-pub mod script;
\ No newline at end of file
+pub mod checker;
+pub mod compiler;
+pub mod error;
+pub mod flags;
+pub mod interpreter;
+pub mod num;
+pub mod script;
+pub mod sighash;
+pub mod standard;
+pub mod taproot;
+pub mod trace;
@@ -0,0 +1,283 @@
+/*
+ * Script numbers. Numeric opcodes read their operands off the stack as
+ * little-endian sign-magnitude integers: the high bit of the last byte is the
+ * sign, and zero is the empty array. Operands are limited to 4 bytes (5 for
+ * CHECKLOCKTIMEVERIFY and CHECKSEQUENCEVERIFY), but results may overflow
+ * that and still be pushed, they just can't be used as operands again.
+ *
+ * This has to match Bitcoin's CScriptNum exactly, byte for byte.
+ */
+
+use std::fmt;
+use std::ops::{ Add, Sub, Neg };
+use script_lang::error::ScriptError;
+
+
+/*---- CONSTANTS ----*/
+
+/// Maximum size of a numeric operand, in bytes
+pub const DEFAULT_MAX_NUM_SIZE: usize = 4;
+
+/// Maximum size of a lock time operand, in bytes. Lock times are unsigned
+/// 32 bit values, so they need the extra byte for the sign.
+pub const LOCKTIME_MAX_NUM_SIZE: usize = 5;
+
+/// Largest element that fits the 64 bit value of a script number, in bytes
+const MAX_NUM_SIZE: usize = 8;
+
+
+/*---- STRUCTS ----*/
+
+/// A script number. Values are held as 64 bits so that arithmetic on two
+/// maximum size operands can't overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScriptNum(i64);
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl ScriptNum {
+
+    /// Creates a script number from an integer
+    ///
+    /// ### Arguments
+    ///
+    /// * `value`   - Integer value
+    pub fn new(value: i64) -> ScriptNum {
+        ScriptNum(value)
+    }
+
+    /// Decodes a script number from a stack element. Elements over 8 bytes
+    /// don't fit the 64 bit value and always overflow, whatever `max_size` is.
+    ///
+    /// ### Arguments
+    ///
+    /// * `data`            - Stack element to decode
+    /// * `require_minimal` - Whether to reject encodings with excess bytes
+    /// * `max_size`        - Maximum size of the element, in bytes
+    pub fn from_bytes(data: &[u8], require_minimal: bool, max_size: usize) -> Result<ScriptNum, ScriptError> {
+        if data.len() > max_size.min(MAX_NUM_SIZE) {
+            return Err(ScriptError::NumOverflow);
+        }
+
+        if require_minimal && !is_minimally_encoded(data) {
+            return Err(ScriptError::NumNotMinimal);
+        }
+
+        if data.is_empty() {
+            return Ok(ScriptNum(0));
+        }
+
+        let mut result: i64 = 0;
+
+        for (i, byte) in data.iter().enumerate() {
+            result |= (*byte as i64) << (8 * i);
+        }
+
+        // If the input's most significant byte has the sign bit set, the
+        // result is negative, so clear the bit and negate
+        let sign_bit = 0x80 << (8 * (data.len() - 1));
+
+        if result & sign_bit != 0 {
+            Ok(ScriptNum(-(result & !sign_bit)))
+        } else {
+            Ok(ScriptNum(result))
+        }
+    }
+
+    /// Encodes the number as a minimal stack element
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        let negative = self.0 < 0;
+        let mut abs = self.0.unsigned_abs();
+
+        while abs > 0 {
+            result.push((abs & 0xff) as u8);
+            abs >>= 8;
+        }
+
+        // If the most significant byte already has its high bit set, an extra
+        // byte is needed for the sign. Otherwise the sign goes in that bit.
+        match result.last().cloned() {
+            Some(last) if last & 0x80 != 0 => result.push(if negative { 0x80 } else { 0 }),
+            Some(_) if negative => *result.last_mut().unwrap() |= 0x80,
+            _ => {}
+        }
+
+        result
+    }
+
+    /// Full value of the number
+    pub fn value(&self) -> i64 {
+        self.0
+    }
+
+    /// Value of the number clamped to the range of an i32, as CScriptNum::getint
+    pub fn get_int(&self) -> i32 {
+        if self.0 > i32::MAX as i64 {
+            i32::MAX
+        } else if self.0 < i32::MIN as i64 {
+            i32::MIN
+        } else {
+            self.0 as i32
+        }
+    }
+}
+
+impl From<i64> for ScriptNum {
+    fn from(value: i64) -> ScriptNum {
+        ScriptNum(value)
+    }
+}
+
+impl Add for ScriptNum {
+    type Output = ScriptNum;
+
+    fn add(self, other: ScriptNum) -> ScriptNum {
+        ScriptNum(self.0 + other.0)
+    }
+}
+
+impl Sub for ScriptNum {
+    type Output = ScriptNum;
+
+    fn sub(self, other: ScriptNum) -> ScriptNum {
+        ScriptNum(self.0 - other.0)
+    }
+}
+
+impl Neg for ScriptNum {
+    type Output = ScriptNum;
+
+    fn neg(self) -> ScriptNum {
+        ScriptNum(-self.0)
+    }
+}
+
+impl fmt::Display for ScriptNum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Whether a stack element is the shortest encoding of the number it holds.
+/// The empty array is minimal, and otherwise the last byte may only be zero
+/// (apart from the sign bit) if the byte before it needs its high bit, since
+/// that would otherwise be taken as the sign.
+///
+/// ### Arguments
+///
+/// * `data`    - Stack element to check
+pub fn is_minimally_encoded(data: &[u8]) -> bool {
+    match data.last() {
+        Some(last) if last & 0x7f == 0 => data.len() > 1 && data[data.len() - 2] & 0x80 != 0,
+        _ => true
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8]) -> Result<i64, ScriptError> {
+        ScriptNum::from_bytes(data, true, DEFAULT_MAX_NUM_SIZE).map(|num| num.value())
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let cases: Vec<(i64, Vec<u8>)> = vec![
+            (0, vec![]),
+            (1, vec![0x01]),
+            (-1, vec![0x81]),
+            (127, vec![0x7f]),
+            (-127, vec![0xff]),
+            (128, vec![0x80, 0x00]),
+            (-128, vec![0x80, 0x80]),
+            (255, vec![0xff, 0x00]),
+            (-255, vec![0xff, 0x80]),
+            (256, vec![0x00, 0x01]),
+            (-256, vec![0x00, 0x81]),
+            (32767, vec![0xff, 0x7f]),
+            (-32767, vec![0xff, 0xff]),
+            (32768, vec![0x00, 0x80, 0x00]),
+            (-32768, vec![0x00, 0x80, 0x80]),
+            (2147483647, vec![0xff, 0xff, 0xff, 0x7f]),
+            (-2147483647, vec![0xff, 0xff, 0xff, 0xff]),
+            (-2147483648, vec![0x00, 0x00, 0x00, 0x80, 0x80])
+        ];
+
+        for (value, bytes) in cases {
+            assert_eq!(ScriptNum::new(value).to_bytes(), bytes, "encoding {}", value);
+            assert_eq!(ScriptNum::from_bytes(&bytes, true, LOCKTIME_MAX_NUM_SIZE).map(|num| num.value()), Ok(value), "decoding {}", value);
+        }
+    }
+
+    #[test]
+    fn negative_zero() {
+        assert_eq!(ScriptNum::from_bytes(&[0x80], false, DEFAULT_MAX_NUM_SIZE).map(|num| num.value()), Ok(0));
+        assert_eq!(decode(&[0x80]), Err(ScriptError::NumNotMinimal));
+        assert_eq!(ScriptNum::new(0).to_bytes(), Vec::<u8>::new());
+        assert_eq!((-ScriptNum::new(0)).to_bytes(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn operand_size_limits() {
+        // -2^31 needs a fifth byte for the sign, so only lock times can hold it
+        let min_i32 = ScriptNum::new(-2147483648).to_bytes();
+
+        assert_eq!(decode(&min_i32), Err(ScriptError::NumOverflow));
+        assert_eq!(ScriptNum::from_bytes(&min_i32, true, LOCKTIME_MAX_NUM_SIZE), Ok(ScriptNum::new(-2147483648)));
+
+        // The largest lock time, 2^32 - 1, fits in five bytes but not four
+        let max_lock_time = ScriptNum::new(0xffffffff).to_bytes();
+
+        assert_eq!(max_lock_time, vec![0xff, 0xff, 0xff, 0xff, 0x00]);
+        assert_eq!(decode(&max_lock_time), Err(ScriptError::NumOverflow));
+        assert_eq!(ScriptNum::from_bytes(&max_lock_time, true, LOCKTIME_MAX_NUM_SIZE), Ok(ScriptNum::new(0xffffffff)));
+
+        assert_eq!(ScriptNum::from_bytes(&[1, 0, 0, 0, 0, 1], false, LOCKTIME_MAX_NUM_SIZE), Err(ScriptError::NumOverflow));
+
+        // Nothing over 8 bytes fits, however large the limit
+        let max = ScriptNum::new(i64::MAX).to_bytes();
+
+        assert_eq!(ScriptNum::from_bytes(&max, true, 16), Ok(ScriptNum::new(i64::MAX)));
+        assert_eq!(ScriptNum::from_bytes(&[1; 9], false, 9), Err(ScriptError::NumOverflow));
+        assert_eq!(ScriptNum::from_bytes(&[1; 9], false, usize::MAX), Err(ScriptError::NumOverflow));
+    }
+
+    #[test]
+    fn non_minimal_encodings_are_rejected() {
+        for data in [vec![0x00], vec![0x01, 0x00], vec![0x7f, 0x80], vec![0x00, 0x80], vec![0xff, 0x00, 0x00]].iter() {
+            assert_eq!(decode(data), Err(ScriptError::NumNotMinimal), "{:?}", data);
+            assert!(ScriptNum::from_bytes(data, false, DEFAULT_MAX_NUM_SIZE).is_ok());
+        }
+
+        assert_eq!(ScriptNum::from_bytes(&[0x01, 0x00], false, DEFAULT_MAX_NUM_SIZE), Ok(ScriptNum::new(1)));
+        assert_eq!(ScriptNum::from_bytes(&[0x7f, 0x80], false, DEFAULT_MAX_NUM_SIZE), Ok(ScriptNum::new(-127)));
+    }
+
+    #[test]
+    fn encodings_are_always_minimal() {
+        let mut values = vec![i64::MAX, i64::MIN + 1];
+
+        for shift in 0..63 {
+            let power = 1i64 << shift;
+            values.extend_from_slice(&[power - 1, power, power + 1, -power + 1, -power, -power - 1]);
+        }
+
+        for value in values {
+            let bytes = ScriptNum::new(value).to_bytes();
+
+            assert!(is_minimally_encoded(&bytes), "{} encoded as {:?}", value, bytes);
+            assert!(bytes.len() <= 9);
+
+            if bytes.len() <= 8 {
+                assert_eq!(ScriptNum::from_bytes(&bytes, true, 8), Ok(ScriptNum::new(value)));
+            }
+        }
+    }
+}
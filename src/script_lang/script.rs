@@ -1,28 +1,30 @@
 use std::fmt;
+use utils::hex::{ to_hex, from_hex };
+use script_lang::error::{ ScriptError, AsmError };
 
 /*---- CONSTANTS ----*/
 
 /// Maximum number of bytes pushable to the stack
-const MAX_SCRIPT_ELEMENT_SIZE: u16 = 520;
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
 
 /// Maximum number of non-push operations per script
-const MAX_OPS_PER_SCRIPT: u8 = 201;
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
 
 /// Maximum number of public keys per multisig
-const MAX_PUB_KEYS_PER_MULTISIG: u8 = 20;
+pub const MAX_PUB_KEYS_PER_MULTISIG: usize = 20;
 
 /// Maximum script length in bytes
-const MAX_SCRIPT_SIZE: u16 = 10000;
+pub const MAX_SCRIPT_SIZE: usize = 10000;
 
 /// Maximum number of values on script interpreter stack
-const MAX_STACK_SIZE: u16 = 1000;
+pub const MAX_STACK_SIZE: usize = 1000;
 
 /// Threshold for lock_time: below this value it is interpreted as block number,
 /// otherwise as UNIX timestamp.
-const LOCKTIME_THRESHOLD: u32 = 500000000; // Tue Nov 5 00:53:20 1985 UTC
+pub const LOCKTIME_THRESHOLD: u32 = 500000000; // Tue Nov 5 00:53:20 1985 UTC
 
 /// Maximum value that an opcode can be
-const MAX_OPCODE: u8 = OpCodes::OP_NOP10 as u8;
+pub const MAX_OPCODE: u8 = OpCodes::OP_NOP10 as u8;
 
 
 /*---- STRUCTS + ENUMS ----*/
@@ -32,21 +34,40 @@ const MAX_OPCODE: u8 = OpCodes::OP_NOP10 as u8;
 /// 
 /// Bitcoin keeps track of this stack as a const_iterator (for ref):
 /// https://github.com/bitcoin/bitcoin/blob/master/src/script/script.h
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Script {
     stack: Vec<StackEntry>
 }
 
-/// Stack of operations to perform in a script
+/// Stack of operations to perform in a script. Pushes keep the opcode they
+/// were written with, so that a parsed script serializes back to exactly
+/// the same bytes even when the push was not minimally encoded.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
-enum StackEntry {
+pub enum StackEntry {
     Op(OpCodes),
-    Hash(Vec<u8>)
+    Push(u8, Vec<u8>),  // push opcode (0x01-0x4e) and the data it pushes
+    Unknown(u8)         // byte that is not (yet) an assigned opcode
+}
+
+/// Iterator over the raw instructions of a script, yielding each opcode
+/// byte along with its push data, if it is a push. OP_0 counts as a push
+/// of empty data. Stops after the first error.
+#[derive(Clone, Debug)]
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    failed: bool
 }
 
 
 /*---- IMPLEMENTATIONS ----*/
 
+impl Default for Script {
+    fn default() -> Script {
+        Script::new()
+    }
+}
+
 impl Script {
 
     /// Returns a new script as a stack to be processed
@@ -56,26 +77,216 @@ impl Script {
         }
     }
 
-    /// Check whether this is pay to script hash
+    /// Parses a script from its raw bytes. Fails on truncated pushes,
+    /// scripts over MAX_SCRIPT_SIZE and pushes over MAX_SCRIPT_ELEMENT_SIZE.
+    ///
+    /// ### Arguments
+    ///
+    /// * `bytes`   - Raw script bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Script, ScriptError> {
+        if bytes.len() > MAX_SCRIPT_SIZE {
+            return Err(ScriptError::ScriptSize);
+        }
+
+        Script::from_tapscript_bytes(bytes)
+    }
+
+    /// Parses a tapscript from its raw bytes. Tapscripts have no size limit
+    /// of their own (BIP342), so only truncated pushes and pushes over
+    /// MAX_SCRIPT_ELEMENT_SIZE fail.
+    ///
+    /// ### Arguments
+    ///
+    /// * `bytes`   - Raw script bytes
+    pub fn from_tapscript_bytes(bytes: &[u8]) -> Result<Script, ScriptError> {
+        let mut script = Script::new();
+
+        for instruction in Instructions::new(bytes) {
+            let (op_code, data) = instruction?;
+
+            let entry = match (data, OpCodes::from_u8(op_code)) {
+                (Some(data), _) if op_code != OpCodes::OP_0 as u8 => {
+                    if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                        return Err(ScriptError::PushSize);
+                    }
+
+                    StackEntry::Push(op_code, data.to_vec())
+                },
+                (_, Some(op)) => StackEntry::Op(op),
+                (_, None) => StackEntry::Unknown(op_code)
+            };
+
+            script.stack.push(entry);
+        }
+
+        Ok(script)
+    }
+
+    /// Serializes the script back into raw bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for entry in &self.stack {
+            match entry {
+                StackEntry::Op(op) => bytes.push(*op as u8),
+                StackEntry::Unknown(op_code) => bytes.push(*op_code),
+                StackEntry::Push(op_code, data) => {
+                    bytes.push(*op_code);
+
+                    match *op_code {
+                        0x4c => bytes.push(data.len() as u8),
+                        0x4d => bytes.extend_from_slice(&(data.len() as u16).to_le_bytes()),
+                        0x4e => bytes.extend_from_slice(&(data.len() as u32).to_le_bytes()),
+                        _ => {}
+                    }
+
+                    bytes.extend_from_slice(data);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Writes the script as assembly text. Operations are written by name,
+    /// pushed data as `<hex>`, and unassigned opcodes as `0x` bytes. Pushes
+    /// that don't use the smallest push opcode keep it, as in
+    /// `OP_PUSHDATA1 <hex>`, so `from_asm` gives back exactly the same script.
+    pub fn to_asm(&self) -> String {
+        let tokens: Vec<String> = self.stack.iter().map(|entry| entry.to_asm()).collect();
+
+        tokens.join(" ")
+    }
+
+    /// Reads a script from assembly text, as written by `to_asm`. Opcodes can
+    /// also be given as `0x` bytes, and OP_FALSE, OP_TRUE, OP_NOP2, OP_NOP3
+    /// and OP_NOP4 are accepted as aliases.
+    ///
+    /// ### Arguments
+    ///
+    /// * `asm`     - Assembly text, with tokens separated by whitespace
+    pub fn from_asm(asm: &str) -> Result<Script, AsmError> {
+        let mut script = Script::new();
+        let mut tokens = asm.split_whitespace();
+
+        while let Some(token) = tokens.next() {
+            if token.starts_with('<') {
+                let data = parse_push_data(token)?;
+                script.push_data(&data);
+                continue;
+            }
+
+            let op_code = parse_op_code(token)?;
+
+            match op_code {
+                0x4c..=0x4e => {
+                    let data = match tokens.next() {
+                        Some(next) if next.starts_with('<') => parse_push_data(next)?,
+                        _ => return Err(AsmError::MissingPushData(token.to_string()))
+                    };
+
+                    if (op_code == OpCodes::OP_PUSHDATA1 as u8 && data.len() > 0xff) ||
+                       (op_code == OpCodes::OP_PUSHDATA2 as u8 && data.len() > 0xffff)
+                    {
+                        return Err(AsmError::InvalidPushData(token.to_string()));
+                    }
+
+                    script.stack.push(StackEntry::Push(op_code, data));
+                },
+                0x01..=0x4b => return Err(AsmError::MissingPushData(token.to_string())),
+                _ => match OpCodes::from_u8(op_code) {
+                    Some(op) => script.stack.push(StackEntry::Op(op)),
+                    None => script.stack.push(StackEntry::Unknown(op_code))
+                }
+            }
+        }
+
+        // Hold written scripts to the same limits as parsed ones
+        Ok(Script::from_bytes(&script.to_bytes())?)
+    }
+
+    /// Appends an operation to the script
+    ///
+    /// ### Arguments
+    ///
+    /// * `op`      - Operation to append
+    pub fn push_op(&mut self, op: OpCodes) -> &mut Script {
+        self.stack.push(StackEntry::Op(op));
+        self
+    }
+
+    /// Appends a push of the given data, using the smallest push opcode
+    /// that fits it
+    ///
+    /// ### Arguments
+    ///
+    /// * `data`    - Data to push
+    pub fn push_data(&mut self, data: &[u8]) -> &mut Script {
+        let op_code = match data.len() {
+            0 => return self.push_op(OpCodes::OP_0),
+            1..=0x4b => data.len() as u8,
+            0x4c..=0xff => OpCodes::OP_PUSHDATA1 as u8,
+            0x100..=0xffff => OpCodes::OP_PUSHDATA2 as u8,
+            _ => OpCodes::OP_PUSHDATA4 as u8
+        };
+
+        self.stack.push(StackEntry::Push(op_code, data.to_vec()));
+        self
+    }
+
+    /// Entries that make up the script, in order
+    pub fn get_entries(&self) -> &[StackEntry] {
+        &self.stack
+    }
+
+    /// Check whether this is pay to script hash: `OP_HASH160 <20 bytes> OP_EQUAL`.
+    /// The hash has to be a direct push, so that this matches exactly the
+    /// 23 byte scripts `is_p2sh_bytes` accepts.
     pub fn is_p2sh(&self) -> bool {
-        return self.stack.len() == 23 &&
-               self.stack[0] == StackEntry::Op(OpCodes::OP_HASH160) &&
-               self.stack[1].value_at_index_eq(0, 0x14) &&
-               self.stack[2] == StackEntry::Op(OpCodes::OP_EQUAL); 
+        if self.stack.len() != 3 {
+            return false;
+        }
+
+        let is_hash_push = match self.stack[1] {
+            StackEntry::Push(op_code, ref data) => op_code == 0x14 && data.len() == 20,
+            _ => false
+        };
+
+        self.stack[0] == StackEntry::Op(OpCodes::OP_HASH160) &&
+        is_hash_push &&
+        self.stack[2] == StackEntry::Op(OpCodes::OP_EQUAL)
+    }
+
+    /// Returns the version and program if this is a witness program: a
+    /// version opcode (OP_0 to OP_16) followed by a single direct push of
+    /// 2 to 40 bytes (BIP141)
+    pub fn get_witness_program(&self) -> Option<(u8, &[u8])> {
+        if self.stack.len() != 2 {
+            return None;
+        }
+
+        let version = match self.stack[0] {
+            StackEntry::Op(OpCodes::OP_0) => 0,
+            StackEntry::Op(op) if op >= OpCodes::OP_1 && op <= OpCodes::OP_16 => op as u8 - (OpCodes::OP_1 as u8 - 1),
+            _ => return None
+        };
+
+        match self.stack[1] {
+            StackEntry::Push(op_code, ref program) if op_code as usize == program.len() && program.len() >= 2 && program.len() <= 40 => {
+                Some((version, program))
+            },
+            _ => None
+        }
     }
 
     /// Whether a script consists purely of push-type opcodes
     pub fn is_push_only(&self) -> bool {
         for entry in &self.stack {
-            if entry.is_a_hash() {
-                return false;
-            }
-
             // Note that IsPushOnly() *does* consider OP_RESERVED to be a
             // push-type opcode, however execution of OP_RESERVED fails, so
             // it's not relevant to P2SH/BIP62 as the scriptSig would fail prior to
             // the P2SH special validation code being executed.
-            if entry > &StackEntry::Op(OpCodes::OP_16) {
+            if entry.get_op_code() > OpCodes::OP_16 as u8 {
                 return false;
             }
         }
@@ -83,33 +294,90 @@ impl Script {
         true
     }
 
-    /// Gets the op_code for a specific data entry, if it exists
+    /// Gets the op_code for the entry at the given index, if it exists
     /// 
     /// ### Arguments
     /// 
-    /// * `entry` - Entry to get the op_code for
-    pub fn get_op_code(&self, entry: &u8) -> Option<u8> {
-        let mut op_code = OpCodes::OP_INVALIDOPCODE as u8;
+    /// * `index` - Index of the entry to get the op_code for
+    pub fn get_op_code(&self, index: usize) -> Option<u8> {
+        self.stack.get(index).map(|entry| entry.get_op_code())
+    }
+
+    /// Returns the part of the script from the given entry onwards
+    ///
+    /// ### Arguments
+    ///
+    /// * `start`   - Index of the first entry to keep
+    pub fn get_subscript(&self, start: usize) -> Script {
+        Script {
+            stack: self.stack[start.min(self.stack.len())..].to_vec()
+        }
+    }
 
-        Some(op_code)
+    /// Removes every minimal push of the given data from the script,
+    /// returning how many were removed (FindAndDelete)
+    ///
+    /// ### Arguments
+    ///
+    /// * `data`    - Pushed data to remove
+    pub fn find_and_delete(&mut self, data: &[u8]) -> usize {
+        let mut push = Script::new();
+        push.push_data(data);
+
+        let target = push.stack[0].clone();
+        let before = self.stack.len();
+        self.stack.retain(|entry| *entry != target);
+
+        before - self.stack.len()
+    }
+
+    /// Removes every occurrence of an operation from the script
+    ///
+    /// ### Arguments
+    ///
+    /// * `op`      - Operation to remove
+    pub fn remove_op(&mut self, op: OpCodes) {
+        self.stack.retain(|entry| *entry != StackEntry::Op(op));
     }
 
 }
 
 impl StackEntry {
-    /// Checks whether the current stack entry is a hash
-    pub fn is_a_hash(&self) -> bool {
-        match self {
-            StackEntry::Hash(_) => true,
-            _ => false
-        }
+    /// Checks whether the current stack entry is a data push
+    pub fn is_a_push(&self) -> bool {
+        matches!(self, StackEntry::Push(_, _))
     }
 
     /// Checks whether the current stack entry is an operation
     pub fn is_an_op(&self) -> bool {
+        matches!(self, StackEntry::Op(_))
+    }
+
+    /// The opcode byte this entry is written as
+    pub fn get_op_code(&self) -> u8 {
         match self {
-            StackEntry::Op(_) => true,
-            _ => false
+            StackEntry::Op(op) => *op as u8,
+            StackEntry::Push(op_code, _) => *op_code,
+            StackEntry::Unknown(op_code) => *op_code
+        }
+    }
+
+    /// Writes the entry as assembly text, in the same form as `Script::to_asm`
+    pub fn to_asm(&self) -> String {
+        match self {
+            StackEntry::Op(op) => op.to_string(),
+            StackEntry::Unknown(op_code) => format!("0x{:02x}", op_code),
+            StackEntry::Push(op_code, data) => {
+                let mut minimal = Script::new();
+                minimal.push_data(data);
+
+                if minimal.stack[0] == *self {
+                    format!("<{}>", to_hex(data))
+                } else {
+                    let name = OpCodes::from_u8(*op_code).map_or_else(|| format!("0x{:02x}", op_code), |op| op.to_string());
+                    format!("{} <{}>", name, to_hex(data))
+                }
+            }
         }
     }
 
@@ -121,17 +389,157 @@ impl StackEntry {
     /// * `value`   - Value to check for
     pub fn value_at_index_eq(&self, index: usize, value: u8) -> bool {
         match self {
-            StackEntry::Hash(v) => v[index] == value,
+            StackEntry::Push(_, v) => v.get(index) == Some(&value),
             _ => false
         }
     }
 }
 
+impl<'a> Instructions<'a> {
+
+    /// Returns an iterator over the instructions in the raw script
+    ///
+    /// ### Arguments
+    ///
+    /// * `bytes`   - Raw script bytes
+    pub fn new(bytes: &'a [u8]) -> Instructions<'a> {
+        Instructions {
+            bytes,
+            position: 0,
+            failed: false
+        }
+    }
+
+    /// Byte offset of the next instruction
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Consumes `count` bytes, or fails if the script ends first
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ScriptError> {
+        if count > self.bytes.len() - self.position {
+            return Err(ScriptError::BadOpcode);
+        }
+
+        let taken = &self.bytes[self.position..self.position + count];
+        self.position += count;
+
+        Ok(taken)
+    }
+
+    /// Reads the instruction at the current position
+    fn read_instruction(&mut self) -> Result<(u8, Option<&'a [u8]>), ScriptError> {
+        let op_code = self.take(1)?[0];
+
+        let length = match op_code {
+            0x00..=0x4b => op_code as usize,
+            0x4c => self.take(1)?[0] as usize,
+            0x4d => {
+                let length = self.take(2)?;
+                u16::from_le_bytes([length[0], length[1]]) as usize
+            },
+            0x4e => {
+                let length = self.take(4)?;
+                u32::from_le_bytes([length[0], length[1], length[2], length[3]]) as usize
+            },
+            _ => return Ok((op_code, None))
+        };
+
+        Ok((op_code, Some(self.take(length)?)))
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<(u8, Option<&'a [u8]>), ScriptError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position >= self.bytes.len() {
+            return None;
+        }
+
+        let instruction = self.read_instruction();
+        self.failed = instruction.is_err();
+
+        Some(instruction)
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Parses an opcode token from assembly text, either by name or as a 0x byte
+///
+/// ### Arguments
+///
+/// * `token`   - Opcode token
+fn parse_op_code(token: &str) -> Result<u8, AsmError> {
+    if token.starts_with("0x") && token.len() == 4 {
+        return match from_hex(&token[2..]) {
+            Ok(byte) => Ok(byte[0]),
+            Err(_) => Err(AsmError::UnknownOpcode(token.to_string()))
+        };
+    }
+
+    let alias = match token {
+        "OP_FALSE" => Some(OpCodes::OP_0),
+        "OP_TRUE" => Some(OpCodes::OP_1),
+        "OP_NOP2" => Some(OpCodes::OP_NOP2),
+        "OP_NOP3" => Some(OpCodes::OP_NOP3),
+        "OP_NOP4" => Some(OpCodes::OP_NOP4),
+        _ => None
+    };
+
+    if let Some(op) = alias {
+        return Ok(op as u8);
+    }
+
+    (0..=255u8)
+        .find(|byte| OpCodes::from_u8(*byte).is_some_and(|op| op.to_string() == token))
+        .ok_or_else(|| AsmError::UnknownOpcode(token.to_string()))
+}
+
+/// Parses a `<hex>` push data token from assembly text
+///
+/// ### Arguments
+///
+/// * `token`   - Push data token, including the angle brackets
+fn parse_push_data(token: &str) -> Result<Vec<u8>, AsmError> {
+    if !token.ends_with('>') || token.len() < 2 {
+        return Err(AsmError::InvalidPushData(token.to_string()));
+    }
+
+    from_hex(&token[1..token.len() - 1]).map_err(|_| AsmError::InvalidPushData(token.to_string()))
+}
+
+/// Check whether raw script bytes are pay to script hash. Extra-fast test
+/// for pay-to-script-hash scripts, without parsing.
+///
+/// ### Arguments
+///
+/// * `bytes`   - Raw script bytes
+pub fn is_p2sh_bytes(bytes: &[u8]) -> bool {
+    bytes.len() == 23 &&
+    bytes[0] == OpCodes::OP_HASH160 as u8 &&
+    bytes[1] == 0x14 &&
+    bytes[22] == OpCodes::OP_EQUAL as u8
+}
+
+/// Check whether an opcode is one of the OP_SUCCESSx opcodes, which make a
+/// tapscript succeed outright so they can be given meaning in soft forks (BIP342)
+///
+/// ### Arguments
+///
+/// * `op_code` - Opcode byte to check
+pub fn is_op_success(op_code: u8) -> bool {
+    matches!(op_code, 80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254)
+}
+
 
 /*---- OP CODES ----*/
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
-enum OpCodes {
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OpCodes {
 
     // push value
     OP_0 = 0x00,
@@ -240,6 +648,8 @@ enum OpCodes {
     OP_WITHIN = 0xa5,
  
     // crypto
+    OP_RIPEMD160 = 0xa6,
+    OP_SHA1 = 0xa7,
     OP_SHA256 = 0xa8,
     OP_HASH160 = 0xa9,
     OP_HASH256 = 0xaa,
@@ -253,13 +663,16 @@ enum OpCodes {
     OP_NOP1 = 0xb0,
     OP_CHECKLOCKTIMEVERIFY = 0xb1,
     OP_CHECKSEQUENCEVERIFY = 0xb2,
-    OP_NOP4 = 0xb3,
+    OP_CHECKTEMPLATEVERIFY = 0xb3,
     OP_NOP5 = 0xb4,
     OP_NOP6 = 0xb5,
     OP_NOP7 = 0xb6,
     OP_NOP8 = 0xb7,
     OP_NOP9 = 0xb8,
     OP_NOP10 = 0xb9,
+
+    // Opcode added by BIP 342 (Tapscript)
+    OP_CHECKSIGADD = 0xba,
  
     OP_INVALIDOPCODE = 0xff
 
@@ -268,6 +681,132 @@ enum OpCodes {
 impl OpCodes {
     pub const OP_NOP2: OpCodes = OpCodes::OP_CHECKLOCKTIMEVERIFY;
     pub const OP_NOP3: OpCodes = OpCodes::OP_CHECKSEQUENCEVERIFY;
+    pub const OP_NOP4: OpCodes = OpCodes::OP_CHECKTEMPLATEVERIFY;
+
+    /// Looks up the named opcode for a script byte. Direct push lengths
+    /// (0x01-0x4b) and unassigned bytes have no name and return None.
+    ///
+    /// ### Arguments
+    ///
+    /// * `byte`    - Opcode byte
+    pub fn from_u8(byte: u8) -> Option<OpCodes> {
+        match byte {
+            0x00 => Some(OpCodes::OP_0),
+            0x4c => Some(OpCodes::OP_PUSHDATA1),
+            0x4d => Some(OpCodes::OP_PUSHDATA2),
+            0x4e => Some(OpCodes::OP_PUSHDATA4),
+            0x4f => Some(OpCodes::OP_1NEGATE),
+            0x50 => Some(OpCodes::OP_RESERVED),
+            0x51 => Some(OpCodes::OP_1),
+            0x52 => Some(OpCodes::OP_2),
+            0x53 => Some(OpCodes::OP_3),
+            0x54 => Some(OpCodes::OP_4),
+            0x55 => Some(OpCodes::OP_5),
+            0x56 => Some(OpCodes::OP_6),
+            0x57 => Some(OpCodes::OP_7),
+            0x58 => Some(OpCodes::OP_8),
+            0x59 => Some(OpCodes::OP_9),
+            0x5a => Some(OpCodes::OP_10),
+            0x5b => Some(OpCodes::OP_11),
+            0x5c => Some(OpCodes::OP_12),
+            0x5d => Some(OpCodes::OP_13),
+            0x5e => Some(OpCodes::OP_14),
+            0x5f => Some(OpCodes::OP_15),
+            0x60 => Some(OpCodes::OP_16),
+            0x61 => Some(OpCodes::OP_NOP),
+            0x62 => Some(OpCodes::OP_VER),
+            0x63 => Some(OpCodes::OP_IF),
+            0x64 => Some(OpCodes::OP_NOTIF),
+            0x65 => Some(OpCodes::OP_VERIF),
+            0x66 => Some(OpCodes::OP_VERNOTIF),
+            0x67 => Some(OpCodes::OP_ELSE),
+            0x68 => Some(OpCodes::OP_ENDIF),
+            0x69 => Some(OpCodes::OP_VERIFY),
+            0x6a => Some(OpCodes::OP_RETURN),
+            0x6b => Some(OpCodes::OP_TOALTSTACK),
+            0x6c => Some(OpCodes::OP_FROMALTSTACK),
+            0x6d => Some(OpCodes::OP_2DROP),
+            0x6e => Some(OpCodes::OP_2DUP),
+            0x6f => Some(OpCodes::OP_3DUP),
+            0x70 => Some(OpCodes::OP_2OVER),
+            0x71 => Some(OpCodes::OP_2ROT),
+            0x72 => Some(OpCodes::OP_2SWAP),
+            0x73 => Some(OpCodes::OP_IFDUP),
+            0x74 => Some(OpCodes::OP_DEPTH),
+            0x75 => Some(OpCodes::OP_DROP),
+            0x76 => Some(OpCodes::OP_DUP),
+            0x77 => Some(OpCodes::OP_NIP),
+            0x78 => Some(OpCodes::OP_OVER),
+            0x79 => Some(OpCodes::OP_PICK),
+            0x7a => Some(OpCodes::OP_ROLL),
+            0x7b => Some(OpCodes::OP_ROT),
+            0x7c => Some(OpCodes::OP_SWAP),
+            0x7d => Some(OpCodes::OP_TUCK),
+            0x7e => Some(OpCodes::OP_CAT),
+            0x7f => Some(OpCodes::OP_SUBSTR),
+            0x80 => Some(OpCodes::OP_LEFT),
+            0x81 => Some(OpCodes::OP_RIGHT),
+            0x82 => Some(OpCodes::OP_SIZE),
+            0x83 => Some(OpCodes::OP_INVERT),
+            0x84 => Some(OpCodes::OP_AND),
+            0x85 => Some(OpCodes::OP_OR),
+            0x86 => Some(OpCodes::OP_XOR),
+            0x87 => Some(OpCodes::OP_EQUAL),
+            0x88 => Some(OpCodes::OP_EQUALVERIFY),
+            0x89 => Some(OpCodes::OP_RESERVED1),
+            0x8a => Some(OpCodes::OP_RESERVED2),
+            0x8b => Some(OpCodes::OP_1ADD),
+            0x8c => Some(OpCodes::OP_1SUB),
+            0x8d => Some(OpCodes::OP_2MUL),
+            0x8e => Some(OpCodes::OP_2DIV),
+            0x8f => Some(OpCodes::OP_NEGATE),
+            0x90 => Some(OpCodes::OP_ABS),
+            0x91 => Some(OpCodes::OP_NOT),
+            0x92 => Some(OpCodes::OP_0NOTEQUAL),
+            0x93 => Some(OpCodes::OP_ADD),
+            0x94 => Some(OpCodes::OP_SUB),
+            0x95 => Some(OpCodes::OP_MUL),
+            0x96 => Some(OpCodes::OP_DIV),
+            0x97 => Some(OpCodes::OP_MOD),
+            0x98 => Some(OpCodes::OP_LSHIFT),
+            0x99 => Some(OpCodes::OP_RSHIFT),
+            0x9a => Some(OpCodes::OP_BOOLAND),
+            0x9b => Some(OpCodes::OP_BOOLOR),
+            0x9c => Some(OpCodes::OP_NUMEQUAL),
+            0x9d => Some(OpCodes::OP_NUMEQUALVERIFY),
+            0x9e => Some(OpCodes::OP_NUMNOTEQUAL),
+            0x9f => Some(OpCodes::OP_LESSTHAN),
+            0xa0 => Some(OpCodes::OP_GREATERTHAN),
+            0xa1 => Some(OpCodes::OP_LESSTHANOREQUAL),
+            0xa2 => Some(OpCodes::OP_GREATERTHANOREQUAL),
+            0xa3 => Some(OpCodes::OP_MIN),
+            0xa4 => Some(OpCodes::OP_MAX),
+            0xa5 => Some(OpCodes::OP_WITHIN),
+            0xa6 => Some(OpCodes::OP_RIPEMD160),
+            0xa7 => Some(OpCodes::OP_SHA1),
+            0xa8 => Some(OpCodes::OP_SHA256),
+            0xa9 => Some(OpCodes::OP_HASH160),
+            0xaa => Some(OpCodes::OP_HASH256),
+            0xab => Some(OpCodes::OP_CODESEPARATOR),
+            0xac => Some(OpCodes::OP_CHECKSIG),
+            0xad => Some(OpCodes::OP_CHECKSIGVERIFY),
+            0xae => Some(OpCodes::OP_CHECKMULTISIG),
+            0xaf => Some(OpCodes::OP_CHECKMULTISIGVERIFY),
+            0xb0 => Some(OpCodes::OP_NOP1),
+            0xb1 => Some(OpCodes::OP_CHECKLOCKTIMEVERIFY),
+            0xb2 => Some(OpCodes::OP_CHECKSEQUENCEVERIFY),
+            0xb3 => Some(OpCodes::OP_CHECKTEMPLATEVERIFY),
+            0xb4 => Some(OpCodes::OP_NOP5),
+            0xb5 => Some(OpCodes::OP_NOP6),
+            0xb6 => Some(OpCodes::OP_NOP7),
+            0xb7 => Some(OpCodes::OP_NOP8),
+            0xb8 => Some(OpCodes::OP_NOP9),
+            0xb9 => Some(OpCodes::OP_NOP10),
+            0xba => Some(OpCodes::OP_CHECKSIGADD),
+            0xff => Some(OpCodes::OP_INVALIDOPCODE),
+            _ => None
+        }
+    }
 }
 
 // Allows for string casting
@@ -276,3 +815,206 @@ impl fmt::Display for OpCodes {
         write!(f, "{:?}", self)
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> Script {
+        let script = Script::from_bytes(bytes).unwrap();
+        assert_eq!(script.to_bytes(), bytes);
+
+        script
+    }
+
+    #[test]
+    fn push_opcodes_round_trip() {
+        // Direct push, then OP_PUSHDATA1/2/4 with little-endian lengths
+        let script = round_trip(&[0x02, 0xaa, 0xbb, 0x4c, 0x01, 0xcc, 0x4d, 0x02, 0x00, 0xdd, 0xee, 0x4e, 0x01, 0x00, 0x00, 0x00, 0xff]);
+
+        assert_eq!(script.get_entries(), &[
+            StackEntry::Push(0x02, vec![0xaa, 0xbb]),
+            StackEntry::Push(0x4c, vec![0xcc]),
+            StackEntry::Push(0x4d, vec![0xdd, 0xee]),
+            StackEntry::Push(0x4e, vec![0xff])
+        ]);
+
+        // Empty pushes through each push opcode are kept as written
+        let script = round_trip(&[0x00, 0x4c, 0x00, 0x4d, 0x00, 0x00, 0x4e, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(script.get_entries()[0], StackEntry::Op(OpCodes::OP_0));
+        assert_eq!(script.get_entries()[1], StackEntry::Push(0x4c, Vec::new()));
+    }
+
+    #[test]
+    fn ops_and_unknown_bytes_round_trip() {
+        let script = round_trip(&[0x76, 0xa9, 0xbb, 0xff, 0x51]);
+
+        assert_eq!(script.get_entries(), &[
+            StackEntry::Op(OpCodes::OP_DUP),
+            StackEntry::Op(OpCodes::OP_HASH160),
+            StackEntry::Unknown(0xbb),
+            StackEntry::Op(OpCodes::OP_INVALIDOPCODE),
+            StackEntry::Op(OpCodes::OP_1)
+        ]);
+
+        round_trip(&[]);
+    }
+
+    #[test]
+    fn push_data_picks_the_smallest_opcode() {
+        let cases: Vec<(usize, Vec<u8>)> = vec![
+            (1, vec![0x01]),
+            (75, vec![0x4b]),
+            (76, vec![0x4c, 76]),
+            (255, vec![0x4c, 0xff]),
+            (256, vec![0x4d, 0x00, 0x01]),
+            (520, vec![0x4d, 0x08, 0x02])
+        ];
+
+        for (len, prefix) in cases {
+            let data = vec![0x42; len];
+            let mut script = Script::new();
+            script.push_data(&data);
+
+            let bytes = script.to_bytes();
+            assert_eq!(&bytes[..prefix.len()], &prefix[..], "push of {} bytes", len);
+            assert_eq!(Script::from_bytes(&bytes), Ok(script));
+        }
+
+        let mut script = Script::new();
+        script.push_data(&[]);
+        assert_eq!(script.to_bytes(), vec![0x00]);
+
+        let mut script = Script::new();
+        script.push_data(&vec![0; 0x10000]);
+        assert_eq!(&script.to_bytes()[..5], &[0x4e, 0x00, 0x00, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn truncated_pushes_are_rejected() {
+        let truncated: [&[u8]; 8] = [
+            &[0x01],
+            &[0x02, 0xaa],
+            &[0x4c],
+            &[0x4c, 0x02, 0xaa],
+            &[0x4d, 0x01],
+            &[0x4d, 0x01, 0x00],
+            &[0x4e, 0x01, 0x00, 0x00],
+            &[0x4e, 0xff, 0xff, 0xff, 0xff, 0x00]
+        ];
+
+        for bytes in truncated.iter() {
+            assert_eq!(Script::from_bytes(bytes), Err(ScriptError::BadOpcode), "{:?}", bytes);
+        }
+
+        // The instruction iterator stops after the first error
+        let mut instructions = Instructions::new(&[0x51, 0x02, 0xaa]);
+        assert_eq!(instructions.next(), Some(Ok((0x51, None))));
+        assert_eq!(instructions.next(), Some(Err(ScriptError::BadOpcode)));
+        assert_eq!(instructions.next(), None);
+    }
+
+    #[test]
+    fn size_limits() {
+        let mut big_push = vec![0x4d, 0x09, 0x02];
+        big_push.extend_from_slice(&[0; MAX_SCRIPT_ELEMENT_SIZE + 1]);
+        assert_eq!(Script::from_bytes(&big_push), Err(ScriptError::PushSize));
+
+        assert!(Script::from_bytes(&[0x61; MAX_SCRIPT_SIZE]).is_ok());
+        assert_eq!(Script::from_bytes(&[0x61; MAX_SCRIPT_SIZE + 1]), Err(ScriptError::ScriptSize));
+        assert!(Script::from_tapscript_bytes(&[0x61; MAX_SCRIPT_SIZE + 1]).is_ok());
+    }
+
+    #[test]
+    fn asm_round_trips() {
+        let asm = "OP_DUP OP_HASH160 <1111111111111111111111111111111111111111> OP_EQUALVERIFY OP_CHECKSIG";
+        let script = Script::from_asm(asm).unwrap();
+
+        assert_eq!(script.to_bytes()[..3], [0x76, 0xa9, 0x14]);
+        assert_eq!(script.to_asm(), asm);
+
+        // Non-minimal pushes and unassigned opcodes survive the round trip
+        let bytes = [0x4c, 0x01, 0xaa, 0x4d, 0x00, 0x00, 0xbb, 0x00, 0x51];
+        let script = Script::from_bytes(&bytes).unwrap();
+        let asm = script.to_asm();
+
+        assert_eq!(asm, "OP_PUSHDATA1 <aa> OP_PUSHDATA2 <> 0xbb OP_0 OP_1");
+        assert_eq!(Script::from_asm(&asm).map(|s| s.to_bytes()), Ok(bytes.to_vec()));
+    }
+
+    #[test]
+    fn asm_aliases_and_raw_bytes() {
+        let aliased = Script::from_asm("OP_FALSE OP_TRUE OP_NOP2 OP_NOP3 OP_NOP4 0x61 <>").unwrap();
+        let expected = Script::from_asm("OP_0 OP_1 OP_CHECKLOCKTIMEVERIFY OP_CHECKSEQUENCEVERIFY OP_NOP4 OP_NOP OP_0").unwrap();
+
+        assert_eq!(aliased.to_bytes(), expected.to_bytes());
+        assert_eq!(aliased.to_bytes(), vec![0x00, 0x51, 0xb1, 0xb2, 0xb3, 0x61, 0x00]);
+        assert_eq!(Script::from_asm(""), Ok(Script::new()));
+    }
+
+    #[test]
+    fn malformed_asm_is_rejected() {
+        let unknown = |token: &str| Err(AsmError::UnknownOpcode(token.to_string()));
+        let invalid = |token: &str| Err(AsmError::InvalidPushData(token.to_string()));
+        let missing = |token: &str| Err(AsmError::MissingPushData(token.to_string()));
+
+        assert_eq!(Script::from_asm("OP_FOO"), unknown("OP_FOO"));
+        assert_eq!(Script::from_asm("0xzz"), unknown("0xzz"));
+        assert_eq!(Script::from_asm("0x123"), unknown("0x123"));
+        assert_eq!(Script::from_asm("<abc>"), invalid("<abc>"));
+        assert_eq!(Script::from_asm("<aa"), invalid("<aa"));
+        assert_eq!(Script::from_asm("OP_PUSHDATA1"), missing("OP_PUSHDATA1"));
+        assert_eq!(Script::from_asm("OP_PUSHDATA2 OP_1"), missing("OP_PUSHDATA2"));
+        assert_eq!(Script::from_asm("0x02"), missing("0x02"));
+
+        let too_long = format!("OP_PUSHDATA1 <{}>", "00".repeat(256));
+        assert_eq!(Script::from_asm(&too_long), invalid("OP_PUSHDATA1"));
+
+        // Written scripts are held to the same limits as parsed ones
+        let oversized_push = format!("<{}>", "00".repeat(MAX_SCRIPT_ELEMENT_SIZE + 1));
+        assert_eq!(Script::from_asm(&oversized_push), Err(AsmError::Script(ScriptError::PushSize)));
+    }
+
+    #[test]
+    fn find_and_delete_removes_minimal_pushes() {
+        let mut script = Script::from_asm("OP_1 <aabb> OP_2 <aabb> OP_3").unwrap();
+        assert_eq!(script.find_and_delete(&[0xaa, 0xbb]), 2);
+        assert_eq!(script.to_asm(), "OP_1 OP_2 OP_3");
+
+        assert_eq!(script.find_and_delete(&[0xaa, 0xbb]), 0);
+        assert_eq!(script.to_asm(), "OP_1 OP_2 OP_3");
+
+        // Only the exact push FindAndDelete would serialize is matched, so
+        // neither a non-minimal push nor the small number opcode are removed
+        let mut script = Script::from_asm("OP_PUSHDATA1 <aabb> OP_5 <05>").unwrap();
+        assert_eq!(script.find_and_delete(&[0xaa, 0xbb]), 0);
+        assert_eq!(script.find_and_delete(&[0x05]), 1);
+        assert_eq!(script.to_asm(), "OP_PUSHDATA1 <aabb> OP_5");
+
+        // Empty data is pushed with OP_0
+        let mut script = Script::from_asm("OP_0 OP_1 OP_0").unwrap();
+        assert_eq!(script.find_and_delete(&[]), 2);
+        assert_eq!(script.to_asm(), "OP_1");
+    }
+
+    #[test]
+    fn p2sh_entries_and_bytes_agree() {
+        let hash = "11".repeat(20);
+        let scripts = vec![
+            (format!("OP_HASH160 <{}> OP_EQUAL", hash), true),
+            (format!("OP_HASH160 OP_PUSHDATA1 <{}> OP_EQUAL", hash), false),
+            (format!("OP_HASH160 <{}> OP_EQUAL", "11".repeat(21)), false),
+            (format!("OP_HASH160 <{}> OP_EQUALVERIFY", hash), false),
+            (format!("OP_HASH160 <{}> OP_EQUAL OP_NOP", hash), false),
+            (format!("OP_SHA256 <{}> OP_EQUAL", hash), false)
+        ];
+
+        for (asm, expected) in scripts {
+            let script = Script::from_asm(&asm).unwrap();
+
+            assert_eq!(script.is_p2sh(), expected, "{}", asm);
+            assert_eq!(is_p2sh_bytes(&script.to_bytes()), expected, "{}", asm);
+        }
+    }
+}
@@ -0,0 +1,754 @@
+/*
+ * Signature hashes. A signature doesn't sign the spending transaction
+ * directly, but a hash of a modified copy of it. The sighash type appended to
+ * each signature picks which inputs and outputs are committed to, so that
+ * other parties can still add to the transaction after it is signed.
+ *
+ * The template hashes that OP_CHECKTEMPLATEVERIFY commits to are built the
+ * same way, from a fixed selection of the spending transaction's fields.
+ */
+
+use utils::amount::Amount;
+use utils::hash256::Hash256;
+use cryptography::hash::{ double_hash, sha256, tagged_hash };
+use primitives::transaction::{ Transaction, TxIn, TxOut, OutPoint };
+use script_lang::script::{ Script, OpCodes };
+use serialize::{ Encodable, serialize, write_var_bytes };
+
+
+/*---- CONSTANTS ----*/
+
+/// Sign all inputs and outputs, for taproot signatures without a sighash
+/// type byte (BIP341)
+pub const SIGHASH_DEFAULT: u32 = 0;
+
+/// Sign all inputs and outputs
+pub const SIGHASH_ALL: u32 = 1;
+
+/// Sign all inputs but no outputs, so anyone can choose where the funds go
+pub const SIGHASH_NONE: u32 = 2;
+
+/// Sign all inputs and only the output with the same index as the signed input
+pub const SIGHASH_SINGLE: u32 = 3;
+
+/// Modifier to sign only the signed input, so others can add inputs freely
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// Mask for the base type, without the ANYONECANPAY modifier
+const SIGHASH_OUTPUT_MASK: u32 = 0x1f;
+
+/// Mask for the base type of taproot signatures, where undefined types are invalid
+const SIGHASH_TAPROOT_OUTPUT_MASK: u32 = 0x03;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// Which signature hash algorithm, and which script rules, apply to a script
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum SigVersion {
+    Base,       // Bare and P2SH scripts
+    WitnessV0,  // Version 0 witness programs (BIP143)
+    Taproot,    // Key path spends of version 1 witness programs (BIP341)
+    Tapscript   // Script path spends of version 1 witness programs (BIP342)
+}
+
+/// State of a taproot spend that taproot signatures commit to, or that
+/// limits how many signatures a tapscript can check
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScriptExecutionData {
+    pub tapleaf_hash: Option<Vec<u8>>,      // Leaf hash of the executing tapscript
+    pub code_separator_pos: u32,            // Position of the last executed OP_CODESEPARATOR, or 0xffffffff
+    pub annex_hash: Option<Vec<u8>>,        // Hash of the annex, if the witness has one
+    pub validation_weight_left: i64         // Weight budget left for signature checks
+}
+
+/// Parts of the BIP341 signature hash that are shared by every input of a
+/// transaction. Unlike earlier signatures, taproot signatures commit to
+/// every output being spent, so these can't be computed from the
+/// transaction alone.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaprootTransactionData {
+    pub spent_outputs: Vec<TxOut>,
+    pub sha_prevouts: Vec<u8>,
+    pub sha_amounts: Vec<u8>,
+    pub sha_script_pubkeys: Vec<u8>,
+    pub sha_sequences: Vec<u8>,
+    pub sha_outputs: Vec<u8>
+}
+
+/// Parts of the BIP119 standard template hash that are shared by every
+/// input of a transaction
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TemplateTransactionData {
+    pub script_sigs_hash: Option<Vec<u8>>,  // Only committed to if some input has a signature script
+    pub sequences_hash: Vec<u8>,
+    pub outputs_hash: Vec<u8>
+}
+
+/// Parts of the BIP143 signature hash that are shared by every input of a
+/// transaction. Computing these once per transaction, rather than once per
+/// signature, is what keeps witness signature hashing linear.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PrecomputedTransactionData {
+    pub hash_prevouts: Hash256,
+    pub hash_sequence: Hash256,
+    pub hash_outputs: Hash256,
+    pub taproot: Option<TaprootTransactionData>,  // Only known once the spent outputs are given
+    pub template: TemplateTransactionData
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl PrecomputedTransactionData {
+
+    /// Precomputes the shared signature hash parts for a transaction
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`  - Transaction to precompute for
+    pub fn new(tx: &Transaction) -> PrecomputedTransactionData {
+        PrecomputedTransactionData {
+            hash_prevouts: get_prevouts_hash(tx),
+            hash_sequence: get_sequence_hash(tx),
+            hash_outputs: get_outputs_hash(tx),
+            taproot: None,
+            template: TemplateTransactionData::new(tx)
+        }
+    }
+
+    /// Precomputes the shared signature hash parts for a transaction,
+    /// including those needed for taproot signatures. None unless there is
+    /// exactly one spent output per input, since taproot signatures commit
+    /// to all of them.
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`              - Transaction to precompute for
+    /// * `spent_outputs`   - Outputs spent by each input of `tx`, in input order
+    pub fn with_spent_outputs(tx: &Transaction, spent_outputs: &[TxOut]) -> Option<PrecomputedTransactionData> {
+        if spent_outputs.len() != tx.inputs().len() {
+            return None;
+        }
+
+        let mut txdata = PrecomputedTransactionData::new(tx);
+        txdata.taproot = Some(TaprootTransactionData::new(tx, spent_outputs));
+
+        Some(txdata)
+    }
+}
+
+impl ScriptExecutionData {
+
+    /// Execution data for a spend that hasn't run any script yet
+    pub fn new() -> ScriptExecutionData {
+        ScriptExecutionData {
+            tapleaf_hash: None,
+            code_separator_pos: 0xffffffff,
+            annex_hash: None,
+            validation_weight_left: 0
+        }
+    }
+}
+
+impl Default for ScriptExecutionData {
+    fn default() -> ScriptExecutionData {
+        ScriptExecutionData::new()
+    }
+}
+
+impl TemplateTransactionData {
+
+    /// Precomputes the single SHA-256 hashes used by template hashes
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`  - Transaction to precompute for
+    pub fn new(tx: &Transaction) -> TemplateTransactionData {
+        let mut script_sigs = Vec::new();
+        let mut sequences = Vec::new();
+        let mut outputs = Vec::new();
+
+        for input in tx.inputs() {
+            write_var_bytes(&mut script_sigs, &input.script_signature);
+            input.sequence.encode(&mut sequences);
+        }
+
+        for output in tx.outputs() {
+            output.encode(&mut outputs);
+        }
+
+        // Leaving out empty signature scripts saves a hash for the common
+        // case of spending only witness programs
+        let has_script_sigs = tx.inputs().iter().any(|input| !input.script_signature.is_empty());
+
+        TemplateTransactionData {
+            script_sigs_hash: if has_script_sigs { Some(sha256(&script_sigs)) } else { None },
+            sequences_hash: sha256(&sequences),
+            outputs_hash: sha256(&outputs)
+        }
+    }
+}
+
+impl TaprootTransactionData {
+
+    /// Precomputes the single SHA-256 hashes used by taproot signatures
+    ///
+    /// ### Arguments
+    ///
+    /// * `tx`              - Transaction to precompute for
+    /// * `spent_outputs`   - Outputs spent by each input of `tx`, in input order
+    pub fn new(tx: &Transaction, spent_outputs: &[TxOut]) -> TaprootTransactionData {
+        let mut prevouts = Vec::new();
+        let mut sequences = Vec::new();
+        let mut outputs = Vec::new();
+        let mut amounts = Vec::new();
+        let mut script_pubkeys = Vec::new();
+
+        for input in tx.inputs() {
+            encode_previous_out(input, &mut prevouts);
+            input.sequence.encode(&mut sequences);
+        }
+
+        for output in tx.outputs() {
+            output.encode(&mut outputs);
+        }
+
+        for output in spent_outputs {
+            encode_amount(output, &mut amounts);
+            write_var_bytes(&mut script_pubkeys, &output.script_public_key);
+        }
+
+        TaprootTransactionData {
+            spent_outputs: spent_outputs.to_vec(),
+            sha_prevouts: sha256(&prevouts),
+            sha_amounts: sha256(&amounts),
+            sha_script_pubkeys: sha256(&script_pubkeys),
+            sha_sequences: sha256(&sequences),
+            sha_outputs: sha256(&outputs)
+        }
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Signature hash for an input, using the algorithm for the given signature
+/// version. None if a witness input index is out of range.
+///
+/// ### Arguments
+///
+/// * `script_code` - Script code committed to by the signature
+/// * `tx`          - Transaction spending the input
+/// * `input_index` - Index of the input being signed
+/// * `hash_type`   - Sighash type, as appended to the signature
+/// * `amount`      - Value of the output being spent (witness only)
+/// * `sig_version` - Signature version of the script being run
+/// * `cache`       - Precomputed hashes for the transaction, if available (witness only)
+pub fn signature_hash(
+    script_code: &Script,
+    tx: &Transaction,
+    input_index: usize,
+    hash_type: u32,
+    amount: Amount,
+    sig_version: SigVersion,
+    cache: Option<&PrecomputedTransactionData>
+) -> Option<Hash256>
+{
+    match sig_version {
+        SigVersion::WitnessV0 => witness_v0_signature_hash(script_code, tx, input_index, hash_type, amount, cache),
+        _ => Some(legacy_signature_hash(script_code, tx, input_index, hash_type))
+    }
+}
+
+/// Original signature hash algorithm, used for all non-witness scripts.
+///
+/// Inherits two quirks from Bitcoin that are now consensus: an input index
+/// past the end of the inputs, or SIGHASH_SINGLE without a matching output,
+/// "signs" the value one instead of failing.
+///
+/// ### Arguments
+///
+/// * `script_code` - Script being executed, from just after the last executed OP_CODESEPARATOR
+/// * `tx`          - Transaction spending the input
+/// * `input_index` - Index of the input being signed
+/// * `hash_type`   - Sighash type, as appended to the signature
+pub fn legacy_signature_hash(script_code: &Script, tx: &Transaction, input_index: usize, hash_type: u32) -> Hash256 {
+    let mut one = [0; 32];
+    one[0] = 1;
+
+    if input_index >= tx.inputs().len() {
+        return Hash256::from_bytes(one);
+    }
+
+    let base_type = hash_type & SIGHASH_OUTPUT_MASK;
+
+    // The SIGHASH_SINGLE bug
+    if base_type == SIGHASH_SINGLE && input_index >= tx.outputs().len() {
+        return Hash256::from_bytes(one);
+    }
+
+    // Remaining OP_CODESEPARATORs are never signed
+    let mut script_code = script_code.clone();
+    script_code.remove_op(OpCodes::OP_CODESEPARATOR);
+
+    let mut tx_copy = Transaction::new();
+    tx_copy.set_version(tx.version());
+    tx_copy.set_lock_time(tx.lock_time());
+
+    // Blank out other inputs' signature scripts, and let others update
+    // their sequence numbers unless all outputs are signed
+    for (i, input) in tx.inputs().iter().enumerate() {
+        if hash_type & SIGHASH_ANYONECANPAY != 0 && i != input_index {
+            continue;
+        }
+
+        let mut input = input.clone();
+        input.witness = Vec::new();
+
+        if i == input_index {
+            input.script_signature = script_code.to_bytes();
+        } else {
+            input.script_signature = Vec::new();
+
+            if base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+                input.sequence = 0;
+            }
+        }
+
+        tx_copy.inputs_mut().push(input);
+    }
+
+    // Outputs before the signed one are nulled out under SIGHASH_SINGLE
+    *tx_copy.outputs_mut() = match base_type {
+        SIGHASH_NONE => Vec::new(),
+        SIGHASH_SINGLE => {
+            let mut outputs = vec![TxOut::new(); input_index];
+            outputs.push(tx.outputs()[input_index].clone());
+            outputs
+        },
+        _ => tx.outputs().to_vec()
+    };
+
+    let mut preimage = tx_copy.serialize_without_witness();
+    hash_type.encode(&mut preimage);
+
+    double_hash(&preimage)
+}
+
+/// Signature hash for version 0 witness programs (BIP143). Unlike the
+/// original algorithm, this commits to the value of the output being spent,
+/// so a signer can be sure of the fee without fetching every previous
+/// transaction, and hashes each part of the transaction only once. Fails if
+/// the input index is out of range, as there is no quirk to fall back on.
+///
+/// ### Arguments
+///
+/// * `script_code` - Script code committed to by the signature
+/// * `tx`          - Transaction spending the input
+/// * `input_index` - Index of the input being signed
+/// * `hash_type`   - Sighash type, as appended to the signature
+/// * `amount`      - Value of the output being spent
+/// * `cache`       - Precomputed hashes for the transaction, if available
+pub fn witness_v0_signature_hash(
+    script_code: &Script,
+    tx: &Transaction,
+    input_index: usize,
+    hash_type: u32,
+    amount: Amount,
+    cache: Option<&PrecomputedTransactionData>
+) -> Option<Hash256>
+{
+    let input = tx.inputs().get(input_index)?;
+    let base_type = hash_type & SIGHASH_OUTPUT_MASK;
+    let anyone_can_pay = hash_type & SIGHASH_ANYONECANPAY != 0;
+
+    let mut hash_prevouts = Hash256::zero();
+    let mut hash_sequence = Hash256::zero();
+    let mut hash_outputs = Hash256::zero();
+
+    if !anyone_can_pay {
+        hash_prevouts = cache.map_or_else(|| get_prevouts_hash(tx), |c| c.hash_prevouts);
+    }
+
+    if !anyone_can_pay && base_type != SIGHASH_SINGLE && base_type != SIGHASH_NONE {
+        hash_sequence = cache.map_or_else(|| get_sequence_hash(tx), |c| c.hash_sequence);
+    }
+
+    if base_type != SIGHASH_SINGLE && base_type != SIGHASH_NONE {
+        hash_outputs = cache.map_or_else(|| get_outputs_hash(tx), |c| c.hash_outputs);
+    } else if base_type == SIGHASH_SINGLE && input_index < tx.outputs().len() {
+        hash_outputs = double_hash(&serialize(&tx.outputs()[input_index]));
+    }
+
+    let mut preimage = Vec::new();
+
+    tx.version().encode(&mut preimage);
+    hash_prevouts.encode(&mut preimage);
+    hash_sequence.encode(&mut preimage);
+    encode_previous_out(input, &mut preimage);
+    write_var_bytes(&mut preimage, &script_code.to_bytes());
+    amount.as_sat().encode(&mut preimage);
+    input.sequence.encode(&mut preimage);
+    hash_outputs.encode(&mut preimage);
+    tx.lock_time().encode(&mut preimage);
+    hash_type.encode(&mut preimage);
+
+    Some(double_hash(&preimage))
+}
+
+/// Signature hash for version 1 witness programs (BIP341). This commits to
+/// every output being spent, and to the annex and executing tapscript leaf
+/// where there are any. Fails on undefined sighash types, on SIGHASH_SINGLE
+/// without a matching output, and if the spent outputs aren't known.
+///
+/// ### Arguments
+///
+/// * `tx`          - Transaction spending the input
+/// * `input_index` - Index of the input being signed
+/// * `hash_type`   - Sighash type, SIGHASH_DEFAULT if the signature has no type byte
+/// * `sig_version` - Taproot for key path spends, Tapscript for script path spends
+/// * `exec_data`   - State of the spend being signed
+/// * `txdata`      - Precomputed hashes for the transaction, including its spent outputs
+pub fn taproot_signature_hash(
+    tx: &Transaction,
+    input_index: usize,
+    hash_type: u32,
+    sig_version: SigVersion,
+    exec_data: &ScriptExecutionData,
+    txdata: &PrecomputedTransactionData
+) -> Option<Hash256>
+{
+    let taproot = txdata.taproot.as_ref()?;
+
+    if input_index >= tx.inputs().len() || input_index >= taproot.spent_outputs.len() {
+        return None;
+    }
+
+    if hash_type > 0x03 && !(0x81..=0x83).contains(&hash_type) {
+        return None;
+    }
+
+    let ext_flag: u8 = match sig_version {
+        SigVersion::Taproot => 0,
+        SigVersion::Tapscript => 1,
+        _ => return None
+    };
+
+    let output_type = match hash_type {
+        SIGHASH_DEFAULT => SIGHASH_ALL,
+        _ => hash_type & SIGHASH_TAPROOT_OUTPUT_MASK
+    };
+    let anyone_can_pay = hash_type & SIGHASH_ANYONECANPAY != 0;
+
+    // Epoch, so the hash can be redefined without colliding with this one
+    let mut preimage = vec![0];
+    preimage.push(hash_type as u8);
+    tx.version().encode(&mut preimage);
+    tx.lock_time().encode(&mut preimage);
+
+    if !anyone_can_pay {
+        preimage.extend_from_slice(&taproot.sha_prevouts);
+        preimage.extend_from_slice(&taproot.sha_amounts);
+        preimage.extend_from_slice(&taproot.sha_script_pubkeys);
+        preimage.extend_from_slice(&taproot.sha_sequences);
+    }
+
+    if output_type == SIGHASH_ALL {
+        preimage.extend_from_slice(&taproot.sha_outputs);
+    }
+
+    let annex_present = exec_data.annex_hash.is_some() as u8;
+    preimage.push(ext_flag * 2 + annex_present);
+
+    if anyone_can_pay {
+        let input = &tx.inputs()[input_index];
+        let spent_output = &taproot.spent_outputs[input_index];
+
+        encode_previous_out(input, &mut preimage);
+        encode_amount(spent_output, &mut preimage);
+        write_var_bytes(&mut preimage, &spent_output.script_public_key);
+        input.sequence.encode(&mut preimage);
+    } else {
+        (input_index as u32).encode(&mut preimage);
+    }
+
+    if let Some(ref annex_hash) = exec_data.annex_hash {
+        preimage.extend_from_slice(annex_hash);
+    }
+
+    if output_type == SIGHASH_SINGLE {
+        if input_index >= tx.outputs().len() {
+            return None;
+        }
+
+        preimage.extend_from_slice(&sha256(&serialize(&tx.outputs()[input_index])));
+    }
+
+    if sig_version == SigVersion::Tapscript {
+        preimage.extend_from_slice(exec_data.tapleaf_hash.as_ref()?);
+        preimage.push(0); // Key version
+        exec_data.code_separator_pos.encode(&mut preimage);
+    }
+
+    Hash256::from_slice(&tagged_hash("TapSighash", &preimage))
+}
+
+/// Standard template hash for an input (BIP119). This commits to everything
+/// that affects the transaction's txid apart from the outpoints being spent,
+/// so an output can restrict exactly how it is spent without creating a
+/// hash cycle.
+///
+/// ### Arguments
+///
+/// * `tx`          - Transaction spending the input
+/// * `input_index` - Index of the input being checked
+/// * `cache`       - Precomputed hashes for the transaction, if available
+pub fn standard_template_hash(
+    tx: &Transaction,
+    input_index: usize,
+    cache: Option<&PrecomputedTransactionData>
+) -> Hash256
+{
+    let computed;
+    let template = match cache {
+        Some(txdata) => &txdata.template,
+        None => {
+            computed = TemplateTransactionData::new(tx);
+            &computed
+        }
+    };
+
+    let mut preimage = Vec::new();
+
+    tx.version().encode(&mut preimage);
+    tx.lock_time().encode(&mut preimage);
+
+    if let Some(ref script_sigs_hash) = template.script_sigs_hash {
+        preimage.extend_from_slice(script_sigs_hash);
+    }
+
+    (tx.inputs().len() as u32).encode(&mut preimage);
+    preimage.extend_from_slice(&template.sequences_hash);
+    (tx.outputs().len() as u32).encode(&mut preimage);
+    preimage.extend_from_slice(&template.outputs_hash);
+    (input_index as u32).encode(&mut preimage);
+
+    Hash256::from_slice(&sha256(&preimage)).unwrap()
+}
+
+/// Hash of every input's previous outpoint
+fn get_prevouts_hash(tx: &Transaction) -> Hash256 {
+    let mut stream = Vec::new();
+
+    for input in tx.inputs() {
+        encode_previous_out(input, &mut stream);
+    }
+
+    double_hash(&stream)
+}
+
+/// Hash of every input's sequence number
+fn get_sequence_hash(tx: &Transaction) -> Hash256 {
+    let mut stream = Vec::new();
+
+    for input in tx.inputs() {
+        input.sequence.encode(&mut stream);
+    }
+
+    double_hash(&stream)
+}
+
+/// Hash of every output
+fn get_outputs_hash(tx: &Transaction) -> Hash256 {
+    let mut stream = Vec::new();
+
+    for output in tx.outputs() {
+        output.encode(&mut stream);
+    }
+
+    double_hash(&stream)
+}
+
+/// Writes the outpoint an input spends, as it appears in the serialized input
+fn encode_previous_out(input: &TxIn, stream: &mut Vec<u8>) {
+    match input.previous_out {
+        Some(ref outpoint) => outpoint.encode(stream),
+        None => OutPoint::new(Hash256::zero(), -1).encode(stream)
+    }
+}
+
+/// Writes the value of an output, as it appears in the serialized output
+fn encode_amount(output: &TxOut, stream: &mut Vec<u8>) {
+    match output.value {
+        Some(value) => (value as i64).encode(stream),
+        None => (-1_i64).encode(stream)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use primitives::test_utils::sample_transaction;
+
+    /// The hash `legacy_signature_hash` falls back on for its quirks
+    fn one() -> Hash256 {
+        let mut one = [0; 32];
+        one[0] = 1;
+
+        Hash256::from_bytes(one)
+    }
+
+    fn script_code() -> Script {
+        let mut script = Script::new();
+        script.push_data(&[0x11; 32]).push_op(OpCodes::OP_CHECKSIG);
+
+        script
+    }
+
+    type Change = fn(&mut Transaction);
+
+    /// Whether a change to the transaction changes the signature hash of input 0
+    fn commits_to(change: Change, hash_type: u32) -> bool {
+        let tx = sample_transaction(2, 2);
+        let mut changed = tx.clone();
+        change(&mut changed);
+
+        legacy_signature_hash(&script_code(), &tx, 0, hash_type) != legacy_signature_hash(&script_code(), &changed, 0, hash_type)
+    }
+
+    #[test]
+    fn hash_types_commit_to_different_parts() {
+        let acp = SIGHASH_ANYONECANPAY;
+        let hash_types = [SIGHASH_ALL, SIGHASH_NONE, SIGHASH_SINGLE, SIGHASH_ALL | acp, SIGHASH_NONE | acp, SIGHASH_SINGLE | acp];
+        let tx = sample_transaction(2, 2);
+
+        let mut hashes: Vec<Hash256> = hash_types.iter().map(|hash_type| legacy_signature_hash(&script_code(), &tx, 0, *hash_type)).collect();
+        hashes.sort();
+        hashes.dedup();
+        assert_eq!(hashes.len(), hash_types.len());
+
+        // Each entry: change, then whether each of the hash types above commits to it
+        let cases: Vec<(Change, [bool; 6])> = vec![
+            (|tx| tx.set_lock_time(501), [true; 6]),
+            (|tx| tx.inputs_mut()[0].sequence += 1, [true; 6]),
+            (|tx| tx.outputs_mut()[0].value = Some(1), [true, false, true, true, false, true]),
+            (|tx| tx.outputs_mut()[1].value = Some(1), [true, false, false, true, false, false]),
+            (|tx| tx.inputs_mut()[1].sequence += 1, [true, false, false, false, false, false]),
+            (|tx| tx.inputs_mut()[1].previous_out = None, [true, true, true, false, false, false]),
+            (|tx| tx.inputs_mut().truncate(1), [true, true, true, false, false, false]),
+
+            // Signature scripts and witnesses are never signed
+            (|tx| tx.inputs_mut()[0].script_signature = vec![0x52], [false; 6]),
+            (|tx| tx.inputs_mut()[1].script_signature = vec![0x52], [false; 6]),
+            (|tx| tx.inputs_mut()[1].witness = vec![vec![1]], [false; 6])
+        ];
+
+        for (i, (change, expected)) in cases.into_iter().enumerate() {
+            for (hash_type, expected) in hash_types.iter().zip(expected.iter()) {
+                assert_eq!(commits_to(change, *hash_type), *expected, "case {}, hash type {:#x}", i, hash_type);
+            }
+        }
+    }
+
+    #[test]
+    fn sighash_single_without_output_signs_one() {
+        let tx = sample_transaction(2, 1);
+
+        assert_eq!(legacy_signature_hash(&script_code(), &tx, 1, SIGHASH_SINGLE), one());
+        assert_eq!(legacy_signature_hash(&script_code(), &tx, 1, SIGHASH_SINGLE | SIGHASH_ANYONECANPAY), one());
+        assert_ne!(legacy_signature_hash(&script_code(), &tx, 0, SIGHASH_SINGLE), one());
+        assert_ne!(legacy_signature_hash(&script_code(), &tx, 1, SIGHASH_ALL), one());
+
+        // An input index out of range is the other way to sign one
+        assert_eq!(legacy_signature_hash(&script_code(), &tx, 2, SIGHASH_ALL), one());
+
+        // The witness algorithm has no such quirk
+        assert!(witness_v0_signature_hash(&script_code(), &tx, 1, SIGHASH_SINGLE, Amount::ZERO, None).is_some());
+        assert_eq!(witness_v0_signature_hash(&script_code(), &tx, 2, SIGHASH_ALL, Amount::ZERO, None), None);
+    }
+
+    #[test]
+    fn unknown_hash_types_sign_like_all() {
+        let tx = sample_transaction(2, 2);
+        let hash = legacy_signature_hash(&script_code(), &tx, 0, 0x04);
+
+        assert_ne!(hash, legacy_signature_hash(&script_code(), &tx, 0, SIGHASH_ALL));
+        assert!(commits_to(|tx| tx.outputs_mut()[1].value = Some(1), 0x04));
+        assert!(commits_to(|tx| tx.inputs_mut()[1].sequence += 1, 0x04));
+    }
+
+    #[test]
+    fn code_separators_are_not_signed() {
+        let tx = sample_transaction(1, 1);
+
+        let mut separated = Script::new();
+        separated.push_op(OpCodes::OP_CODESEPARATOR).push_data(&[0x11; 32])
+            .push_op(OpCodes::OP_CODESEPARATOR).push_op(OpCodes::OP_CHECKSIG);
+
+        let mut other = script_code();
+        other.push_op(OpCodes::OP_NOP);
+
+        let hash = legacy_signature_hash(&script_code(), &tx, 0, SIGHASH_ALL);
+        assert_eq!(legacy_signature_hash(&separated, &tx, 0, SIGHASH_ALL), hash);
+        assert_ne!(legacy_signature_hash(&other, &tx, 0, SIGHASH_ALL), hash);
+    }
+
+    #[test]
+    fn template_hashes_commit_to_everything_but_outpoints() {
+        let tx = sample_transaction(2, 2);
+        let hash = standard_template_hash(&tx, 0, None);
+
+        assert_eq!(standard_template_hash(&tx, 0, Some(&PrecomputedTransactionData::new(&tx))), hash);
+        assert_ne!(standard_template_hash(&tx, 1, None), hash);
+
+        let changes: Vec<Change> = vec![
+            |tx| tx.set_version(3),
+            |tx| tx.set_lock_time(501),
+            |tx| tx.inputs_mut()[1].sequence += 1,
+            |tx| tx.inputs_mut()[1].script_signature = vec![0x52],
+            |tx| tx.outputs_mut()[1].value = Some(1),
+            |tx| tx.outputs_mut()[1].script_public_key.push(0x87),
+            |tx| tx.outputs_mut().truncate(1),
+            |tx| { let input = tx.inputs()[1].clone(); tx.inputs_mut().push(input) }
+        ];
+
+        for (i, change) in changes.into_iter().enumerate() {
+            let mut changed = tx.clone();
+            change(&mut changed);
+
+            assert_ne!(standard_template_hash(&changed, 0, None), hash, "change {}", i);
+        }
+
+        // Outpoints and witnesses are left out, so the hash can be committed
+        // to before the transaction it spends exists
+        let mut changed = tx.clone();
+        changed.inputs_mut()[0].previous_out = None;
+        changed.inputs_mut()[1].witness = vec![vec![1]];
+        assert_eq!(standard_template_hash(&changed, 0, None), hash);
+    }
+
+    #[test]
+    fn template_hashes_skip_empty_signature_scripts() {
+        let mut tx = sample_transaction(2, 1);
+
+        for input in tx.inputs_mut().iter_mut() {
+            input.script_signature.clear();
+        }
+
+        // Empty signature scripts leave their hash out of the preimage, rather
+        // than hashing a list of empty scripts
+        let mut preimage = Vec::new();
+        tx.version().encode(&mut preimage);
+        tx.lock_time().encode(&mut preimage);
+        2u32.encode(&mut preimage);
+        preimage.extend_from_slice(&sha256(&[10, 0, 0, 0, 11, 0, 0, 0]));
+        1u32.encode(&mut preimage);
+        preimage.extend_from_slice(&sha256(&serialize(&tx.outputs()[0])));
+        0u32.encode(&mut preimage);
+
+        assert_eq!(standard_template_hash(&tx, 0, None).as_bytes(), &sha256(&preimage)[..]);
+        assert_eq!(TemplateTransactionData::new(&tx).script_sigs_hash, None);
+    }
+}
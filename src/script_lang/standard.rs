@@ -0,0 +1,341 @@
+/*
+ * Standard output script templates
+ */
+
+use std::fmt;
+use cryptography::keys::PUBLIC_KEY_SIZE;
+use script_lang::script::{ Script, StackEntry, OpCodes };
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// A destination that an output can pay to, equivalent to Bitcoin's
+/// CTxDestination. Textual encodings (base58check, bech32) are not
+/// handled here.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Address {
+    PubKeyHash([u8; 20]),           // P2PKH
+    ScriptHash([u8; 20]),           // P2SH (BIP16)
+    WitnessKeyHash([u8; 20]),       // P2WPKH (BIP141)
+    WitnessScriptHash([u8; 32]),    // P2WSH (BIP141)
+    Taproot([u8; 32])               // P2TR (BIP341)
+}
+
+/// The template an output script matches, along with its solution: the
+/// keys or hashes that have to be satisfied to spend it. Equivalent to
+/// Bitcoin's txnouttype together with the solutions from Solver.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TxOutType {
+    NonStandard,
+    PubKey(Vec<u8>),                                    // <pubkey> OP_CHECKSIG
+    PubKeyHash([u8; 20]),                               // OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+    ScriptHash([u8; 20]),                               // OP_HASH160 <hash> OP_EQUAL
+    MultiSig { required: usize, keys: Vec<Vec<u8>> },   // m <pubkey>... n OP_CHECKMULTISIG
+    NullData,                                           // OP_RETURN followed by pushes only
+    WitnessV0KeyHash([u8; 20]),                         // OP_0 <20 byte hash>
+    WitnessV0ScriptHash([u8; 32]),                      // OP_0 <32 byte hash>
+    WitnessV1Taproot([u8; 32]),                         // OP_1 <32 byte x-only key>
+    WitnessUnknown { version: u8, program: Vec<u8> }    // Witness program of a future version
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl Address {
+
+    /// Builds the output script that pays to this address
+    pub fn get_script_public_key(&self) -> Vec<u8> {
+        let mut script = Script::new();
+
+        match self {
+            Address::PubKeyHash(hash) => {
+                script.push_op(OpCodes::OP_DUP)
+                      .push_op(OpCodes::OP_HASH160)
+                      .push_data(hash)
+                      .push_op(OpCodes::OP_EQUALVERIFY)
+                      .push_op(OpCodes::OP_CHECKSIG);
+            },
+            Address::ScriptHash(hash) => {
+                script.push_op(OpCodes::OP_HASH160)
+                      .push_data(hash)
+                      .push_op(OpCodes::OP_EQUAL);
+            },
+            Address::WitnessKeyHash(hash) => {
+                script.push_op(OpCodes::OP_0).push_data(hash);
+            },
+            Address::WitnessScriptHash(hash) => {
+                script.push_op(OpCodes::OP_0).push_data(hash);
+            },
+            Address::Taproot(key) => {
+                script.push_op(OpCodes::OP_1).push_data(key);
+            }
+        }
+
+        script.to_bytes()
+    }
+}
+
+impl TxOutType {
+
+    /// The address an output of this type pays to, if it has one. Bare
+    /// public keys, multisig and null data outputs have no address.
+    pub fn get_address(&self) -> Option<Address> {
+        match self {
+            TxOutType::PubKeyHash(hash) => Some(Address::PubKeyHash(*hash)),
+            TxOutType::ScriptHash(hash) => Some(Address::ScriptHash(*hash)),
+            TxOutType::WitnessV0KeyHash(hash) => Some(Address::WitnessKeyHash(*hash)),
+            TxOutType::WitnessV0ScriptHash(hash) => Some(Address::WitnessScriptHash(*hash)),
+            TxOutType::WitnessV1Taproot(key) => Some(Address::Taproot(*key)),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for TxOutType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TxOutType::NonStandard => "nonstandard",
+            TxOutType::PubKey(_) => "pubkey",
+            TxOutType::PubKeyHash(_) => "pubkeyhash",
+            TxOutType::ScriptHash(_) => "scripthash",
+            TxOutType::MultiSig { .. } => "multisig",
+            TxOutType::NullData => "nulldata",
+            TxOutType::WitnessV0KeyHash(_) => "witness_v0_keyhash",
+            TxOutType::WitnessV0ScriptHash(_) => "witness_v0_scripthash",
+            TxOutType::WitnessV1Taproot(_) => "witness_v1_taproot",
+            TxOutType::WitnessUnknown { .. } => "witness_unknown"
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Works out which standard template an output script matches, and
+/// extracts its solution
+///
+/// ### Arguments
+///
+/// * `script`  - Output script to classify
+pub fn classify(script: &Script) -> TxOutType {
+    let entries = script.get_entries();
+
+    // Shortcut for pay-to-script-hash, which are more constrained than the
+    // other types: it is always OP_HASH160 20 [20 byte hash] OP_EQUAL
+    if script.is_p2sh() {
+        return TxOutType::ScriptHash(to_array_20(get_push_data(&entries[1]).unwrap()));
+    }
+
+    if let Some((version, program)) = script.get_witness_program() {
+        return match (version, program.len()) {
+            (0, 20) => TxOutType::WitnessV0KeyHash(to_array_20(program)),
+            (0, 32) => {
+                let mut hash = [0; 32];
+                hash.copy_from_slice(program);
+                TxOutType::WitnessV0ScriptHash(hash)
+            },
+            (0, _) => TxOutType::NonStandard,
+            (1, 32) => {
+                let mut key = [0; 32];
+                key.copy_from_slice(program);
+                TxOutType::WitnessV1Taproot(key)
+            },
+            _ => TxOutType::WitnessUnknown { version, program: program.to_vec() }
+        };
+    }
+
+    // Provably prunable, data-carrying output. So long as the script passes
+    // the push-only check, the size of the data is left to policy.
+    if entries.first() == Some(&StackEntry::Op(OpCodes::OP_RETURN)) && script.get_subscript(1).is_push_only() {
+        return TxOutType::NullData;
+    }
+
+    if let Some(key) = match_pay_to_pubkey(entries) {
+        return TxOutType::PubKey(key);
+    }
+
+    if let Some(hash) = match_pay_to_pubkey_hash(entries) {
+        return TxOutType::PubKeyHash(hash);
+    }
+
+    if let Some((required, keys)) = match_multisig(entries) {
+        return TxOutType::MultiSig { required, keys };
+    }
+
+    TxOutType::NonStandard
+}
+
+/// Matches `<pubkey> OP_CHECKSIG`, with the key pushed directly
+fn match_pay_to_pubkey(entries: &[StackEntry]) -> Option<Vec<u8>> {
+    match entries {
+        [StackEntry::Push(op_code, key), StackEntry::Op(OpCodes::OP_CHECKSIG)]
+            if *op_code as usize == key.len() && key.len() == PUBLIC_KEY_SIZE => Some(key.clone()),
+        _ => None
+    }
+}
+
+/// Matches `OP_DUP OP_HASH160 <20 byte hash> OP_EQUALVERIFY OP_CHECKSIG`,
+/// with the hash pushed directly
+fn match_pay_to_pubkey_hash(entries: &[StackEntry]) -> Option<[u8; 20]> {
+    match entries {
+        [StackEntry::Op(OpCodes::OP_DUP), StackEntry::Op(OpCodes::OP_HASH160), StackEntry::Push(0x14, hash),
+         StackEntry::Op(OpCodes::OP_EQUALVERIFY), StackEntry::Op(OpCodes::OP_CHECKSIG)] => Some(to_array_20(hash)),
+        _ => None
+    }
+}
+
+/// Matches `m <pubkey>... n OP_CHECKMULTISIG`, with 1 <= m <= n <= 16,
+/// returning m and the keys
+fn match_multisig(entries: &[StackEntry]) -> Option<(usize, Vec<Vec<u8>>)> {
+    if entries.len() < 4 || entries[entries.len() - 1] != StackEntry::Op(OpCodes::OP_CHECKMULTISIG) {
+        return None;
+    }
+
+    let required = decode_small_int(&entries[0])?;
+    let total = decode_small_int(&entries[entries.len() - 2])?;
+    let mut keys = Vec::new();
+
+    for entry in &entries[1..entries.len() - 2] {
+        match get_push_data(entry) {
+            Some(key) if key.len() == PUBLIC_KEY_SIZE => keys.push(key.to_vec()),
+            _ => return None
+        }
+    }
+
+    if keys.len() != total || required < 1 || required > total {
+        return None;
+    }
+
+    Some((required, keys))
+}
+
+/// Value of an OP_1 to OP_16 entry
+fn decode_small_int(entry: &StackEntry) -> Option<usize> {
+    match entry {
+        StackEntry::Op(op) if *op >= OpCodes::OP_1 && *op <= OpCodes::OP_16 => {
+            Some((*op as u8 - (OpCodes::OP_1 as u8 - 1)) as usize)
+        },
+        _ => None
+    }
+}
+
+/// Data pushed by a push entry
+fn get_push_data(entry: &StackEntry) -> Option<&[u8]> {
+    match entry {
+        StackEntry::Push(_, data) => Some(data),
+        _ => None
+    }
+}
+
+/// Copies a 20 byte hash out of a slice known to be 20 bytes long
+fn to_array_20(bytes: &[u8]) -> [u8; 20] {
+    let mut hash = [0; 20];
+    hash.copy_from_slice(bytes);
+
+    hash
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify_asm(asm: &str) -> TxOutType {
+        classify(&Script::from_asm(asm).unwrap())
+    }
+
+    fn push(byte: &str, count: usize) -> String {
+        format!("<{}>", byte.repeat(count))
+    }
+
+    #[test]
+    fn standard_templates() {
+        let key = push("11", PUBLIC_KEY_SIZE);
+        let other_key = push("22", PUBLIC_KEY_SIZE);
+
+        let cases = vec![
+            (format!("{} OP_CHECKSIG", key), TxOutType::PubKey(vec![0x11; PUBLIC_KEY_SIZE])),
+            (format!("OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG", push("33", 20)), TxOutType::PubKeyHash([0x33; 20])),
+            (format!("OP_HASH160 {} OP_EQUAL", push("33", 20)), TxOutType::ScriptHash([0x33; 20])),
+            (format!("OP_0 {}", push("33", 20)), TxOutType::WitnessV0KeyHash([0x33; 20])),
+            (format!("OP_0 {}", push("33", 32)), TxOutType::WitnessV0ScriptHash([0x33; 32])),
+            (format!("OP_1 {}", push("33", 32)), TxOutType::WitnessV1Taproot([0x33; 32])),
+            (
+                format!("OP_1 {} {} OP_2 OP_CHECKMULTISIG", key, other_key),
+                TxOutType::MultiSig { required: 1, keys: vec![vec![0x11; PUBLIC_KEY_SIZE], vec![0x22; PUBLIC_KEY_SIZE]] }
+            ),
+            (
+                format!("OP_2 {} {} OP_2 OP_CHECKMULTISIG", key, other_key),
+                TxOutType::MultiSig { required: 2, keys: vec![vec![0x11; PUBLIC_KEY_SIZE], vec![0x22; PUBLIC_KEY_SIZE]] }
+            ),
+            ("OP_RETURN".to_string(), TxOutType::NullData),
+            ("OP_RETURN <aabb> OP_1".to_string(), TxOutType::NullData),
+
+            // Only version 0 programs have their lengths fixed; other versions
+            // and lengths are left for future soft forks
+            (format!("OP_1 {}", push("33", 20)), TxOutType::WitnessUnknown { version: 1, program: vec![0x33; 20] }),
+            ("OP_16 <aabb>".to_string(), TxOutType::WitnessUnknown { version: 16, program: vec![0xaa, 0xbb] })
+        ];
+
+        for (asm, expected) in cases {
+            assert_eq!(classify_asm(&asm), expected, "{}", asm);
+        }
+    }
+
+    #[test]
+    fn near_misses_are_non_standard() {
+        let key = push("11", PUBLIC_KEY_SIZE);
+
+        let cases = vec![
+            String::new(),
+
+            // Version 0 programs must be exactly 20 or 32 bytes
+            format!("OP_0 {}", push("33", 21)),
+            format!("OP_0 {}", push("33", 31)),
+
+            // Multisig with m > n, m = 0, or n not matching the keys
+            format!("OP_3 {} {} OP_2 OP_CHECKMULTISIG", key, key),
+            format!("OP_0 {} OP_1 OP_CHECKMULTISIG", key),
+            format!("OP_1 {} OP_2 OP_CHECKMULTISIG", key),
+            format!("OP_1 {} OP_1 OP_CHECKMULTISIGVERIFY", key),
+            format!("OP_1 {} OP_1 OP_CHECKMULTISIG", push("11", PUBLIC_KEY_SIZE + 1)),
+
+            // Keys and hashes must be direct pushes of the right size
+            format!("{} OP_CHECKSIG", push("11", PUBLIC_KEY_SIZE + 1)),
+            format!("OP_PUSHDATA1 {} OP_CHECKSIG", key),
+            format!("OP_DUP OP_HASH160 OP_PUSHDATA1 {} OP_EQUALVERIFY OP_CHECKSIG", push("33", 20)),
+            format!("OP_HASH160 OP_PUSHDATA1 {} OP_EQUAL", push("33", 20)),
+            format!("OP_DUP OP_HASH160 {} OP_EQUALVERIFY OP_CHECKSIG", push("33", 32)),
+
+            // Null data may only be followed by pushes
+            "OP_RETURN OP_NOP".to_string()
+        ];
+
+        for asm in cases {
+            assert_eq!(classify_asm(&asm), TxOutType::NonStandard, "{}", asm);
+        }
+    }
+
+    #[test]
+    fn addresses_round_trip_through_their_scripts() {
+        let addresses = [
+            Address::PubKeyHash([1; 20]),
+            Address::ScriptHash([2; 20]),
+            Address::WitnessKeyHash([3; 20]),
+            Address::WitnessScriptHash([4; 32]),
+            Address::Taproot([5; 32])
+        ];
+
+        for address in addresses.iter() {
+            let script = Script::from_bytes(&address.get_script_public_key()).unwrap();
+
+            assert_eq!(classify(&script).get_address().as_ref(), Some(address));
+        }
+
+        assert_eq!(classify_asm("OP_RETURN").get_address(), None);
+        assert_eq!(classify_asm("OP_RETURN").to_string(), "nulldata");
+        assert_eq!(classify_asm("OP_2 <aabb>").to_string(), "witness_unknown");
+    }
+}
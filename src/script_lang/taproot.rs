@@ -0,0 +1,299 @@
+/*
+ * Taproot commitments (BIP341). A version 1 witness program is an x-only
+ * output key: an internal key tweaked by a hash of itself and, optionally,
+ * the merkle root of a tree of scripts. It can be spent either with a
+ * signature for the output key (the key path), or by revealing one of the
+ * scripts along with a control block proving the tree commits to it (the
+ * script path).
+ */
+
+use cryptography::hash::{ sha256, tagged_hash };
+use cryptography::schnorr::{ self, SCHNORR_PUBLIC_KEY_SIZE };
+use script_lang::script::{ Script, OpCodes };
+use serialize::write_var_bytes;
+
+
+/*---- CONSTANTS ----*/
+
+/// Mask for the leaf version in the first control block byte. The low bit
+/// holds the parity of the output key.
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+
+/// Leaf version of scripts run under the tapscript rules (BIP342)
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+
+/// Size of a control block for a tree with a single leaf: the leaf version
+/// and parity byte, followed by the internal key
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+
+/// Size of each merkle path node in a control block
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+
+/// Maximum merkle path length, so the deepest leaf is at depth 128
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// Maximum size of a control block
+pub const TAPROOT_CONTROL_MAX_SIZE: usize = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+
+/// First byte of the annex, an optional last witness element that is
+/// reserved for future extensions
+pub const ANNEX_TAG: u8 = 0x50;
+
+/// Validation weight used up by each signature check that passes in a tapscript
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+
+/// Validation weight budget given on top of the size of the witness
+pub const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// A tree of scripts that a taproot output commits to. Leaves nearer the
+/// root are cheaper to spend, as their merkle paths are shorter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TapTree {
+    Leaf(u8, Vec<u8>),                  // Leaf version and raw script
+    Branch(Box<TapTree>, Box<TapTree>)
+}
+
+/// A taproot output, built from its internal key and script tree
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaprootOutput {
+    internal_key: Vec<u8>,
+    tree: Option<TapTree>,
+    output_key: Vec<u8>,
+    output_key_odd: bool    // Parity of the output key's Y coordinate
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl TapTree {
+
+    /// Returns a leaf holding a tapscript
+    ///
+    /// ### Arguments
+    ///
+    /// * `script`  - Script for the leaf
+    pub fn leaf(script: &Script) -> TapTree {
+        TapTree::Leaf(TAPROOT_LEAF_TAPSCRIPT, script.to_bytes())
+    }
+
+    /// Returns a branch joining two subtrees
+    ///
+    /// ### Arguments
+    ///
+    /// * `left`    - First subtree
+    /// * `right`   - Second subtree
+    pub fn branch(left: TapTree, right: TapTree) -> TapTree {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    /// Merkle root of the tree
+    pub fn get_hash(&self) -> Vec<u8> {
+        match self {
+            TapTree::Leaf(version, script) => compute_tapleaf_hash(*version, script),
+            TapTree::Branch(left, right) => compute_tapbranch_hash(&left.get_hash(), &right.get_hash())
+        }
+    }
+
+    /// Merkle path from a leaf up to the root, as it appears in a control
+    /// block. None if the tree has no such leaf.
+    ///
+    /// ### Arguments
+    ///
+    /// * `version` - Leaf version of the leaf
+    /// * `script`  - Raw script of the leaf
+    pub fn get_merkle_path(&self, version: u8, script: &[u8]) -> Option<Vec<Vec<u8>>> {
+        match self {
+            TapTree::Leaf(leaf_version, leaf_script) => {
+                match *leaf_version == version && leaf_script.as_slice() == script {
+                    true => Some(Vec::new()),
+                    false => None
+                }
+            },
+            TapTree::Branch(left, right) => {
+                if let Some(mut path) = left.get_merkle_path(version, script) {
+                    path.push(right.get_hash());
+                    return Some(path);
+                }
+
+                let mut path = right.get_merkle_path(version, script)?;
+                path.push(left.get_hash());
+
+                Some(path)
+            }
+        }
+    }
+}
+
+impl TaprootOutput {
+
+    /// Builds a taproot output from its internal key and script tree. Fails
+    /// if the internal key is invalid, or, with negligible probability, if
+    /// the tweak doesn't give a valid key.
+    ///
+    /// ### Arguments
+    ///
+    /// * `internal_key`    - Raw x-only internal key
+    /// * `tree`            - Scripts the output can be spent with, if any
+    pub fn new(internal_key: &[u8], tree: Option<TapTree>) -> Option<TaprootOutput> {
+        let merkle_root = tree.as_ref().map(|tree| tree.get_hash());
+        let tweak = compute_tap_tweak(internal_key, merkle_root.as_deref());
+        let (output_key, output_key_odd) = schnorr::tweak_public_key(internal_key, &tweak)?;
+
+        Some(TaprootOutput {
+            internal_key: internal_key.to_vec(),
+            tree,
+            output_key,
+            output_key_odd
+        })
+    }
+
+    /// The untweaked internal key
+    pub fn get_internal_key(&self) -> &[u8] {
+        &self.internal_key
+    }
+
+    /// The tweaked output key, which is the witness program
+    pub fn get_output_key(&self) -> &[u8] {
+        &self.output_key
+    }
+
+    /// Merkle root of the script tree, if the output has one
+    pub fn get_merkle_root(&self) -> Option<Vec<u8>> {
+        self.tree.as_ref().map(|tree| tree.get_hash())
+    }
+
+    /// Tweak added to the internal key. Key path spends are signed with
+    /// the internal keypair tweaked by this.
+    pub fn get_tweak(&self) -> Vec<u8> {
+        let merkle_root = self.get_merkle_root();
+
+        compute_tap_tweak(&self.internal_key, merkle_root.as_deref())
+    }
+
+    /// Builds the output script, OP_1 <output key>
+    pub fn get_script_public_key(&self) -> Vec<u8> {
+        let mut script = Script::new();
+        script.push_op(OpCodes::OP_1)
+              .push_data(&self.output_key);
+
+        script.to_bytes()
+    }
+
+    /// Control block for spending the output with one of its scripts. None
+    /// if the tree has no such leaf.
+    ///
+    /// ### Arguments
+    ///
+    /// * `version` - Leaf version of the script
+    /// * `script`  - Raw script to spend with
+    pub fn get_control_block(&self, version: u8, script: &[u8]) -> Option<Vec<u8>> {
+        let path = self.tree.as_ref()?.get_merkle_path(version, script)?;
+
+        let mut control = vec![version | self.output_key_odd as u8];
+        control.extend_from_slice(&self.internal_key);
+
+        for node in path {
+            control.extend_from_slice(&node);
+        }
+
+        Some(control)
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Hash of a script tree leaf
+///
+/// ### Arguments
+///
+/// * `version` - Leaf version
+/// * `script`  - Raw script
+pub fn compute_tapleaf_hash(version: u8, script: &[u8]) -> Vec<u8> {
+    let mut preimage = vec![version & TAPROOT_LEAF_MASK];
+    write_var_bytes(&mut preimage, script);
+
+    tagged_hash("TapLeaf", &preimage)
+}
+
+/// Hash of a script tree branch. The children are sorted first, so a merkle
+/// path doesn't have to say which side each node is on.
+///
+/// ### Arguments
+///
+/// * `a`   - Hash of one child
+/// * `b`   - Hash of the other child
+pub fn compute_tapbranch_hash(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let (first, second) = if a < b { (a, b) } else { (b, a) };
+
+    let mut preimage = Vec::with_capacity(64);
+    preimage.extend_from_slice(first);
+    preimage.extend_from_slice(second);
+
+    tagged_hash("TapBranch", &preimage)
+}
+
+/// Tweak committing an internal key to a script tree. Committing to the key
+/// as well stops a key path spend being hidden behind another key.
+///
+/// ### Arguments
+///
+/// * `internal_key`    - Raw x-only internal key
+/// * `merkle_root`     - Merkle root of the script tree, if there is one
+pub fn compute_tap_tweak(internal_key: &[u8], merkle_root: Option<&[u8]>) -> Vec<u8> {
+    let mut preimage = internal_key.to_vec();
+
+    if let Some(root) = merkle_root {
+        preimage.extend_from_slice(root);
+    }
+
+    tagged_hash("TapTweak", &preimage)
+}
+
+/// Hash of an annex, as committed to by taproot signatures
+///
+/// ### Arguments
+///
+/// * `annex`   - Annex, including its tag byte
+pub fn compute_annex_hash(annex: &[u8]) -> Vec<u8> {
+    let mut preimage = Vec::new();
+    write_var_bytes(&mut preimage, annex);
+
+    sha256(&preimage)
+}
+
+/// Whether a control block has a valid size: the base part followed by
+/// whole merkle path nodes, up to the maximum depth
+///
+/// ### Arguments
+///
+/// * `control` - Control block from the witness
+pub fn is_valid_control_size(control: &[u8]) -> bool {
+    control.len() >= TAPROOT_CONTROL_BASE_SIZE &&
+    control.len() <= TAPROOT_CONTROL_MAX_SIZE &&
+    (control.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+}
+
+/// Whether a control block proves that a witness program commits to a leaf.
+/// The control block has to be of a valid size.
+///
+/// ### Arguments
+///
+/// * `control`       - Control block from the witness
+/// * `program`       - Witness program, the x-only output key
+/// * `tapleaf_hash`  - Hash of the leaf being spent
+pub fn verify_taproot_commitment(control: &[u8], program: &[u8], tapleaf_hash: &[u8]) -> bool {
+    let internal_key = &control[1..1 + SCHNORR_PUBLIC_KEY_SIZE];
+    let mut hash = tapleaf_hash.to_vec();
+
+    for node in control[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE) {
+        hash = compute_tapbranch_hash(&hash, node);
+    }
+
+    let tweak = compute_tap_tweak(internal_key, Some(&hash));
+
+    schnorr::check_tweak(program, control[0] & 1 == 1, internal_key, &tweak)
+}
@@ -0,0 +1,234 @@
+/*
+ * Script execution traces, for working out where a failing spend goes wrong.
+ * A trace records the interpreter's state just before each instruction it
+ * steps over, and which of those instructions failed, if one did.
+ */
+
+use std::fmt;
+use utils::hex::to_hex;
+use script_lang::error::ScriptError;
+use script_lang::script::StackEntry;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// Which of the scripts making up a spend a step was taken in
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ScriptPhase {
+    ScriptSig,
+    ScriptPubKey,
+    RedeemScript,   // P2SH (BIP16)
+    WitnessScript   // Witness programs: version 0 scripts, including the one implied by P2WPKH, and tapscripts
+}
+
+/// Interpreter state at a single instruction, taken before it runs
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceStep {
+    pub phase: ScriptPhase,
+    pub pc: usize,
+    pub entry: StackEntry,
+    pub executing: bool,            // False inside an untaken IF branch
+    pub stack: Vec<Vec<u8>>,
+    pub alt_stack: Vec<Vec<u8>>,
+    pub exec_stack: Vec<bool>       // Conditions of the enclosing IF branches
+}
+
+/// Every step taken while verifying a spend, along with the outcome
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScriptTrace {
+    pub steps: Vec<TraceStep>,
+    pub failed_step: Option<usize>,     // Index of the step whose instruction failed
+    pub result: Result<(), ScriptError>
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl Default for ScriptTrace {
+    fn default() -> ScriptTrace {
+        ScriptTrace::new()
+    }
+}
+
+impl ScriptTrace {
+
+    /// Returns an empty trace of a successful spend
+    pub fn new() -> ScriptTrace {
+        ScriptTrace {
+            steps: Vec::new(),
+            failed_step: None,
+            result: Ok(())
+        }
+    }
+
+    /// The step the spend failed at, if it failed while running a script.
+    /// Failures outside of script execution, such as a witness program
+    /// mismatch or a dirty stack at the end, have no failing step.
+    pub fn get_failed_step(&self) -> Option<&TraceStep> {
+        match self.result {
+            Ok(()) => None,
+            Err(_) => self.steps.get(self.failed_step?)
+        }
+    }
+}
+
+impl fmt::Display for ScriptPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ScriptPhase::ScriptSig => "scriptSig",
+            ScriptPhase::ScriptPubKey => "scriptPubKey",
+            ScriptPhase::RedeemScript => "redeemScript",
+            ScriptPhase::WitnessScript => "witnessScript"
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let marker = if self.executing { " " } else { "-" };
+        let exec: Vec<&str> = self.exec_stack.iter().map(|b| if *b { "1" } else { "0" }).collect();
+
+        write!(
+            f,
+            "{:<13} {:>4} {}{:<24} stack: [{}] alt: [{}] exec: [{}]",
+            self.phase,
+            self.pc,
+            marker,
+            self.entry.to_asm(),
+            format_stack(&self.stack),
+            format_stack(&self.alt_stack),
+            exec.join(" ")
+        )
+    }
+}
+
+impl fmt::Display for ScriptTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+
+        match self.result {
+            Ok(()) => write!(f, "result: ok"),
+            Err(e) => write!(f, "result: {}", e)
+        }
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Writes stack elements bottom first, as `<hex>` so that empty elements show
+fn format_stack(stack: &[Vec<u8>]) -> String {
+    let elements: Vec<String> = stack.iter().map(|element| format!("<{}>", to_hex(element))).collect();
+
+    elements.join(" ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryptography::hash::sha256;
+    use script_lang::checker::BaseSignatureChecker;
+    use script_lang::flags::ScriptVerifyFlags;
+    use script_lang::interpreter::trace_verify_script;
+    use script_lang::script::{ Script, OpCodes };
+
+    fn run_trace(script_sig: &Script, script_pubkey: &Script, witness: &[Vec<u8>], flags: ScriptVerifyFlags) -> ScriptTrace {
+        trace_verify_script(&script_sig.to_bytes(), &script_pubkey.to_bytes(), witness, flags, &BaseSignatureChecker)
+    }
+
+    #[test]
+    fn successful_spends_have_no_failed_step() {
+        let mut script_sig = Script::new();
+        script_sig.push_op(OpCodes::OP_1);
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_DUP).push_op(OpCodes::OP_DROP);
+
+        let trace = run_trace(&script_sig, &script_pubkey, &[], ScriptVerifyFlags::NONE);
+
+        assert_eq!(trace.result, Ok(()));
+        assert_eq!(trace.steps.len(), 3);
+        assert!(trace.get_failed_step().is_none());
+    }
+
+    #[test]
+    fn failed_step_is_the_failing_instruction() {
+        let mut script_sig = Script::new();
+        script_sig.push_op(OpCodes::OP_0);
+
+        // The OP_VERIFY fails, and nothing after it runs
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_DUP).push_op(OpCodes::OP_VERIFY).push_op(OpCodes::OP_1);
+
+        let trace = run_trace(&script_sig, &script_pubkey, &[], ScriptVerifyFlags::NONE);
+        let step = trace.get_failed_step().unwrap();
+
+        assert_eq!(trace.result, Err(ScriptError::Verify));
+        assert_eq!(step.phase, ScriptPhase::ScriptPubKey);
+        assert_eq!(step.pc, 1);
+        assert_eq!(step.entry, StackEntry::Op(OpCodes::OP_VERIFY));
+        assert_eq!(step.stack, vec![Vec::<u8>::new(), Vec::new()]);
+    }
+
+    #[test]
+    fn failures_after_execution_have_no_failed_step() {
+        let empty = Script::new();
+
+        // A false result
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_0);
+
+        let trace = run_trace(&empty, &script_pubkey, &[], ScriptVerifyFlags::NONE);
+
+        assert_eq!(trace.result, Err(ScriptError::EvalFalse));
+        assert!(trace.get_failed_step().is_none());
+
+        // Extra elements left on the stack
+        let mut script_sig = Script::new();
+        script_sig.push_op(OpCodes::OP_1).push_op(OpCodes::OP_1);
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_NOP);
+
+        let flags = ScriptVerifyFlags::P2SH | ScriptVerifyFlags::WITNESS | ScriptVerifyFlags::CLEANSTACK;
+        let trace = run_trace(&script_sig, &script_pubkey, &[], flags);
+
+        assert_eq!(trace.result, Err(ScriptError::CleanStack));
+        assert!(trace.get_failed_step().is_none());
+
+        // An unclosed OP_IF
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_1).push_op(OpCodes::OP_IF).push_op(OpCodes::OP_1);
+
+        let trace = run_trace(&empty, &script_pubkey, &[], ScriptVerifyFlags::NONE);
+
+        assert_eq!(trace.result, Err(ScriptError::UnbalancedConditional));
+        assert!(trace.get_failed_step().is_none());
+    }
+
+    #[test]
+    fn witness_mismatch_has_no_failed_step() {
+        let mut witness_script = Script::new();
+        witness_script.push_op(OpCodes::OP_1);
+
+        let mut script_pubkey = Script::new();
+        script_pubkey.push_op(OpCodes::OP_0).push_data(&sha256(&witness_script.to_bytes()));
+
+        let flags = ScriptVerifyFlags::P2SH | ScriptVerifyFlags::WITNESS;
+
+        let trace = run_trace(&Script::new(), &script_pubkey, &[vec![OpCodes::OP_2 as u8]], flags);
+
+        assert_eq!(trace.result, Err(ScriptError::WitnessProgramMismatch));
+        assert!(!trace.steps.is_empty());
+        assert!(trace.get_failed_step().is_none());
+
+        let trace = run_trace(&Script::new(), &script_pubkey, &[witness_script.to_bytes()], flags);
+
+        assert_eq!(trace.result, Ok(()));
+    }
+}
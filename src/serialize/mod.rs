@@ -0,0 +1,390 @@
+/*
+ * Consensus binary serialization. Everything that gets hashed, written to
+ * disk or sent over the wire goes through the Encodable/Decodable pair in
+ * here, so the byte layout must match the original Bitcoin encoding exactly.
+ *
+ * Integers are little-endian, and variable-length collections are prefixed
+ * with their length as a "CompactSize" varint:
+ *
+ *  - value < 253               -> 1 byte
+ *  - value <= 0xffff           -> 0xfd followed by 2 bytes
+ *  - value <= 0xffffffff       -> 0xfe followed by 4 bytes
+ *  - anything else             -> 0xff followed by 8 bytes
+ */
+
+use std::fmt;
+use std::error::Error;
+
+
+/*---- CONSTANTS ----*/
+
+/// Maximum size of any single serialized collection or byte vector (32MB)
+pub const MAX_SIZE: u64 = 0x02000000;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// Errors that can occur while decoding consensus-serialized data
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SerializeError {
+    UnexpectedEnd { needed: usize, remaining: usize },  // Input ran out before the value was complete
+    NonCanonicalCompactSize(u64),                       // CompactSize was not encoded in its shortest form
+    OversizedCompactSize(u64),                          // CompactSize exceeds MAX_SIZE
+    TrailingBytes(usize),                               // Input had bytes left over after decoding
+    InvalidValue(&'static str)                          // Decoded value is not allowed in this position
+}
+
+/// A cursor over a byte slice that consensus decoders read from
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+
+/*---- TRAITS ----*/
+
+/// Data which can be written out in consensus form
+pub trait Encodable {
+
+    /// Appends the consensus encoding of this value to the stream
+    ///
+    /// ### Arguments
+    ///
+    /// * `stream`  - Byte stream to write to
+    fn encode(&self, stream: &mut Vec<u8>);
+
+    /// Number of bytes the consensus encoding of this value takes up
+    fn serialized_size(&self) -> usize {
+        let mut stream = Vec::new();
+        self.encode(&mut stream);
+
+        stream.len()
+    }
+}
+
+/// Data which can be read back in from consensus form
+pub trait Decodable: Sized {
+
+    /// Reads a value of this type from the reader
+    ///
+    /// ### Arguments
+    ///
+    /// * `reader`  - Reader to consume bytes from
+    fn decode(reader: &mut Reader) -> Result<Self, SerializeError>;
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerializeError::UnexpectedEnd { needed, remaining } => {
+                write!(f, "Unexpected end of data: needed {} bytes but only {} remain", needed, remaining)
+            },
+            SerializeError::NonCanonicalCompactSize(size) => write!(f, "Non-canonical CompactSize encoding of {}", size),
+            SerializeError::OversizedCompactSize(size) => write!(f, "CompactSize of {} exceeds the maximum of {}", size, MAX_SIZE),
+            SerializeError::TrailingBytes(count) => write!(f, "{} bytes left over after decoding", count),
+            SerializeError::InvalidValue(reason) => write!(f, "Invalid value: {}", reason)
+        }
+    }
+}
+
+impl Error for SerializeError {}
+
+impl<'a> Reader<'a> {
+
+    /// Returns a new reader positioned at the start of the data
+    ///
+    /// ### Arguments
+    ///
+    /// * `data`    - Bytes to read from
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader {
+            data,
+            position: 0
+        }
+    }
+
+    /// Number of bytes not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    /// Whether every byte has been consumed
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Consumes the next `count` bytes
+    ///
+    /// ### Arguments
+    ///
+    /// * `count`   - Number of bytes to read
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], SerializeError> {
+        if count > self.remaining() {
+            return Err(SerializeError::UnexpectedEnd { needed: count, remaining: self.remaining() });
+        }
+
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+
+        Ok(bytes)
+    }
+
+    /// Reads a fixed-size array of bytes
+    fn read_array_4(&mut self) -> Result<[u8; 4], SerializeError> {
+        let mut result = [0; 4];
+        result.copy_from_slice(self.read_bytes(4)?);
+
+        Ok(result)
+    }
+
+    /// Reads a fixed-size array of bytes
+    fn read_array_8(&mut self) -> Result<[u8; 8], SerializeError> {
+        let mut result = [0; 8];
+        result.copy_from_slice(self.read_bytes(8)?);
+
+        Ok(result)
+    }
+}
+
+impl Encodable for u8 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.push(*self);
+    }
+}
+
+impl Decodable for u8 {
+    fn decode(reader: &mut Reader) -> Result<u8, SerializeError> {
+        Ok(reader.read_bytes(1)?[0])
+    }
+}
+
+impl Encodable for u16 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decodable for u16 {
+    fn decode(reader: &mut Reader) -> Result<u16, SerializeError> {
+        let bytes = reader.read_bytes(2)?;
+
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+impl Encodable for u32 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decodable for u32 {
+    fn decode(reader: &mut Reader) -> Result<u32, SerializeError> {
+        Ok(u32::from_le_bytes(reader.read_array_4()?))
+    }
+}
+
+impl Encodable for i32 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decodable for i32 {
+    fn decode(reader: &mut Reader) -> Result<i32, SerializeError> {
+        Ok(i32::from_le_bytes(reader.read_array_4()?))
+    }
+}
+
+impl Encodable for u64 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decodable for u64 {
+    fn decode(reader: &mut Reader) -> Result<u64, SerializeError> {
+        Ok(u64::from_le_bytes(reader.read_array_8()?))
+    }
+}
+
+impl Encodable for i64 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decodable for i64 {
+    fn decode(reader: &mut Reader) -> Result<i64, SerializeError> {
+        Ok(i64::from_le_bytes(reader.read_array_8()?))
+    }
+}
+
+impl<T: Encodable> Encodable for Vec<T> {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        write_compact_size(stream, self.len() as u64);
+
+        for item in self {
+            item.encode(stream);
+        }
+    }
+}
+
+impl<T: Decodable> Decodable for Vec<T> {
+    fn decode(reader: &mut Reader) -> Result<Vec<T>, SerializeError> {
+        let count = read_compact_size(reader)? as usize;
+
+        // Every item takes up at least a byte, so never trust the
+        // claimed count for the allocation beyond what is left to read
+        let mut result = Vec::with_capacity(count.min(reader.remaining()));
+
+        for _ in 0..count {
+            result.push(T::decode(reader)?);
+        }
+
+        Ok(result)
+    }
+}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Serializes a value into a fresh byte vector
+///
+/// ### Arguments
+///
+/// * `value`   - Value to serialize
+pub fn serialize<T: Encodable>(value: &T) -> Vec<u8> {
+    let mut stream = Vec::new();
+    value.encode(&mut stream);
+
+    stream
+}
+
+/// Deserializes a value, requiring that every input byte is consumed
+///
+/// ### Arguments
+///
+/// * `data`    - Bytes to deserialize
+pub fn deserialize<T: Decodable>(data: &[u8]) -> Result<T, SerializeError> {
+    let mut reader = Reader::new(data);
+    let value = T::decode(&mut reader)?;
+
+    match reader.remaining() {
+        0 => Ok(value),
+        n => Err(SerializeError::TrailingBytes(n))
+    }
+}
+
+/// Number of bytes a CompactSize encoding of the value takes up
+///
+/// ### Arguments
+///
+/// * `size`    - Value to be encoded
+pub fn compact_size_len(size: u64) -> usize {
+    match size {
+        0..=252 => 1,
+        253..=0xffff => 3,
+        0x10000..=0xffffffff => 5,
+        _ => 9
+    }
+}
+
+/// Writes a CompactSize varint to the stream
+///
+/// ### Arguments
+///
+/// * `stream`  - Byte stream to write to
+/// * `size`    - Value to encode
+pub fn write_compact_size(stream: &mut Vec<u8>, size: u64) {
+    match size {
+        0..=252 => stream.push(size as u8),
+        253..=0xffff => {
+            stream.push(0xfd);
+            (size as u16).encode(stream);
+        },
+        0x10000..=0xffffffff => {
+            stream.push(0xfe);
+            (size as u32).encode(stream);
+        },
+        _ => {
+            stream.push(0xff);
+            size.encode(stream);
+        }
+    }
+}
+
+/// Reads a CompactSize varint, rejecting non-canonical encodings and
+/// anything larger than MAX_SIZE
+///
+/// ### Arguments
+///
+/// * `reader`  - Reader to consume bytes from
+pub fn read_compact_size(reader: &mut Reader) -> Result<u64, SerializeError> {
+    let size = match u8::decode(reader)? {
+        0xfd => {
+            let size = u16::decode(reader)? as u64;
+
+            if size < 253 {
+                return Err(SerializeError::NonCanonicalCompactSize(size));
+            }
+
+            size
+        },
+        0xfe => {
+            let size = u32::decode(reader)? as u64;
+
+            if size < 0x10000 {
+                return Err(SerializeError::NonCanonicalCompactSize(size));
+            }
+
+            size
+        },
+        0xff => {
+            let size = u64::decode(reader)?;
+
+            if size < 0x100000000 {
+                return Err(SerializeError::NonCanonicalCompactSize(size));
+            }
+
+            size
+        },
+        n => n as u64
+    };
+
+    if size > MAX_SIZE {
+        return Err(SerializeError::OversizedCompactSize(size));
+    }
+
+    Ok(size)
+}
+
+/// Writes a length-prefixed byte vector to the stream
+///
+/// ### Arguments
+///
+/// * `stream`  - Byte stream to write to
+/// * `bytes`   - Bytes to write
+pub fn write_var_bytes(stream: &mut Vec<u8>, bytes: &[u8]) {
+    write_compact_size(stream, bytes.len() as u64);
+    stream.extend_from_slice(bytes);
+}
+
+/// Reads a length-prefixed byte vector
+///
+/// ### Arguments
+///
+/// * `reader`  - Reader to consume bytes from
+pub fn read_var_bytes(reader: &mut Reader) -> Result<Vec<u8>, SerializeError> {
+    let length = read_compact_size(reader)? as usize;
+
+    Ok(reader.read_bytes(length)?.to_vec())
+}
+
+
+#[cfg(test)]
+mod tests;
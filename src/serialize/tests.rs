@@ -0,0 +1,255 @@
+use super::*;
+use primitives::test_utils::sample_transaction;
+use primitives::transaction::{ Transaction, TxValidationError };
+use utils::amount::MAX_MONEY;
+
+
+/*---- HELPERS ----*/
+
+fn compact_size(size: u64) -> Vec<u8> {
+    let mut stream = Vec::new();
+    write_compact_size(&mut stream, size);
+
+    stream
+}
+
+fn read_compact(data: &[u8]) -> Result<u64, SerializeError> {
+    read_compact_size(&mut Reader::new(data))
+}
+
+fn witness_transaction() -> Transaction {
+    let mut tx = sample_transaction(1, 1);
+    tx.inputs_mut()[0].witness = vec![vec![1, 2, 3], Vec::new()];
+
+    tx
+}
+
+
+/*---- TESTS ----*/
+
+#[test]
+fn integers_round_trip() {
+    assert_eq!(serialize(&0x1234u16), vec![0x34, 0x12]);
+    assert_eq!(serialize(&0x12345678u32), vec![0x78, 0x56, 0x34, 0x12]);
+    assert_eq!(serialize(&-2i32), vec![0xfe, 0xff, 0xff, 0xff]);
+
+    assert_eq!(deserialize::<u8>(&serialize(&0xabu8)), Ok(0xab));
+    assert_eq!(deserialize::<u16>(&serialize(&0xbeefu16)), Ok(0xbeef));
+    assert_eq!(deserialize::<u32>(&serialize(&0xdeadbeefu32)), Ok(0xdeadbeef));
+    assert_eq!(deserialize::<i32>(&serialize(&i32::MIN)), Ok(i32::MIN));
+    assert_eq!(deserialize::<u64>(&serialize(&u64::MAX)), Ok(u64::MAX));
+    assert_eq!(deserialize::<i64>(&serialize(&-1i64)), Ok(-1));
+}
+
+#[test]
+fn compact_size_round_trip() {
+    let cases: [(u64, usize); 8] = [
+        (0, 1), (252, 1),
+        (253, 3), (0xffff, 3),
+        (0x10000, 5), (0xffffffff, 5),
+        (0x100000000, 9), (u64::MAX, 9)
+    ];
+
+    for &(size, len) in cases.iter() {
+        let encoded = compact_size(size);
+
+        assert_eq!(encoded.len(), len);
+        assert_eq!(compact_size_len(size), len);
+
+        // Sizes above MAX_SIZE encode fine but are refused on the way back in
+        match size > MAX_SIZE {
+            true => assert_eq!(read_compact(&encoded), Err(SerializeError::OversizedCompactSize(size))),
+            false => assert_eq!(read_compact(&encoded), Ok(size))
+        }
+    }
+
+    assert_eq!(compact_size(253), vec![0xfd, 0xfd, 0x00]);
+    assert_eq!(compact_size(0x10000), vec![0xfe, 0x00, 0x00, 0x01, 0x00]);
+}
+
+#[test]
+fn non_canonical_compact_size_is_rejected() {
+    assert_eq!(read_compact(&[0xfd, 0xfc, 0x00]), Err(SerializeError::NonCanonicalCompactSize(252)));
+    assert_eq!(read_compact(&[0xfd, 0x00, 0x00]), Err(SerializeError::NonCanonicalCompactSize(0)));
+    assert_eq!(read_compact(&[0xfe, 0xff, 0xff, 0x00, 0x00]), Err(SerializeError::NonCanonicalCompactSize(0xffff)));
+    assert_eq!(read_compact(&[0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]), Err(SerializeError::NonCanonicalCompactSize(0xffffffff)));
+}
+
+#[test]
+fn compact_size_above_max_size_is_rejected() {
+    assert_eq!(read_compact(&compact_size(MAX_SIZE)), Ok(MAX_SIZE));
+    assert_eq!(read_compact(&compact_size(MAX_SIZE + 1)), Err(SerializeError::OversizedCompactSize(MAX_SIZE + 1)));
+
+    // The length check happens before any of the claimed bytes are read
+    let mut data = compact_size(MAX_SIZE + 1);
+    data.extend_from_slice(&[0; 16]);
+
+    assert_eq!(deserialize::<Vec<u8>>(&data), Err(SerializeError::OversizedCompactSize(MAX_SIZE + 1)));
+}
+
+#[test]
+fn truncated_input_is_rejected() {
+    assert_eq!(deserialize::<u32>(&[1, 2, 3]), Err(SerializeError::UnexpectedEnd { needed: 4, remaining: 3 }));
+    assert_eq!(deserialize::<u64>(&[]), Err(SerializeError::UnexpectedEnd { needed: 8, remaining: 0 }));
+    assert_eq!(read_compact(&[0xfe, 0x00, 0x00]), Err(SerializeError::UnexpectedEnd { needed: 4, remaining: 2 }));
+
+    // A byte vector claiming more bytes than are left
+    let mut reader = Reader::new(&[3, 0xaa, 0xbb]);
+    assert_eq!(read_var_bytes(&mut reader), Err(SerializeError::UnexpectedEnd { needed: 3, remaining: 2 }));
+
+    // Every strict prefix of a transaction fails to decode
+    for tx in [sample_transaction(1, 1), witness_transaction()].iter() {
+        let encoded = serialize(tx);
+
+        for len in 0..encoded.len() {
+            assert!(deserialize::<Transaction>(&encoded[..len]).is_err(), "prefix of {} bytes decoded", len);
+        }
+    }
+}
+
+#[test]
+fn trailing_bytes_are_rejected() {
+    assert_eq!(deserialize::<u16>(&[1, 2, 3]), Err(SerializeError::TrailingBytes(1)));
+
+    let mut encoded = serialize(&sample_transaction(1, 1));
+    encoded.push(0);
+
+    assert_eq!(deserialize::<Transaction>(&encoded), Err(SerializeError::TrailingBytes(1)));
+}
+
+#[test]
+fn var_bytes_and_vectors_round_trip() {
+    let mut stream = Vec::new();
+    write_var_bytes(&mut stream, &[0xaa; 300]);
+
+    assert_eq!(&stream[..3], &[0xfd, 0x2c, 0x01]);
+    assert_eq!(read_var_bytes(&mut Reader::new(&stream)), Ok(vec![0xaa; 300]));
+
+    let values: Vec<u32> = vec![1, 2, 0xffffffff];
+    assert_eq!(serialize(&values).len(), 1 + 3 * 4);
+    assert_eq!(deserialize::<Vec<u32>>(&serialize(&values)), Ok(values));
+}
+
+#[test]
+fn transactions_round_trip() {
+    let legacy = sample_transaction(1, 1);
+    let encoded = serialize(&legacy);
+
+    assert_eq!(encoded.len(), legacy.serialized_size());
+    assert_eq!(deserialize::<Transaction>(&encoded), Ok(legacy.clone()));
+
+    // The witness format carries the marker and flag after the version
+    let segwit = witness_transaction();
+    let encoded = serialize(&segwit);
+
+    assert_eq!(&encoded[4..6], &[0x00, 0x01]);
+    assert_eq!(deserialize::<Transaction>(&encoded), Ok(segwit.clone()));
+    assert_eq!(segwit.txid(), legacy.txid());
+    assert!(segwit.wtxid() != legacy.wtxid());
+}
+
+#[test]
+fn witness_transaction_sizes() {
+    let tx = witness_transaction();
+
+    // version 4 + input count 1 + input 42 + output count 1 + output 11 + lock time 4
+    assert_eq!(tx.get_stripped_size(), 63);
+
+    // plus marker and flag 2, and the witness stack: item count 1, [1, 2, 3] 4, [] 1
+    assert_eq!(tx.get_total_size(), 71);
+    assert_eq!(serialize(&tx).len(), 71);
+
+    assert_eq!(tx.get_weight(), 63 * 3 + 71);
+    assert_eq!(tx.get_virtual_size(), 65);   // 260 / 4
+}
+
+#[test]
+fn non_witness_transaction_sizes() {
+    let tx = sample_transaction(1, 1);
+
+    assert_eq!(tx.get_stripped_size(), 63);
+    assert_eq!(tx.get_total_size(), 63);
+    assert_eq!(tx.get_weight(), 4 * 63);
+    assert_eq!(tx.get_virtual_size(), 63);
+
+    // Weights that aren't a multiple of 4 round the virtual size up
+    let mut tx = witness_transaction();
+    tx.inputs_mut()[0].witness.push(vec![4]);
+
+    assert_eq!(tx.get_weight(), 63 * 3 + 73);
+    assert_eq!(tx.get_virtual_size(), 66);   // ceil(262 / 4)
+}
+
+#[test]
+fn cached_ids_follow_mutation() {
+    let mut tx = witness_transaction();
+    let (txid, wtxid) = (tx.txid(), tx.wtxid());
+
+    // Witness changes only move the wtxid
+    tx.inputs_mut()[0].witness.push(vec![4]);
+    assert_eq!(tx.txid(), txid);
+    assert!(tx.wtxid() != wtxid);
+
+    tx.set_lock_time(501);
+    assert!(tx.txid() != txid);
+
+    // Whatever the cache holds must match a freshly decoded copy
+    let decoded = deserialize::<Transaction>(&serialize(&tx)).unwrap();
+    assert_eq!(tx.txid(), decoded.txid());
+    assert_eq!(tx.wtxid(), decoded.wtxid());
+
+    tx.outputs_mut().clear();
+    tx.set_version(1);
+    let decoded = deserialize::<Transaction>(&serialize(&tx)).unwrap();
+    assert_eq!(tx.txid(), decoded.txid());
+    assert_eq!(tx, decoded);
+}
+
+#[test]
+fn null_fields_round_trip() {
+    let mut tx = sample_transaction(1, 1);
+    tx.inputs_mut()[0].previous_out = None;
+    tx.outputs_mut()[0].value = None;
+
+    let encoded = serialize(&tx);
+
+    assert_eq!(&encoded[5..37], &[0; 32]);
+    assert_eq!(&encoded[37..41], &[0xff; 4]);
+    assert_eq!(deserialize::<Transaction>(&encoded), Ok(tx));
+}
+
+#[test]
+fn txout_value_edges_round_trip() {
+    let mut tx = sample_transaction(1, 1);
+
+    for &value in [0, MAX_MONEY, i64::MAX as u64].iter() {
+        tx.outputs_mut()[0].value = Some(value);
+        assert_eq!(deserialize::<Transaction>(&serialize(&tx)), Ok(tx.clone()));
+    }
+
+    // Values that don't fit in an i64 are capped instead of wrapping to -1,
+    // which would come back as a null output
+    tx.outputs_mut()[0].value = Some(u64::MAX);
+    let decoded = deserialize::<Transaction>(&serialize(&tx)).unwrap();
+
+    assert_eq!(decoded.outputs()[0].value, Some(i64::MAX as u64));
+    assert_eq!(tx.get_output_value(), Err(TxValidationError::OutputValueOutOfRange { index: 0, value: u64::MAX }));
+}
+
+#[test]
+fn invalid_transaction_data_is_rejected() {
+    // Negative output values other than -1
+    let mut encoded = serialize(&sample_transaction(1, 1));
+    let value_start = encoded.len() - 4 - 3 - 8;
+    encoded[value_start..value_start + 8].copy_from_slice(&(-2i64).to_le_bytes());
+
+    assert_eq!(deserialize::<Transaction>(&encoded), Err(SerializeError::InvalidValue("negative txout value")));
+
+    // A witness flag with no witness items
+    let mut encoded = serialize(&witness_transaction());
+    let witness_start = encoded.len() - 4 - 6;
+    encoded.truncate(witness_start);
+    encoded.extend_from_slice(&[0, 100, 0, 0, 0]);
+
+    assert_eq!(deserialize::<Transaction>(&encoded), Err(SerializeError::InvalidValue("superfluous witness record")));
+}
@@ -1,4 +1,4 @@
-/** 
+/* 
  * SATOSHI NAKAMOTO (BITCOIN TEAM):
  * 
  * No amount larger than this (in satoshi) is valid. (i.e. total number of satoshis)
@@ -11,7 +11,7 @@
  * for the creation of coins out of thin air modification could lead to a fork.
  */
 
-/**
+/*
  * BYRON HOUWENS:
  * 
  * If the intent is to divide the number of whole coins based on a separate 
@@ -19,9 +19,252 @@
  * and MAX_MONEY = 21000000 * COINS) are directly from the Bitcoin protocol.
  */
 
+use std::fmt;
+use std::error::Error;
+use std::str::FromStr;
+
 pub const COINS: u64 = 100000000; // whole coins
-const MAX_MONEY: u64 = 21000000 * COINS;
+pub const MAX_MONEY: u64 = 21000000 * COINS;
+
+/// Number of decimal places in a whole coin
+const COIN_DECIMALS: usize = 8;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// A non-negative amount of money in satoshis, always within [0, MAX_MONEY].
+/// All arithmetic is checked, so an Amount can never overflow or leave the
+/// valid money range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+/// A signed amount of money in satoshis, within [-MAX_MONEY, MAX_MONEY].
+/// Used for fees and balance deltas.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SignedAmount(i64);
+
+/// Errors when constructing or parsing an amount
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AmountError {
+    OutOfRange,         // Outside of the valid money range
+    Negative,           // Negative value where only non-negative amounts are allowed
+    InvalidFormat,      // Not a decimal coin string
+    TooPrecise          // More than 8 decimal places
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const MAX: Amount = Amount(MAX_MONEY);
+
+    /// Creates an amount from a number of satoshis
+    ///
+    /// ### Arguments
+    ///
+    /// * `satoshis`    - Number of satoshis
+    pub fn from_sat(satoshis: u64) -> Result<Amount, AmountError> {
+        match is_valid_amount(&satoshis) {
+            true => Ok(Amount(satoshis)),
+            false => Err(AmountError::OutOfRange)
+        }
+    }
+
+    /// Number of satoshis in this amount
+    pub fn as_sat(&self) -> u64 {
+        self.0
+    }
+
+    /// Adds two amounts, returning None if the result leaves the money range
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Amount to add
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).and_then(|sum| Amount::from_sat(sum).ok())
+    }
+
+    /// Subtracts an amount, returning None if the result would be negative
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Amount to subtract
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    /// Multiplies by a whole number, returning None if the result leaves
+    /// the money range
+    ///
+    /// ### Arguments
+    ///
+    /// * `factor`  - Number to multiply by
+    pub fn checked_mul(self, factor: u64) -> Option<Amount> {
+        self.0.checked_mul(factor).and_then(|product| Amount::from_sat(product).ok())
+    }
+
+    /// Converts into a signed amount. Always succeeds, since the money
+    /// range is the same either side of zero
+    pub fn to_signed(self) -> SignedAmount {
+        SignedAmount(self.0 as i64)
+    }
+}
+
+impl SignedAmount {
+    pub const ZERO: SignedAmount = SignedAmount(0);
+
+    /// Creates a signed amount from a number of satoshis
+    ///
+    /// ### Arguments
+    ///
+    /// * `satoshis`    - Number of satoshis, possibly negative
+    pub fn from_sat(satoshis: i64) -> Result<SignedAmount, AmountError> {
+        match satoshis.checked_abs() {
+            Some(abs) if abs as u64 <= MAX_MONEY => Ok(SignedAmount(satoshis)),
+            _ => Err(AmountError::OutOfRange)
+        }
+    }
+
+    /// Number of satoshis in this amount
+    pub fn as_sat(&self) -> i64 {
+        self.0
+    }
+
+    /// Whether this amount is below zero
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    /// Absolute value of this amount
+    pub fn abs(self) -> Amount {
+        Amount(self.0.unsigned_abs())
+    }
+
+    /// Adds two amounts, returning None if the result leaves the money range
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Amount to add
+    pub fn checked_add(self, other: SignedAmount) -> Option<SignedAmount> {
+        self.0.checked_add(other.0).and_then(|sum| SignedAmount::from_sat(sum).ok())
+    }
+
+    /// Subtracts an amount, returning None if the result leaves the money range
+    ///
+    /// ### Arguments
+    ///
+    /// * `other`   - Amount to subtract
+    pub fn checked_sub(self, other: SignedAmount) -> Option<SignedAmount> {
+        self.0.checked_sub(other.0).and_then(|difference| SignedAmount::from_sat(difference).ok())
+    }
+
+    /// Multiplies by a whole number, returning None if the result leaves
+    /// the money range
+    ///
+    /// ### Arguments
+    ///
+    /// * `factor`  - Number to multiply by
+    pub fn checked_mul(self, factor: i64) -> Option<SignedAmount> {
+        self.0.checked_mul(factor).and_then(|product| SignedAmount::from_sat(product).ok())
+    }
+
+    /// Converts into an unsigned amount, failing if this amount is negative
+    pub fn to_unsigned(self) -> Result<Amount, AmountError> {
+        match self.is_negative() {
+            true => Err(AmountError::Negative),
+            false => Ok(Amount(self.0 as u64))
+        }
+    }
+}
+
+/// Formats as a decimal coin string with all 8 decimal places, eg. "0.00012345"
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:08}", self.0 / COINS, self.0 % COINS)
+    }
+}
+
+impl fmt::Display for SignedAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.is_negative() {
+            true => write!(f, "-{}", self.abs()),
+            false => write!(f, "{}", self.abs())
+        }
+    }
+}
+
+/// Parses a decimal coin string such as "21", "0.5" or "0.00012345".
+/// Parsing is exact: more than 8 decimal places is an error, never rounded.
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    fn from_str(s: &str) -> Result<Amount, AmountError> {
+        if s.starts_with('-') {
+            return Err(AmountError::Negative);
+        }
+
+        let (whole, fraction) = match s.find('.') {
+            Some(point) => (&s[..point], &s[point + 1..]),
+            None => (s, "")
+        };
 
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(AmountError::InvalidFormat);
+        }
+
+        if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+            return Err(AmountError::InvalidFormat);
+        }
+
+        if fraction.len() > COIN_DECIMALS {
+            return Err(AmountError::TooPrecise);
+        }
+
+        let mut satoshis: u64 = 0;
+
+        // Pad the fraction out to 8 digits so every digit is a whole satoshi
+        let padded_fraction = format!("{:0<width$}", fraction, width = COIN_DECIMALS);
+
+        for c in whole.chars().chain(padded_fraction.chars()) {
+            let digit = c.to_digit(10).unwrap() as u64;
+
+            satoshis = match satoshis.checked_mul(10).and_then(|s| s.checked_add(digit)) {
+                Some(s) => s,
+                None => return Err(AmountError::OutOfRange)
+            };
+        }
+
+        Amount::from_sat(satoshis)
+    }
+}
+
+impl FromStr for SignedAmount {
+    type Err = AmountError;
+
+    fn from_str(s: &str) -> Result<SignedAmount, AmountError> {
+        match s.starts_with('-') {
+            true => Ok(SignedAmount(-(s[1..].parse::<Amount>()?.0 as i64))),
+            false => Ok(s.parse::<Amount>()?.to_signed())
+        }
+    }
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::OutOfRange => write!(f, "Amount out of range"),
+            AmountError::Negative => write!(f, "Amount is negative"),
+            AmountError::InvalidFormat => write!(f, "Invalid amount format"),
+            AmountError::TooPrecise => write!(f, "Amount has more than {} decimal places", COIN_DECIMALS)
+        }
+    }
+}
+
+impl Error for AmountError {}
+
+
+/*---- FUNCTIONS ----*/
 
 /// Checks whether the passed number of satoshis is valid
 /// given the system parameters
@@ -30,5 +273,91 @@ const MAX_MONEY: u64 = 21000000 * COINS;
 /// 
 /// * `value`   - Value to check
 pub fn is_valid_amount(value: &u64) -> bool {
-    value >= &0 && value <= &MAX_MONEY
-}
\ No newline at end of file
+    *value <= MAX_MONEY
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(satoshis: u64) -> Amount {
+        Amount::from_sat(satoshis).unwrap()
+    }
+
+    fn signed(satoshis: i64) -> SignedAmount {
+        SignedAmount::from_sat(satoshis).unwrap()
+    }
+
+    #[test]
+    fn money_range() {
+        assert_eq!(Amount::from_sat(MAX_MONEY), Ok(Amount::MAX));
+        assert_eq!(Amount::from_sat(MAX_MONEY + 1), Err(AmountError::OutOfRange));
+        assert_eq!(Amount::from_sat(u64::MAX), Err(AmountError::OutOfRange));
+
+        assert_eq!(SignedAmount::from_sat(-(MAX_MONEY as i64)).map(|a| a.abs()), Ok(Amount::MAX));
+        assert_eq!(SignedAmount::from_sat(MAX_MONEY as i64 + 1), Err(AmountError::OutOfRange));
+        assert_eq!(SignedAmount::from_sat(i64::MIN), Err(AmountError::OutOfRange));
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(amount(1).checked_add(amount(2)), Some(amount(3)));
+        assert_eq!(Amount::MAX.checked_add(amount(1)), None);
+        assert_eq!(amount(1).checked_sub(amount(2)), None);
+        assert_eq!(amount(3).checked_sub(amount(2)), Some(amount(1)));
+        assert_eq!(amount(COINS).checked_mul(21000000), Some(Amount::MAX));
+        assert_eq!(amount(COINS).checked_mul(21000001), None);
+        assert_eq!(amount(2).checked_mul(u64::MAX), None);
+
+        assert_eq!(signed(1).checked_sub(signed(2)), Some(signed(-1)));
+        assert_eq!(signed(-(MAX_MONEY as i64)).checked_sub(signed(1)), None);
+        assert_eq!(Amount::MAX.to_signed().checked_add(signed(1)), None);
+        assert_eq!(signed(-2).checked_mul(3), Some(signed(-6)));
+        assert_eq!(signed(-2).checked_mul(i64::MAX), None);
+
+        assert_eq!(signed(-5).to_unsigned(), Err(AmountError::Negative));
+        assert_eq!(signed(5).to_unsigned(), Ok(amount(5)));
+    }
+
+    #[test]
+    fn parsing_is_exact() {
+        assert_eq!("21".parse::<Amount>(), Ok(amount(21 * COINS)));
+        assert_eq!("0.5".parse::<Amount>(), Ok(amount(COINS / 2)));
+        assert_eq!(".5".parse::<Amount>(), Ok(amount(COINS / 2)));
+        assert_eq!("1.".parse::<Amount>(), Ok(amount(COINS)));
+        assert_eq!("0.00000001".parse::<Amount>(), Ok(amount(1)));
+        assert_eq!("21000000".parse::<Amount>(), Ok(Amount::MAX));
+        assert_eq!("-0.5".parse::<SignedAmount>(), Ok(signed(-(COINS as i64) / 2)));
+    }
+
+    #[test]
+    fn bad_strings_are_rejected() {
+        assert_eq!("0.000000001".parse::<Amount>(), Err(AmountError::TooPrecise));
+        assert_eq!("-1".parse::<Amount>(), Err(AmountError::Negative));
+        assert_eq!("--1".parse::<SignedAmount>(), Err(AmountError::Negative));
+        assert_eq!("21000000.00000001".parse::<Amount>(), Err(AmountError::OutOfRange));
+        assert_eq!("99999999999999999999".parse::<Amount>(), Err(AmountError::OutOfRange));
+
+        for s in ["", ".", "1.2.3", "1e5", "+1", " 1", "0x10", "1,5"].iter() {
+            assert_eq!(s.parse::<Amount>(), Err(AmountError::InvalidFormat), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for &satoshis in [0, 1, 12345, COINS, COINS + 1, MAX_MONEY].iter() {
+            let a = amount(satoshis);
+
+            assert_eq!(a.to_string().parse::<Amount>(), Ok(a));
+            assert_eq!(a.to_signed().to_string().parse::<SignedAmount>(), Ok(a.to_signed()));
+
+            let negative = signed(-(satoshis as i64));
+            assert_eq!(negative.to_string().parse::<SignedAmount>(), Ok(negative));
+        }
+
+        assert_eq!(amount(12345).to_string(), "0.00012345");
+        assert_eq!(Amount::MAX.to_string(), "21000000.00000000");
+        assert_eq!(signed(-(COINS as i64) - 1).to_string(), "-1.00000001");
+    }
+}
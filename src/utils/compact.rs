@@ -1,4 +1,4 @@
-/**
+/*
  * The "compact" format is a representation of a whole
  * number N using an unsigned (signed?) 32bit number similar to a
  * floating point format.
@@ -19,33 +19,33 @@
  * implementation accident.
  */
 
-use ramp::Int;
+use bigint::BigInt;
 
 
-/// Creates a "compact" format version of a Int.
+/// Creates a "compact" format version of a BigInt.
 /// 
 /// ### Arguments
 /// 
 /// * `bits`        - Bit size
 /// * `negative`    - Whether the resulting value is negative
 /// * `overflow`    - Whether the resulting value is compact
-pub fn set_compact(bits: &u32, negative: &mut bool, overflow: &mut bool) -> Int {
+pub fn set_compact(bits: &u32, negative: &mut bool, overflow: &mut bool) -> BigInt {
     let size = bits >> 24;
     let mut word = bits & 0x007fffff;
-    let mut compact_result: Int;
+    let mut compact_result: BigInt;
 
-    if size <= 3 as u32 {
-        word >>= 8 as u32 * (3 as u32 - size);
-        compact_result = Int::from(word);
+    if size <= 3 {
+        word >>= 8 * (3 - size);
+        compact_result = BigInt::from(word);
     } else {
-        compact_result = Int::from(word);
-        compact_result <<= (8 as u32 * (size - 3 as u32)) as usize;
+        compact_result = BigInt::from(word);
+        compact_result <<= (8 * (size - 3)) as usize;
     }
 
     *negative = word != 0 && (bits & 0x00800000) != 0;
-    *overflow =  word != 0 && ((size > 34 as u32) ||
-                 (word > 0xff && size > 33 as u32) ||
-                 (word > 0xffff && size > 32 as u32));
+    *overflow =  word != 0 && ((size > 34) ||
+                 (word > 0xff && size > 33) ||
+                 (word > 0xffff && size > 32));
 
     compact_result
 }
@@ -0,0 +1,182 @@
+/*
+ * A fixed-size 256-bit hash, used for block hashes, txids and outpoints.
+ *
+ * As in original Bitcoin, the bytes are stored in the order they come out of
+ * the hash function (and go onto the wire), but are displayed as hex in
+ * reversed byte order. That way block hashes read as a big-endian number
+ * with their leading zeroes up front.
+ */
+
+use std::fmt;
+use bigint::BigInt;
+use num_traits::Num;
+use utils::hex::{ HexError, to_hex, from_hex };
+use serialize::{ Encodable, Decodable, Reader, SerializeError };
+
+
+/*---- STRUCTS ----*/
+
+/// 256-bit hash. Ordering is byte-wise over the stored bytes, which matches
+/// Bitcoin's uint256 comparison. Use `to_int` for numeric comparisons.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hash256([u8; 32]);
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl Hash256 {
+
+    /// Returns the all-zero hash
+    pub fn zero() -> Hash256 {
+        Hash256([0; 32])
+    }
+
+    /// Whether every byte of the hash is zero
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|b| *b == 0)
+    }
+
+    /// Builds a hash from its raw (internal order) bytes
+    ///
+    /// ### Arguments
+    ///
+    /// * `bytes`   - Raw hash bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Hash256 {
+        Hash256(bytes)
+    }
+
+    /// Builds a hash from a slice of raw bytes, if it is exactly 32 bytes long
+    ///
+    /// ### Arguments
+    ///
+    /// * `bytes`   - Raw hash bytes
+    pub fn from_slice(bytes: &[u8]) -> Option<Hash256> {
+        if bytes.len() != 32 {
+            return None;
+        }
+
+        let mut hash = [0; 32];
+        hash.copy_from_slice(bytes);
+
+        Some(Hash256(hash))
+    }
+
+    /// Parses a hash from its display (reversed byte order) hex form
+    ///
+    /// ### Arguments
+    ///
+    /// * `hex`     - 64 character hex string
+    pub fn from_hex(hex: &str) -> Result<Hash256, HexError> {
+        let mut bytes = from_hex(hex)?;
+        bytes.reverse();
+
+        match Hash256::from_slice(&bytes) {
+            Some(hash) => Ok(hash),
+            None => Err(HexError::InvalidLength(bytes.len()))
+        }
+    }
+
+    /// Raw (internal order) bytes of the hash
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Interprets the hash as a 256-bit little-endian number, for
+    /// proof-of-work and chain work arithmetic
+    pub fn to_int(&self) -> BigInt {
+        // Display order is big-endian, so it parses straight into a number
+        BigInt::from_str_radix(&self.to_string(), 16).unwrap()
+    }
+}
+
+impl AsRef<[u8]> for Hash256 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut reversed = self.0;
+        reversed.reverse();
+
+        write!(f, "{}", to_hex(&reversed))
+    }
+}
+
+impl fmt::Debug for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hash256({})", self)
+    }
+}
+
+impl Encodable for Hash256 {
+    fn encode(&self, stream: &mut Vec<u8>) {
+        stream.extend_from_slice(&self.0);
+    }
+}
+
+impl Decodable for Hash256 {
+    fn decode(reader: &mut Reader) -> Result<Hash256, SerializeError> {
+        // read_bytes guarantees the length, so we're safe to unwrap
+        Ok(Hash256::from_slice(reader.read_bytes(32)?).unwrap())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_is_displayed_in_reversed_byte_order() {
+        let mut bytes = [0; 32];
+        bytes[0] = 0x01;
+        bytes[31] = 0xab;
+
+        let hash = Hash256::from_bytes(bytes);
+        let hex = "ab00000000000000000000000000000000000000000000000000000000000001";
+
+        assert_eq!(hash.to_string(), hex);
+        assert_eq!(Hash256::from_hex(hex), Ok(hash));
+        assert_eq!(Hash256::from_hex(&hex.to_uppercase()), Ok(hash));
+        assert_eq!(format!("{:?}", hash), format!("Hash256({})", hex));
+    }
+
+    #[test]
+    fn bad_hex_is_rejected() {
+        assert_eq!(Hash256::from_hex(""), Err(HexError::InvalidLength(0)));
+        assert_eq!(Hash256::from_hex(&"00".repeat(31)), Err(HexError::InvalidLength(31)));
+        assert_eq!(Hash256::from_hex(&"00".repeat(33)), Err(HexError::InvalidLength(33)));
+        assert_eq!(Hash256::from_hex(&"0".repeat(63)), Err(HexError::OddLength(63)));
+        assert_eq!(Hash256::from_hex(&format!("{}zz", "00".repeat(31))), Err(HexError::InvalidCharacter('z')));
+
+        assert_eq!(Hash256::from_slice(&[0; 31]), None);
+        assert_eq!(Hash256::from_slice(&[0; 32]), Some(Hash256::zero()));
+    }
+
+    #[test]
+    fn to_int_reads_the_display_order() {
+        assert_eq!(Hash256::zero().to_int(), BigInt::from(0));
+
+        // The first stored byte is the least significant
+        let mut bytes = [0; 32];
+        bytes[0] = 0x01;
+        assert_eq!(Hash256::from_bytes(bytes).to_int(), BigInt::from(1));
+
+        bytes[1] = 0x02;
+        assert_eq!(Hash256::from_bytes(bytes).to_int(), BigInt::from(0x0201));
+
+        let max = Hash256::from_bytes([0xff; 32]).to_int();
+        assert_eq!(max, BigInt::from_str_radix(&"f".repeat(64), 16).unwrap());
+
+        // Byte-wise ordering isn't numeric ordering
+        let mut low = [0; 32];
+        low[0] = 0x02;
+        let mut high = [0; 32];
+        high[31] = 0x01;
+
+        assert!(Hash256::from_bytes(low) > Hash256::from_bytes(high));
+        assert!(Hash256::from_bytes(low).to_int() < Hash256::from_bytes(high).to_int());
+    }
+}
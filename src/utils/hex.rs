@@ -0,0 +1,80 @@
+/*
+ * Plain hex encoding and decoding of byte strings
+ */
+
+use std::fmt;
+use std::error::Error;
+
+
+/*---- STRUCTS + ENUMS ----*/
+
+/// Errors that can occur when decoding a hex string
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HexError {
+    OddLength(usize),           // Hex strings must describe whole bytes
+    InvalidCharacter(char),     // Character outside of [0-9a-fA-F]
+    InvalidLength(usize)        // Decoded to the wrong number of bytes for the target type
+}
+
+
+/*---- IMPLEMENTATIONS ----*/
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HexError::OddLength(len) => write!(f, "Hex string has odd length {}", len),
+            HexError::InvalidCharacter(c) => write!(f, "Invalid hex character '{}'", c),
+            HexError::InvalidLength(len) => write!(f, "Hex string decodes to an unexpected {} bytes", len)
+        }
+    }
+}
+
+impl Error for HexError {}
+
+
+/*---- FUNCTIONS ----*/
+
+/// Encodes bytes as a lowercase hex string
+///
+/// ### Arguments
+///
+/// * `bytes`   - Bytes to encode
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string (either case) into bytes
+///
+/// ### Arguments
+///
+/// * `hex`     - Hex string to decode
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, HexError> {
+    let chars: Vec<char> = hex.chars().collect();
+
+    if !chars.len().is_multiple_of(2) {
+        return Err(HexError::OddLength(chars.len()));
+    }
+
+    let mut bytes = Vec::with_capacity(chars.len() / 2);
+
+    for pair in chars.chunks(2) {
+        let high = hex_digit(pair[0])?;
+        let low = hex_digit(pair[1])?;
+
+        bytes.push(high << 4 | low);
+    }
+
+    Ok(bytes)
+}
+
+/// Value of a single hex digit
+///
+/// ### Arguments
+///
+/// * `c`       - Character to convert
+fn hex_digit(c: char) -> Result<u8, HexError> {
+    match c.to_digit(16) {
+        Some(d) => Ok(d as u8),
+        None => Err(HexError::InvalidCharacter(c))
+    }
+}
@@ -1,13 +1,18 @@
-/**
+/*
  * Contains a bunch of basic utils and constants for use
  * throughout the blockchain
  */
 
 pub mod amount;
 pub mod compact;
+pub mod hex;
+pub mod hash256;
 
 
 /*---- CONSTANTS ----*/
 
 /// Timestamp phrase to prove time of creation (original Bitcoin)
-pub const PSZ_TIMESTAMP: &'static str = "The Times 03/Jan/2009 Chancellor on brink of second bailout for banks";
\ No newline at end of file
+pub const PSZ_TIMESTAMP: &str = "The Times 03/Jan/2009 Chancellor on brink of second bailout for banks";
+
+/// Public key the genesis reward is paid to, as hex (original Bitcoin)
+pub const GENESIS_OUTPUT_KEY: &str = "04678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5f";
\ No newline at end of file